@@ -1,4 +1,7 @@
-use cold::{link::link, opt::parse_opts};
+use cold::{
+    link::{link, DEFAULT_SCRIPT},
+    opt::{help_text, parse_opts, version_banner},
+};
 use tracing::info;
 
 fn main() -> anyhow::Result<()> {
@@ -12,6 +15,36 @@ fn main() -> anyhow::Result<()> {
 
     info!("Parsed options: {opt:?}");
 
+    if opt.help {
+        // --help: print options and exit
+        print!("{}", help_text());
+        return Ok(());
+    }
+    if opt.version {
+        // --version: print version and exit
+        println!("{}", version_banner());
+        return Ok(());
+    }
+    if opt.verbose {
+        // --verbose: print version and the default linker script, continue
+        // linking if there are input files
+        println!("{}", version_banner());
+        println!("using internal linker script:");
+        println!("==================================================");
+        print!("{}", DEFAULT_SCRIPT);
+        println!("==================================================");
+        if opt.obj_file.is_empty() {
+            return Ok(());
+        }
+    }
+    if opt.print_version {
+        // -v: print version and continue linking
+        println!("{}", version_banner());
+        if opt.obj_file.is_empty() {
+            return Ok(());
+        }
+    }
+
     link(&opt)?;
     Ok(())
 }
@@ -1,9 +1,31 @@
-use crate::opt::{FileOpt, ObjectFileOpt, Opt};
+use crate::opt::{BuildId, Discard, FileOpt, ObjectFileOpt, Opt, OrphanHandling, Symbolic};
+use crate::script::{
+    glob_match, memory_function, parse_script, Assignment, AssignmentLocation, InputCommand,
+    Insert, MemoryRegion, OutputSectionDescription, Script,
+};
 use anyhow::{anyhow, bail, Context};
+use md5::Md5;
 use object::elf::{
-    ProgramHeader64, Sym64, DF_1_PIE, DT_FLAGS_1, DT_JMPREL, DT_NEEDED, DT_PLTGOT, DT_PLTREL,
-    DT_PLTRELSZ, DT_RELA, R_X86_64_JUMP_SLOT,
+    DF_1_GLOBAL, DF_1_INITFIRST, DF_1_NODELETE, DF_1_NOOPEN, DF_1_NOW, DF_1_ORIGIN, DF_1_PIE,
+    DF_BIND_NOW, DF_ORIGIN, DF_STATIC_TLS, DF_SYMBOLIC, DF_TEXTREL, DT_BIND_NOW, DT_DEBUG, DT_FINI,
+    DT_FINI_ARRAY, DT_FINI_ARRAYSZ, DT_FLAGS, DT_FLAGS_1, DT_INIT, DT_INIT_ARRAY, DT_INIT_ARRAYSZ,
+    DT_JMPREL, DT_NEEDED, DT_PLTGOT, DT_PLTREL, DT_PLTRELSZ, DT_PREINIT_ARRAY, DT_PREINIT_ARRAYSZ,
+    DT_RELA, DT_RELACOUNT, DT_RELAENT, DT_RELASZ, DT_RPATH, DT_RUNPATH, DT_TEXTREL, DT_VERNEED,
+    DT_VERNEEDNUM, DT_VERSYM, ELF_NOTE_GNU, GNU_PROPERTY_UINT32_AND_HI, GNU_PROPERTY_UINT32_AND_LO,
+    GNU_PROPERTY_UINT32_OR_HI, GNU_PROPERTY_UINT32_OR_LO, GNU_PROPERTY_X86_FEATURE_1_AND,
+    GNU_PROPERTY_X86_FEATURE_1_IBT, GNU_PROPERTY_X86_UINT32_AND_HI, GNU_PROPERTY_X86_UINT32_AND_LO,
+    GNU_PROPERTY_X86_UINT32_OR_AND_HI, GNU_PROPERTY_X86_UINT32_OR_AND_LO,
+    GNU_PROPERTY_X86_UINT32_OR_HI, GNU_PROPERTY_X86_UINT32_OR_LO, NT_GNU_BUILD_ID,
+    NT_GNU_PROPERTY_TYPE_0, R_X86_64_64, R_X86_64_COPY, R_X86_64_DTPMOD64, R_X86_64_DTPOFF32,
+    R_X86_64_DTPOFF64, R_X86_64_GLOB_DAT, R_X86_64_GOT64, R_X86_64_GOTOFF64, R_X86_64_GOTPC32,
+    R_X86_64_GOTPC32_TLSDESC, R_X86_64_GOTPC64, R_X86_64_GOTPCREL64, R_X86_64_GOTPCRELX,
+    R_X86_64_GOTPLT64, R_X86_64_GOTTPOFF, R_X86_64_IRELATIVE, R_X86_64_JUMP_SLOT, R_X86_64_NONE,
+    R_X86_64_PC32, R_X86_64_PC64, R_X86_64_PLTOFF64, R_X86_64_RELATIVE, R_X86_64_REX_GOTPCRELX,
+    R_X86_64_SIZE32, R_X86_64_SIZE64, R_X86_64_TLSDESC, R_X86_64_TLSDESC_CALL, R_X86_64_TLSGD,
+    R_X86_64_TLSLD, R_X86_64_TPOFF32, R_X86_64_TPOFF64, STT_FUNC, STT_GNU_IFUNC, STT_NOTYPE,
+    STT_OBJECT, STT_TLS, STV_DEFAULT, STV_HIDDEN, STV_INTERNAL, STV_PROTECTED,
 };
+use object::read::elf::{Dyn, ElfFile, SectionHeader as _, Sym as _};
 use object::write::elf::*;
 use object::{
     elf::{DT_GNU_HASH, DT_HASH, DT_NULL, DT_SONAME, DT_STRSZ, DT_STRTAB, DT_SYMENT, DT_SYMTAB},
@@ -11,10 +33,18 @@ use object::{
         elf::{SectionIndex, Writer},
         StringId,
     },
-    Object, ObjectSection, ObjectSymbol,
+    Object, ObjectSection, ObjectSymbol, SymbolFlags,
+};
+use object::{Endianness, ObjectKind};
+use sha1::{Digest, Sha1};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Read,
+    ops::Range,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::LazyLock,
 };
-use object::{LittleEndian, ObjectKind};
-use std::{collections::BTreeMap, os::unix::fs::PermissionsExt, path::PathBuf};
 use tracing::{info, info_span, warn};
 use typed_arena::Arena;
 
@@ -30,13 +60,137 @@ fn lookup_file(name: &str, paths: &Vec<String>) -> anyhow::Result<PathBuf> {
     Err(anyhow!("File {name} cannot be found"))
 }
 
+/// Lookup libNAME.so.VERSION with the highest version in the first search path
+/// having one, for systems without the development symlink libNAME.so
+fn lookup_versioned_library(name: &str, paths: &Vec<String>) -> anyhow::Result<PathBuf> {
+    for path in paths {
+        let Ok(entries) = std::fs::read_dir(path) else {
+            continue;
+        };
+        let mut versions = vec![];
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let Some(version) = file_name
+                .strip_prefix(name)
+                .and_then(|suffix| suffix.strip_prefix('.'))
+            else {
+                continue;
+            };
+            // numeric components only, e.g. 3 or 1.10.2
+            let Ok(version) = version
+                .split('.')
+                .map(|part| part.parse::<u64>())
+                .collect::<Result<Vec<u64>, _>>()
+            else {
+                continue;
+            };
+            if entry.path().is_file() {
+                versions.push((version, entry.path()));
+            }
+        }
+        if let Some((_, path)) = versions.into_iter().max() {
+            eprintln!(
+                "warning: {name} is not found, using {} instead, is the development package missing?",
+                path.display()
+            );
+            return Ok(path);
+        }
+    }
+    Err(anyhow!("File {name} cannot be found"))
+}
+
+/// Parse glibc ld.so.cache into library file names and paths, keeping
+/// x86-64 entries only
+fn parse_ld_so_cache(data: &[u8]) -> Option<BTreeMap<String, String>> {
+    let read_u32 = |offset: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(
+            data.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    };
+    // the new format follows the entries of the old format, if present
+    let mut header = 0;
+    if data.starts_with(b"ld.so-1.7.0") {
+        let nlibs = read_u32(12)? as usize;
+        header = (16 + nlibs * 12).next_multiple_of(8);
+    }
+    if !data.get(header..)?.starts_with(b"glibc-ld.so.cache1.1") {
+        return None;
+    }
+    // strings are relative to the new format header
+    let read_string = |offset: u32| -> Option<String> {
+        let start = header + offset as usize;
+        let len = data.get(start..)?.iter().position(|b| *b == 0)?;
+        Some(String::from_utf8_lossy(&data[start..start + len]).to_string())
+    };
+    let nlibs = read_u32(header + 20)? as usize;
+    let mut entries = BTreeMap::new();
+    for i in 0..nlibs {
+        // flags, key, value, osversion and hwcap of 24 bytes
+        let entry = header + 48 + i * 24;
+        // FLAG_ELF_LIBC6 | FLAG_X8664_LIB64
+        if read_u32(entry)? != 0x0303 {
+            continue;
+        }
+        let key = read_string(read_u32(entry + 4)?)?;
+        let value = read_string(read_u32(entry + 8)?)?;
+        // the first entry wins, like ld.so
+        entries.entry(key).or_insert(value);
+    }
+    Some(entries)
+}
+
+static LD_SO_CACHE: LazyLock<BTreeMap<String, String>> = LazyLock::new(|| {
+    std::fs::read("/etc/ld.so.cache")
+        .ok()
+        .and_then(|data| parse_ld_so_cache(&data))
+        .unwrap_or_default()
+});
+
+/// Lookup library in the ld.so cache of the host, after search paths, unless
+/// linking against a sysroot
+fn lookup_ld_so_cache(name: &str, sysroot: Option<&str>) -> anyhow::Result<PathBuf> {
+    if sysroot.is_none() {
+        if let Some(path) = LD_SO_CACHE.get(name) {
+            info!("File {name} is found at {path} in ld.so cache");
+            return Ok(PathBuf::from(path));
+        }
+    }
+    Err(anyhow!("File {name} cannot be found"))
+}
+
+/// Resolve path beginning with = or $SYSROOT relative to sysroot
+fn sysroot_path(path: &str, sysroot: Option<&str>) -> String {
+    match path.strip_prefix('=').or(path.strip_prefix("$SYSROOT")) {
+        Some(rest) => format!("{}{}", sysroot.unwrap_or_default(), rest),
+        None => path.to_string(),
+    }
+}
+
 /// Resolve library namespec to paths
 pub fn path_resolution(opt: &Opt) -> anyhow::Result<Opt> {
     // resolve library to actual files
     let mut opt = opt.clone();
+
+    // apply sysroot to search paths
+    let sysroot = opt.sysroot.clone();
+    for path in opt.search_dir.iter_mut().chain(opt.rpath_link.iter_mut()) {
+        *path = sysroot_path(path, sysroot.as_deref());
+    }
+
     for obj_file in &mut opt.obj_file {
         // convert ObjectFileOpt::Library to ObjectFileOpt::File
         if let ObjectFileOpt::Library(lib) = obj_file {
+            if lib.verbatim {
+                // -l:filename: lookup file name as is
+                let path = lookup_file(&lib.name, &opt.search_dir)
+                    .or_else(|_| lookup_ld_so_cache(&lib.name, sysroot.as_deref()))?;
+                *obj_file = ObjectFileOpt::File(FileOpt {
+                    name: format!("{}", path.display()),
+                    as_needed: lib.as_needed,
+                });
+                continue;
+            }
+
             if !lib.link_static {
                 // lookup dynamic library first
                 let path = format!("lib{}.so", lib.name);
@@ -51,7 +205,15 @@ pub fn path_resolution(opt: &Opt) -> anyhow::Result<Opt> {
 
             // lookup static library
             let path = format!("lib{}.a", lib.name);
-            let path = lookup_file(&path, &opt.search_dir)?;
+            let path = match lookup_file(&path, &opt.search_dir) {
+                Err(_) if !lib.link_static => {
+                    // shared library in ld.so cache, or with version suffix
+                    let path = format!("lib{}.so", lib.name);
+                    lookup_ld_so_cache(&path, sysroot.as_deref())
+                        .or_else(|_| lookup_versioned_library(&path, &opt.search_dir))?
+                }
+                res => res?,
+            };
             *obj_file = ObjectFileOpt::File(FileOpt {
                 name: format!("{}", path.display()),
                 as_needed: lib.as_needed,
@@ -59,627 +221,6119 @@ pub fn path_resolution(opt: &Opt) -> anyhow::Result<Opt> {
             continue;
         }
     }
+
+    // --separate-debug-file defaults to OUTPUT.dbg
+    if let (Some(file), Some(output)) = (&mut opt.separate_debug_file, &opt.output) {
+        if file.is_empty() {
+            *file = format!("{}.dbg", output);
+        }
+    }
     Ok(opt)
 }
 
-#[derive(Debug, Clone)]
-pub struct ObjectFile {
-    pub name: String,
-    /// --as-needed
-    pub as_needed: bool,
-    pub content: Vec<u8>,
+/// Read an input file, text files are linker scripts whose INPUT and GROUP
+/// files are read in place, e.g. libc.so of glibc
+fn read_input_file(
+    opt: &Opt,
+    name: &str,
+    as_needed: bool,
+    in_group: bool,
+    files: &mut Vec<ObjectFile>,
+    groups: &mut Vec<Range<usize>>,
+    script: &mut Script,
+) -> anyhow::Result<()> {
+    info!("Reading {}", name);
+    let content = std::fs::read(name).context(format!("Reading file {}", name))?;
+    let is_binary = content.starts_with(&object::elf::ELFMAG)
+        || content.starts_with(&object::archive::MAGIC)
+        || content.starts_with(&object::archive::THIN_MAGIC);
+    let text = match std::str::from_utf8(&content) {
+        Ok(text) if !is_binary => text,
+        _ => {
+            files.push(ObjectFile {
+                name: name.to_string(),
+                as_needed,
+                just_symbols: false,
+                content,
+            });
+            return Ok(());
+        }
+    };
+    info!("Reading {} as linker script", name);
+    let mut parsed = parse_script(name, text)?;
+    let inputs = std::mem::take(&mut parsed.inputs);
+    script.extend(parsed);
+    read_script_inputs(
+        opt, name, inputs, as_needed, in_group, files, groups, script,
+    )
 }
 
-// we want our own Relocation & RelocationTarget struct for easier handling
-#[derive(Debug)]
-pub enum RelocationTarget {
-    // relocation against section with additional offset
-    Section((String, u64)),
-    // relocation against symbol
-    Symbol(String),
+/// Read files of INPUT and GROUP commands of a linker script, a GROUP is
+/// searched repeatedly unless it is in a group already
+#[allow(clippy::too_many_arguments)]
+fn read_script_inputs(
+    opt: &Opt,
+    script_name: &str,
+    inputs: Vec<InputCommand>,
+    as_needed: bool,
+    in_group: bool,
+    files: &mut Vec<ObjectFile>,
+    groups: &mut Vec<Range<usize>>,
+    script: &mut Script,
+) -> anyhow::Result<()> {
+    for input in inputs {
+        let start = files.len();
+        for file in input.files {
+            let path = script_input_path(opt, script_name, &file.name)?;
+            read_input_file(
+                opt,
+                &path,
+                as_needed || file.as_needed,
+                in_group || input.group,
+                files,
+                groups,
+                script,
+            )?;
+        }
+        if input.group && !in_group {
+            groups.push(start..files.len());
+        }
+    }
+    Ok(())
 }
 
-#[derive(Debug)]
-pub struct Relocation {
-    // offset into the output section
-    offset: u64,
-    kind: object::RelocationKind,
-    encoding: object::RelocationEncoding,
-    size: u8,
-    addend: i64,
-    target: RelocationTarget,
+/// Path of a file in INPUT or GROUP of a linker script: -lname is searched
+/// like -l, absolute paths of scripts in sysroot are relative to sysroot,
+/// other files are looked up in the current directory, then search paths
+fn script_input_path(opt: &Opt, script_name: &str, name: &str) -> anyhow::Result<String> {
+    if let Some(lib) = name.strip_prefix("-l") {
+        let path = lookup_file(&format!("lib{}.so", lib), &opt.search_dir)
+            .or_else(|_| lookup_file(&format!("lib{}.a", lib), &opt.search_dir))
+            .or_else(|_| lookup_ld_so_cache(&format!("lib{}.so", lib), opt.sysroot.as_deref()))?;
+        return Ok(format!("{}", path.display()));
+    }
+    if name.starts_with('/') {
+        return Ok(match &opt.sysroot {
+            Some(sysroot) if Path::new(script_name).starts_with(sysroot) => {
+                format!("{}{}", sysroot, name)
+            }
+            _ => name.to_string(),
+        });
+    }
+    if name.starts_with('=') || name.starts_with("$SYSROOT") {
+        return Ok(sysroot_path(name, opt.sysroot.as_deref()));
+    }
+    if Path::new(name).is_file() {
+        return Ok(name.to_string());
+    }
+    Ok(format!("{}", lookup_file(name, &opt.search_dir)?.display()))
 }
 
-#[derive(Debug)]
-pub struct Symbol {
-    // reside in which section
-    section_name: String,
-    // offset into the output section
-    offset: u64,
-    // indices in output .strtab
-    symbol_name_string_id: Option<StringId>,
-    // indices in output .dynstr
-    symbol_name_dynamic_string_id: Option<StringId>,
-    // local or global
-    is_global: bool,
-    // a plt symbol to dynamic library
-    is_plt: bool,
+/// Whether local symbol should be discarded from .symtab according to -x/-X
+fn discard_local(opt: &Opt, name: &str) -> bool {
+    match opt.discard {
+        Discard::None => false,
+        Discard::Locals => name.starts_with(".L"),
+        Discard::All => true,
+    }
 }
 
-#[derive(Debug, Clone)]
-pub struct DynamicSymbol {
-    name: String,
+/// STV_* visibility of ELF symbol
+fn symbol_visibility<'data>(symbol: &impl ObjectSymbol<'data>) -> u8 {
+    match symbol.flags() {
+        SymbolFlags::Elf { st_other, .. } => st_other & 0x3,
+        _ => STV_DEFAULT,
+    }
 }
 
-#[derive(Default, Debug)]
-pub struct OutputSection {
-    pub name: String,
-    pub content: Vec<u8>,
-    // offset from ELF load address
-    pub offset: u64,
-    // relocations in this section
-    pub relocations: Vec<Relocation>,
-    pub is_executable: bool,
-    pub is_writable: bool,
-    pub is_bss: bool,
-    // indices in output ELF
-    pub section_index: Option<SectionIndex>,
-    pub name_string_id: Option<StringId>,
+/// STT_* type of ELF symbol
+fn symbol_type<'data>(symbol: &impl ObjectSymbol<'data>) -> u8 {
+    match symbol.flags() {
+        SymbolFlags::Elf { st_info, .. } => st_info & 0xf,
+        _ => STT_NOTYPE,
+    }
 }
 
-#[derive(Default, Debug)]
-pub struct OutputRelocationSection {
-    pub relocations: Vec<Rel>,
-    // offset from ELF load address
-    pub offset: u64,
-    // indices in output ELF
-    pub name_string_id: Option<StringId>,
+/// The most constraining visibility: internal, hidden, protected, then default
+fn merge_visibility(a: u8, b: u8) -> u8 {
+    let rank = |visibility| match visibility {
+        STV_INTERNAL => 3,
+        STV_HIDDEN => 2,
+        STV_PROTECTED => 1,
+        _ => 0,
+    };
+    if rank(a) >= rank(b) {
+        a
+    } else {
+        b
+    }
 }
 
-#[derive(Default, Debug)]
-pub struct Needed {
-    pub name: String,
-    // indices in output ELF
-    pub name_string_id: Option<StringId>,
+/// Index of symbol version in .gnu.version, 0 and 1 are reserved for local and global
+fn version_index(version_needs: &[VersionNeed], version: &Option<(String, String)>) -> u16 {
+    let Some((file, version)) = version else {
+        return object::elf::VER_NDX_GLOBAL;
+    };
+    let mut index = object::elf::VER_NDX_GLOBAL + 1;
+    for need in version_needs {
+        for name in &need.versions {
+            if need.file == *file && name == version {
+                return index;
+            }
+            index += 1;
+        }
+    }
+    unreachable!("Version {version} of {file} is not recorded")
 }
 
-struct Linker<'a> {
-    opt: Opt,
-    files: Vec<ObjectFile>,
+/// Whether a definition in shared library can be preempted at runtime,
+/// so references to it must go through the dynamic linker
+fn is_preemptible(opt: &Opt, symbol: &Symbol) -> bool {
+    opt.shared
+        && symbol.is_global_binding()
+        && !symbol.is_plt
+        && symbol.visibility != STV_PROTECTED
+        && symbol.section_name != ABSOLUTE_SECTION
+        && match opt.symbolic {
+            Symbolic::None => true,
+            Symbolic::Functions => !symbol.is_function(),
+            Symbolic::All => false,
+        }
+}
 
-    // section name => section
-    output_sections: BTreeMap<String, OutputSection>,
+/// Append a plt entry jumping through the .got entry of the symbol, which is
+/// allocated with other .got entries, returns the offset of the entry in
+/// .plt.got
+fn add_plt_got_entry(
+    output_sections: &mut BTreeMap<String, OutputSection>,
+    name: &str,
+    ibt: bool,
+) -> u64 {
+    let plt_got = output_sections
+        .entry(".plt.got".to_string())
+        .or_insert_with(|| OutputSection {
+            name: ".plt.got".to_string(),
+            is_executable: true,
+            alignment: 8,
+            ..OutputSection::default()
+        });
+    let offset = plt_got.content.len() as u64;
+    let jmp_offset = if ibt {
+        plt_got.content.extend(vec![
+            // f3 0f 1e fa       endbr64
+            0xf3, 0x0f, 0x1e, 0xfa, // ff 25 xx xx xx xx jmp *name@GOTPCREL(%rip)
+            0xff, 0x25, 0x00, 0x00, 0x00, 0x00,
+            // 66 0f 1f 44 00 00 nopw 0x0(%rax,%rax,1)
+            0x66, 0x0f, 0x1f, 0x44, 0x00, 0x00,
+        ]);
+        4
+    } else {
+        plt_got.content.extend(vec![
+            // ff 25 xx xx xx xx jmp *name@GOTPCREL(%rip)
+            0xff, 0x25, 0x00, 0x00, 0x00, 0x00, // 66 90             xchg %ax,%ax
+            0x66, 0x90,
+        ]);
+        0
+    };
+    plt_got.relocations.push(Relocation {
+        offset: offset + jmp_offset + 2,
+        kind: object::RelocationKind::GotRelative,
+        encoding: object::RelocationEncoding::Generic,
+        size: 32,
+        r_type: R_X86_64_NONE,
+        addend: -4,
+        target: RelocationTarget::Symbol(name.to_string()),
+    });
+    offset
+}
 
-    // symbol table: symbol name => symbol
-    symbols: BTreeMap<String, Symbol>,
+/// Append a lazy plt entry with its .got.plt slot and R_X86_64_JUMP_SLOT
+/// relocation against dynamic symbol, returns the section and offset of the
+/// entry to call: with IBT, .plt only holds the lazy binding code, while
+/// calls go to the .plt.sec entry
+fn add_plt_entry(
+    format: ElfFormat,
+    output_sections: &mut BTreeMap<String, OutputSection>,
+    output_relocations: &mut BTreeMap<String, OutputRelocationSection>,
+    symbol_index: u32,
+    ibt: bool,
+) -> (&'static str, u64) {
+    let plt = output_sections.get_mut(".plt").unwrap();
+    let plt_offset = plt.content.len() as u64;
+    // index into .rela.plt
+    let idx = (plt_offset / 16 - 1) as usize;
+    // each got entry: 8 bytes
+    // 24: got header
+    let got_addend = (idx as i64 * 8 + 24) - 4;
 
-    // dynamic symbols are saved in two parts:
-    // plt dynamic symbols that are UNDEF
-    plt_dynamic_symbols: Vec<DynamicSymbol>,
-    // other defined dynamic symbols, sorted by hash bucket
-    dynamic_symbols: Vec<DynamicSymbol>,
+    // offsets of lazy binding code and of jmp plt_first_entry operand
+    let (lazy_offset, jmp_offset) = if ibt {
+        // each entry in plt:
+        // f3 0f 1e fa       endbr64
+        plt.content.extend(vec![0xf3, 0x0f, 0x1e, 0xfa]);
+        // 68 xx xx xx xx    push index
+        plt.content.push(0x68);
+        plt.content.extend_from_slice(&format.u32_bytes(idx as u32));
+        // e9 xx xx xx xx    jmp plt_first_entry
+        plt.content.extend(vec![0xe9, 0x00, 0x00, 0x00, 0x00]);
+        // 66 90             xchg %ax,%ax
+        plt.content.extend(vec![0x66, 0x90]);
+        // the .got.plt slot points to endbr64 at the start
+        (0, 10)
+    } else {
+        // each entry in plt:
+        // ff 25 xx xx xx xx jmp *.got.plt+yy(%rip)
+        plt.content.extend(vec![0xff, 0x25, 0x00, 0x00, 0x00, 0x00]);
+        // 68 xx xx xx xx    push index
+        plt.content.push(0x68);
+        plt.content.extend_from_slice(&format.u32_bytes(idx as u32));
+        // e9 xx xx xx xx    jmp plt_first_entry
+        plt.content.extend(vec![0xe9, 0x00, 0x00, 0x00, 0x00]);
 
-    // section address => offset
-    section_address: BTreeMap<String, u64>,
+        // relocation for jmp *.got.plt+yy(%rip)
+        plt.relocations.push(Relocation {
+            offset: 0x2 + plt_offset,
+            kind: object::RelocationKind::Relative,
+            encoding: object::RelocationEncoding::Generic,
+            size: 32,
+            r_type: R_X86_64_NONE,
+            addend: got_addend,
+            target: RelocationTarget::Section((".got.plt".to_string(), 0)),
+        });
+        (6, 12)
+    };
+    // relocation for jmp plt_first_entry
+    plt.relocations.push(Relocation {
+        offset: jmp_offset + plt_offset,
+        kind: object::RelocationKind::Relative,
+        encoding: object::RelocationEncoding::Generic,
+        size: 32,
+        r_type: R_X86_64_NONE,
+        addend: 0 - 4,
+        target: RelocationTarget::Section((".plt".to_string(), 0)),
+    });
 
-    // elf writer
-    writer: Writer<'a>,
+    let entry = if ibt {
+        let plt_sec = output_sections
+            .entry(".plt.sec".to_string())
+            .or_insert_with(|| OutputSection {
+                name: ".plt.sec".to_string(),
+                is_executable: true,
+                alignment: 16,
+                ..OutputSection::default()
+            });
+        let sec_offset = plt_sec.content.len() as u64;
+        plt_sec.content.extend(vec![
+            // f3 0f 1e fa       endbr64
+            0xf3, 0x0f, 0x1e, 0xfa, // ff 25 xx xx xx xx jmp *.got.plt+yy(%rip)
+            0xff, 0x25, 0x00, 0x00, 0x00, 0x00,
+            // 66 0f 1f 44 00 00 nopw 0x0(%rax,%rax,1)
+            0x66, 0x0f, 0x1f, 0x44, 0x00, 0x00,
+        ]);
+        // relocation for jmp *.got.plt+yy(%rip)
+        plt_sec.relocations.push(Relocation {
+            offset: sec_offset + 6,
+            kind: object::RelocationKind::Relative,
+            encoding: object::RelocationEncoding::Generic,
+            size: 32,
+            r_type: R_X86_64_NONE,
+            addend: got_addend,
+            target: RelocationTarget::Section((".got.plt".to_string(), 0)),
+        });
+        (".plt.sec", sec_offset)
+    } else {
+        (".plt", plt_offset)
+    };
 
-    load_address: u64,
+    // add entry in .got.plt
+    let got_plt = output_sections.get_mut(".got.plt").unwrap();
+    let got_offset = got_plt.content.len() as u64;
+    // 8 bytes for absolute address
+    got_plt.content.extend(vec![0; 8]);
 
-    // dynamic, dynsym, dynstr, hash, gnu_hash
-    dynamic_section_index: SectionIndex,
-    dynamic_section_offset: u64,
-    dynsym_section_index: SectionIndex,
-    dynsym_section_offset: u64,
-    dynstr_section_offset: u64,
-    hash_section_offset: u64,
-    gnu_hash_section_offset: u64,
-    dynamic_entries_count: usize,
-    soname_dynamic_string_index: Option<StringId>,
+    // static relocation to the lazy binding code in plt in binary
+    got_plt.relocations.push(Relocation {
+        offset: got_offset,
+        kind: object::RelocationKind::Absolute,
+        encoding: object::RelocationEncoding::Generic,
+        size: 64,
+        r_type: R_X86_64_NONE,
+        addend: (plt_offset + lazy_offset) as i64,
+        target: RelocationTarget::Section((".plt".to_string(), 0)),
+    });
 
-    // program header offset & len
-    phdr_offset: usize,
-    phdr_len: usize,
+    // add dynamic relocation R_X86_64_JUMP_SLOT to actual symbol
+    output_relocations
+        .entry(".rela.plt".to_string())
+        .or_default()
+        .relocations
+        .push(DynamicRelocation {
+            section_name: ".got.plt".to_string(),
+            offset: got_offset,
+            symbol_index,
+            r_type: R_X86_64_JUMP_SLOT,
+            addend: 0,
+            target: None,
+        });
 
-    // dynamically link against shared libraries
-    dynamic_link: bool,
-    needed: Vec<Needed>,
+    entry
+}
 
-    // output relocations
-    output_relocations: BTreeMap<String, OutputRelocationSection>,
+/// Whether section name can be used in __start_SECTION and __stop_SECTION
+fn is_c_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
-impl<'a> Linker<'a> {
-    fn link(opt: &Opt) -> anyhow::Result<()> {
-        info!("Link with options: {opt:?}");
+/// Symbols locating the ELF header, text, data and the end of memory image, like GNU ld
+const LAYOUT_SYMBOLS: &[&str] = &[
+    "__ehdr_start",
+    "__executable_start",
+    "etext",
+    "_etext",
+    "__etext",
+    "edata",
+    "_edata",
+    "__bss_start",
+    "end",
+    "_end",
+];
 
-        let opt = path_resolution(opt)?;
-        info!("Options after path resolution: {opt:?}");
+/// Default linker script, printed by --verbose: well-known output sections
+/// in the order of the default GNU ld script, -ffunction-sections and
+/// -fdata-sections variants are merged, legacy constructors and destructors
+/// are converted, other sections are orphans
+pub const DEFAULT_SCRIPT: &str = "SECTIONS
+{
+  .interp : { *(.interp) }
+  .note.gnu.property : { *(.note.gnu.property) }
+  .note.gnu.build-id : { *(.note.gnu.build-id) }
+  .init : { KEEP (*(.init)) }
+  .plt : { *(.plt) }
+  .plt.got : { *(.plt.got) }
+  .plt.sec : { *(.plt.sec) }
+  .iplt : { *(.iplt) }
+  .text : { *(.text .text.*) }
+  .fini : { KEEP (*(.fini)) }
+  .rodata : { *(.rodata .rodata.*) }
+  .eh_frame_hdr : { *(.eh_frame_hdr) }
+  .eh_frame : { KEEP (*(.eh_frame)) }
+  .gcc_except_table : { *(.gcc_except_table) }
+  .tdata : { *(.tdata .tdata.*) }
+  .tbss : { *(.tbss .tbss.*) }
+  .preinit_array : { KEEP (*(.preinit_array)) }
+  .init_array : { KEEP (*(.init_array .init_array.* .ctors .ctors.*)) }
+  .fini_array : { KEEP (*(.fini_array .fini_array.* .dtors .dtors.*)) }
+  .data.rel.ro : { *(.data.rel.ro .data.rel.ro.*) }
+  .got : { *(.got) }
+  .got.plt : { *(.got.plt) }
+  .igot.plt : { *(.igot.plt) }
+  .data : { *(.data .data.*) }
+  .bss : { *(.bss .bss.*) }
+  .lrodata : { *(.lrodata .lrodata.*) }
+  .ldata : { *(.ldata .ldata.*) }
+  .lbss : { *(.lbss .lbss.*) }
+}
+";
 
-        let mut arena = Arena::new();
-        let mut buffer = vec![];
-        let mut linker = Linker {
-            opt,
-            files: vec![],
-            output_sections: BTreeMap::new(),
-            symbols: BTreeMap::new(),
-            section_address: BTreeMap::new(),
-            writer: Writer::new(object::Endianness::Little, true, &mut buffer),
-            load_address: 0,
-            dynamic_section_index: SectionIndex(0),
-            dynamic_section_offset: 0,
-            dynamic_entries_count: 0,
-            dynsym_section_index: SectionIndex(0),
-            dynsym_section_offset: 0,
-            dynstr_section_offset: 0,
-            hash_section_offset: 0,
-            gnu_hash_section_offset: 0,
-            soname_dynamic_string_index: None,
-            phdr_offset: 0,
-            phdr_len: 0,
-            dynamic_link: false,
-            needed: vec![],
-            output_relocations: BTreeMap::new(),
-            dynamic_symbols: vec![],
-            plt_dynamic_symbols: vec![],
-        };
-        linker.read_files()?;
-        linker.parse_files()?;
-        linker.generate_plt()?;
-        linker.reserve(&mut arena)?;
-        linker.relocate()?;
-        linker.write()?;
+/// Parsed DEFAULT_SCRIPT
+static DEFAULT_LAYOUT: LazyLock<Script> =
+    LazyLock::new(|| parse_script("default linker script", DEFAULT_SCRIPT).unwrap());
 
-        // done, save to file
-        let output = linker.opt.output.as_ref().unwrap();
-        info!("Writing to executable {}", output);
-        std::fs::write(output, buffer)?;
+/// Position of an output section in the default linker script
+fn default_position(name: &str) -> Option<usize> {
+    let name = unique_section_name(name);
+    DEFAULT_LAYOUT
+        .sections
+        .iter()
+        .position(|section| section.name == name)
+}
 
-        // make executable
-        let mut perms = std::fs::metadata(output)?.permissions();
-        perms.set_mode(0o755);
-        std::fs::set_permissions(output, perms)?;
+/// Key of an output section created by --unique, which shares the name of
+/// other output sections, keys of the same name sort in creation order
+fn unique_section_key(name: &str, index: usize) -> String {
+    format!("{}\0{:08}", name, index)
+}
 
-        Ok(())
+/// Name of an output section from its key, see unique_section_key()
+fn unique_section_name(key: &str) -> &str {
+    key.split('\0').next().unwrap()
+}
+
+/// Group of an output section in memory: .interp, notes, code, read-only
+/// data, data that is read-only after relocation, data and zero-filled data
+fn section_group(name: &str, output_section: &OutputSection) -> u32 {
+    if name == ".interp" {
+        0
+    } else if name.starts_with(".note") || output_section.is_note {
+        1
+    } else if output_section.is_executable {
+        2
+    } else if !output_section.is_writable && !output_section.is_tls {
+        3
+    } else if output_section.is_tls
+        || matches!(
+            name,
+            ".preinit_array" | ".init_array" | ".fini_array" | ".data.rel.ro" | ".got"
+        )
+    {
+        4
+    } else if !output_section.is_bss {
+        5
+    } else {
+        6
     }
+}
 
-    fn read_files(&mut self) -> anyhow::Result<()> {
-        let Linker { opt, files, .. } = self;
+/// Well-known section that an orphan section follows, chosen by its flags
+/// like GNU ld
+fn orphan_anchor(name: &str, output_section: &OutputSection) -> &'static str {
+    match section_group(name, output_section) {
+        0 | 1 => ".note.gnu.build-id",
+        2 => ".text",
+        3 => ".rodata",
+        4 if output_section.is_bss => ".tbss",
+        4 => ".tdata",
+        5 => ".data",
+        _ => ".bss",
+    }
+}
 
-        // read files
-        for obj_file in &opt.obj_file {
-            match obj_file {
-                ObjectFileOpt::File(file_opt) => {
-                    info!("Reading {}", file_opt.name);
-                    files.push(ObjectFile {
-                        name: file_opt.name.clone(),
-                        as_needed: file_opt.as_needed,
-                        content: std::fs::read(&file_opt.name)
-                            .context(format!("Reading file {}", file_opt.name))?,
-                    });
-                }
-                ObjectFileOpt::Library(_) => unreachable!("Path resolution is not working"),
-                ObjectFileOpt::StartGroup => warn!("--start-group unhandled"),
-                ObjectFileOpt::EndGroup => warn!("--end-group unhandled"),
-            }
+/// Order of output sections in memory, file and section header table:
+/// by group, then as listed in linker scripts, then like the default linker
+/// script, so that .bss comes last and .tbss follows .tdata in the TLS
+/// template; sections of INSERT go before or after their targets, orphans
+/// by name after their anchors
+fn layout_order(output_sections: &BTreeMap<String, OutputSection>) -> Vec<String> {
+    let mut names: Vec<String> = output_sections.keys().cloned().collect();
+    names.sort_by_key(|name| {
+        let output_section = &output_sections[name];
+        let script_order = output_section.script_order.unwrap_or(usize::MAX);
+        // (whether in the default layout, position in it, before or after it)
+        let (in_default, position, place) = match (&output_section.insert, default_position(name)) {
+            (Some(Insert::Before(target)), _) => (true, default_position(target), 0),
+            (Some(Insert::After(target)), _) => (true, default_position(target), 2),
+            (None, _) if output_section.script_order.is_some() => (false, None, 0),
+            (None, Some(position)) => (true, Some(position), 1),
+            (None, None) => (
+                true,
+                default_position(orphan_anchor(name, output_section)),
+                3,
+            ),
+        };
+        (
+            section_group(name, output_section),
+            in_default,
+            position,
+            place,
+            script_order,
+        )
+    });
+    names
+}
+
+/// Build ID of the output according to --build-id
+fn compute_build_id(style: &BuildId, output: &[u8]) -> anyhow::Result<Vec<u8>> {
+    Ok(match style {
+        BuildId::Sha1 => Sha1::digest(output).to_vec(),
+        BuildId::Md5 => Md5::digest(output).to_vec(),
+        BuildId::Uuid => {
+            // random UUID of version 4
+            let mut uuid = [0; 16];
+            std::fs::File::open("/dev/urandom")?.read_exact(&mut uuid)?;
+            uuid[6] = (uuid[6] & 0x0f) | 0x40;
+            uuid[8] = (uuid[8] & 0x3f) | 0x80;
+            uuid.to_vec()
         }
+        BuildId::Hex(bytes) => bytes.clone(),
+    })
+}
 
-        Ok(())
+/// CRC32 of .gnu_debuglink, as computed by zlib
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb88320 & (crc & 1).wrapping_neg());
+        }
     }
+    !crc
+}
 
-    fn parse_files(&mut self) -> anyhow::Result<()> {
-        let Linker {
-            opt,
-            files,
-            output_sections,
-            symbols,
-            dynamic_symbols,
-            plt_dynamic_symbols,
-            ..
-        } = self;
+/// Debug file of --separate-debug-file like objcopy --only-keep-debug: the
+/// debug sections, with headers of the loaded sections of the output at the
+/// same addresses but without content except notes, keeping the build ID
+fn write_debug_file(
+    format: ElfFormat,
+    output: &[u8],
+    debug_sections: &BTreeMap<String, OutputSection>,
+) -> anyhow::Result<Vec<u8>> {
+    if format.class.is_64 {
+        write_debug_file_of::<object::elf::FileHeader64<Endianness>>(format, output, debug_sections)
+    } else {
+        write_debug_file_of::<object::elf::FileHeader32<Endianness>>(format, output, debug_sections)
+    }
+}
 
-        // parse files and resolve symbols
-        let mut objs = vec![];
-        for file in files {
-            info!("Parsing {}", file.name);
-            if file.name.ends_with(".a") {
-                // archive
-                let ar = object::read::archive::ArchiveFile::parse(file.content.as_slice())
-                    .context(format!("Parsing file {} as archive", file.name))?;
-                for member in ar.members() {
-                    let member = member?;
-                    let name = format!("{}({})", file.name, std::str::from_utf8(member.name())?);
-                    info!("Parsing {}", name);
-                    let obj = object::File::parse(member.data(file.content.as_slice())?)
-                        .context(format!("Parsing file {} as object", name))?;
-                    objs.push((name, obj));
-                }
-            } else {
-                // object
-                let obj = object::File::parse(file.content.as_slice())
-                    .context(format!("Parsing file {} as object", file.name))?;
-                objs.push((file.name.clone(), obj));
-            }
+/// Debug file of output parsed with the file header type of its class
+fn write_debug_file_of<Elf: object::read::elf::FileHeader<Endian = Endianness>>(
+    format: ElfFormat,
+    output: &[u8],
+    debug_sections: &BTreeMap<String, OutputSection>,
+) -> anyhow::Result<Vec<u8>> {
+    let elf = ElfFile::<Elf>::parse(output)?;
+    let endian = elf.endian();
+    let header = elf.elf_header();
+    let loaded: Vec<_> = elf
+        .elf_section_table()
+        .iter()
+        .filter(|section| (section.sh_flags(endian).into() as u32) & object::elf::SHF_ALLOC != 0)
+        .collect();
+
+    let mut buffer = vec![];
+    let mut writer = format.writer(&mut buffer);
+    writer.reserve_file_header();
+    writer.reserve_null_section_index();
+    let mut name_ids = vec![];
+    let mut offsets = vec![];
+    for section in &loaded {
+        name_ids
+            .push(writer.add_section_name(elf.elf_section_table().section_name(endian, section)?));
+        writer.reserve_section_index();
+        offsets.push(if section.sh_type(endian) == object::elf::SHT_NOTE {
+            writer.reserve(
+                section.sh_size(endian).into() as usize,
+                section.sh_addralign(endian).into().max(1) as usize,
+            )
+        } else {
+            0
+        });
+    }
+    let mut debug_ids = vec![];
+    for (name, section) in debug_sections {
+        debug_ids.push(writer.add_section_name(name.as_bytes()));
+        writer.reserve_section_index();
+        offsets.push(writer.reserve(section.content.len(), section.alignment.max(1) as usize));
+    }
+    let _shstrtab_section_index = writer.reserve_shstrtab_section_index();
+    writer.reserve_section_headers();
+    writer.reserve_shstrtab();
+
+    writer.write_file_header(&FileHeader {
+        os_abi: header.e_ident().os_abi,
+        abi_version: header.e_ident().abi_version,
+        e_type: header.e_type(endian),
+        e_machine: header.e_machine(endian),
+        e_entry: header.e_entry(endian).into(),
+        e_flags: header.e_flags(endian),
+    })?;
+    for (section, offset) in loaded.iter().zip(&offsets) {
+        if section.sh_type(endian) == object::elf::SHT_NOTE {
+            writer.pad_until(*offset);
+            writer.write(section.data(endian, output)?);
         }
+    }
+    for (section, offset) in debug_sections.values().zip(&offsets[loaded.len()..]) {
+        writer.pad_until(*offset);
+        writer.write(&section.content);
+    }
 
-        for (name, obj) in objs {
-            let _span = info_span!("file", name).entered();
-            match obj {
-                object::File::Elf64(elf) => {
-                    if elf.kind() == ObjectKind::Dynamic {
-                        // linked against dynamic library
-                        self.dynamic_link = true;
-                        self.needed.push(Needed {
-                            name: name.clone(),
-                            name_string_id: None,
-                        });
+    writer.write_null_section_header();
+    for ((section, name), offset) in loaded.iter().zip(name_ids).zip(&offsets) {
+        let is_note = section.sh_type(endian) == object::elf::SHT_NOTE;
+        writer.write_section_header(&SectionHeader {
+            name: Some(name),
+            sh_type: if is_note {
+                object::elf::SHT_NOTE
+            } else {
+                object::elf::SHT_NOBITS
+            },
+            sh_flags: section.sh_flags(endian).into(),
+            sh_addr: section.sh_addr(endian).into(),
+            sh_offset: *offset as u64,
+            sh_size: section.sh_size(endian).into(),
+            sh_link: 0,
+            sh_info: 0,
+            sh_addralign: section.sh_addralign(endian).into(),
+            sh_entsize: section.sh_entsize(endian).into(),
+        });
+    }
+    for ((section, name), offset) in debug_sections
+        .values()
+        .zip(debug_ids)
+        .zip(&offsets[loaded.len()..])
+    {
+        writer.write_section_header(&SectionHeader {
+            name: Some(name),
+            sh_type: object::elf::SHT_PROGBITS,
+            sh_flags: if section.is_strings {
+                (object::elf::SHF_MERGE | object::elf::SHF_STRINGS) as u64
+            } else {
+                0
+            },
+            sh_addr: 0,
+            sh_offset: *offset as u64,
+            sh_size: section.content.len() as u64,
+            sh_link: 0,
+            sh_info: 0,
+            sh_addralign: section.alignment.max(1),
+            sh_entsize: section.is_strings as u64,
+        });
+    }
+    writer.write_shstrtab_section_header();
+    writer.write_shstrtab();
+    debug_assert_eq!(writer.reserved_len(), writer.len());
+    Ok(buffer)
+}
 
-                        // walk through its dynamic symbols
-                        for symbol in elf.dynamic_symbols() {
-                            if !symbol.is_undefined() {
-                                let name = symbol.name()?;
-                                info!("Defining dynamic symbol {}", name);
-                                plt_dynamic_symbols.push(DynamicSymbol {
-                                    name: name.to_string(),
-                                });
-                            }
-                        }
-                        continue;
-                    }
+/// Output sections in file order, split into those before the tables of
+/// the dynamic linker, those before .dynamic and the rest
+fn layout_parts(output_sections: &BTreeMap<String, OutputSection>) -> [Vec<String>; 3] {
+    let mut parts: [Vec<String>; 3] = Default::default();
+    for name in layout_order(output_sections) {
+        let part = match section_group(&name, &output_sections[&name]) {
+            0 | 1 => 0,
+            2..=4 => 1,
+            _ => 2,
+        };
+        parts[part].push(name);
+    }
+    parts
+}
 
-                    // collect section sizes prior to this object
-                    let section_sizes: BTreeMap<String, u64> = output_sections
-                        .iter()
-                        .map(|(key, value)| (key.clone(), value.content.len() as u64))
-                        .collect();
-
-                    for section in elf.sections() {
-                        let name = section.name()?;
-                        if !name.is_empty() {
-                            let _span = info_span!("section", name).entered();
-                            let data = section.data()?;
-                            let (is_executable, is_writable) = match section.flags() {
-                                object::SectionFlags::Elf { sh_flags } => {
-                                    if ((sh_flags as u32) & object::elf::SHF_ALLOC) == 0 {
-                                        // non-alloc, skip
-                                        continue;
-                                    } else {
-                                        (
-                                            ((sh_flags as u32) & object::elf::SHF_EXECINSTR) != 0,
-                                            ((sh_flags as u32) & object::elf::SHF_WRITE) != 0,
-                                        )
-                                    }
-                                }
-                                _ => unimplemented!(),
-                            };
+/// Note output sections in file order, split into runs of the same
+/// alignment, each described by a PT_NOTE
+fn note_runs(
+    names: &[String],
+    output_sections: &BTreeMap<String, OutputSection>,
+) -> Vec<Vec<String>> {
+    let mut runs: Vec<Vec<String>> = vec![];
+    let mut after_note = false;
+    for name in names {
+        let output_section = &output_sections[name];
+        if output_section.is_note {
+            match runs.last_mut() {
+                Some(run)
+                    if after_note
+                        && output_sections[&run[0]].alignment == output_section.alignment =>
+                {
+                    run.push(name.clone())
+                }
+                _ => runs.push(vec![name.clone()]),
+            }
+        }
+        after_note = output_section.is_note;
+    }
+    runs
+}
 
-                            // copy to output
-                            let out = output_sections
-                                .entry(name.to_string())
-                                .or_insert_with(OutputSection::default);
-                            out.name = name.to_string();
-                            out.content.extend(data);
-                            if (data.len() as u64) < section.size() {
-                                // handle bss, extend with zero
-                                out.content.resize(
-                                    out.content.len() - data.len() + section.size() as usize,
-                                    0,
-                                );
-                            }
-                            out.is_executable |= is_executable;
-                            out.is_writable |= is_writable;
-                            out.is_bss |= section.kind() == object::SectionKind::UninitializedData;
-                            for (offset, relocation) in section.relocations() {
-                                match relocation.target() {
-                                    object::RelocationTarget::Symbol(symbol_id) => {
-                                        let symbol = elf.symbol_by_index(symbol_id)?;
-                                        if symbol.kind() == object::SymbolKind::Section {
-                                            // relocation to a section
-                                            let section_index = symbol.section_index().unwrap();
-                                            let target_section =
-                                                elf.section_by_index(section_index)?;
-                                            let target_section_name = target_section.name()?;
-                                            info!(
-                                                "Found relocation targeting section {}",
-                                                target_section_name
-                                            );
+/// Permissions of the loadable segment containing an output section
+fn segment_flags(name: &str, output_section: &OutputSection) -> u32 {
+    match section_group(name, output_section) {
+        2 => object::elf::PF_R | object::elf::PF_X,
+        0 | 1 | 3 => object::elf::PF_R,
+        _ => object::elf::PF_R | object::elf::PF_W,
+    }
+}
 
-                                            out.relocations.push(Relocation {
-                                                offset: offset
-                                                    + *section_sizes.get(name).unwrap_or(&0),
-                                                kind: relocation.kind(),
-                                                encoding: relocation.encoding(),
-                                                size: relocation.size(),
-                                                addend: relocation.addend(),
-                                                target: RelocationTarget::Section((
-                                                    target_section_name.to_string(),
-                                                    // record current size of section, because there can be existing content in the section from other object file
-                                                    *section_sizes
-                                                        .get(target_section_name)
-                                                        .unwrap_or(&0),
-                                                )),
-                                            });
-                                        } else {
-                                            // relocation to a symbol
-                                            let symbol_name = symbol.name()?;
-                                            info!(
-                                                "Found relocation targeting symbol {}",
-                                                symbol_name
-                                            );
+/// Extend the last loadable segment to cover a range of the file, or start
+/// a new one if their permissions differ or the range has fixed virtual and
+/// physical addresses, unless it is joined to the last segment; otherwise a
+/// new segment follows the last one in memory
+fn extend_load_segments(
+    load_segments: &mut Vec<LoadSegment>,
+    offset: u64,
+    size: u64,
+    flags: u32,
+    address: Option<(u64, u64)>,
+    joined: bool,
+) {
+    match load_segments.last_mut() {
+        Some(segment) if joined => {
+            segment.size = offset + size - segment.offset;
+            segment.flags |= flags;
+        }
+        Some(segment) if segment.flags == flags && address.is_none() => {
+            segment.size = offset + size - segment.offset
+        }
+        last => {
+            let (address, physical_address) = address.unwrap_or_else(|| {
+                last.map_or((offset, offset), |segment| {
+                    (
+                        segment.address + offset - segment.offset,
+                        segment.physical_address + offset - segment.offset,
+                    )
+                })
+            });
+            load_segments.push(LoadSegment {
+                offset,
+                size,
+                flags,
+                address,
+                physical_address,
+            })
+        }
+    }
+}
 
-                                            out.relocations.push(Relocation {
-                                                offset: offset
-                                                    + *section_sizes.get(name).unwrap_or(&0),
-                                                kind: relocation.kind(),
-                                                encoding: relocation.encoding(),
-                                                size: relocation.size(),
-                                                addend: relocation.addend(),
-                                                target: RelocationTarget::Symbol(
-                                                    symbol_name.to_string(),
-                                                ),
-                                            });
-                                        }
-                                    }
-                                    _ => unimplemented!(),
-                                };
-                            }
-                        }
-                    }
+/// Whether a memory region accepts an output section not assigned to any,
+/// like GNU ld: the section has any of its attributes and none of those
+/// negated after '!'
+fn region_accepts(attributes: &str, output_section: &OutputSection) -> bool {
+    let has = |attribute: char| match attribute.to_ascii_lowercase() {
+        'r' => !output_section.is_writable,
+        'w' => output_section.is_writable,
+        'x' => output_section.is_executable,
+        'a' => true,
+        'i' | 'l' => !output_section.is_bss,
+        _ => false,
+    };
+    let (attributes, negated) = attributes.split_once('!').unwrap_or((attributes, ""));
+    attributes.chars().any(has) && !negated.chars().any(has)
+}
 
-                    for symbol in elf.symbols() {
-                        if !symbol.is_undefined()
-                            && symbol.kind() != object::SymbolKind::Section
-                            && symbol.kind() != object::SymbolKind::File
-                        {
-                            let name = symbol.name()?;
-                            match symbol.section() {
-                                object::SymbolSection::Section(section_index) => {
-                                    let section = elf.section_by_index(section_index)?;
-                                    let section_name = section.name()?;
-                                    info!("Defining symbol {} from section {}", name, section_name);
-                                    // offset: consider existing section content from other files
-                                    let offset = symbol.address()
-                                        + section_sizes.get(section_name).unwrap_or(&0);
-                                    symbols.insert(
-                                        name.to_string(),
-                                        Symbol {
-                                            section_name: section_name.to_string(),
-                                            offset,
-                                            symbol_name_string_id: None,
-                                            symbol_name_dynamic_string_id: None,
-                                            is_global: symbol.is_global(),
-                                            is_plt: false,
-                                        },
-                                    );
+/// End of a section placed in a memory region, which must fit in it
+fn region_end(region: &MemoryRegion, name: &str, address: u64, size: u64) -> anyhow::Result<u64> {
+    let end = address + size;
+    let region_end = region.origin + region.length;
+    if address < region.origin {
+        bail!(
+            "section `{}' at {:#x} is before region `{}' at {:#x}",
+            name,
+            address,
+            region.name,
+            region.origin
+        );
+    } else if end > region_end {
+        bail!(
+            "section `{}' will not fit in region `{}': region `{}' overflowed by {} bytes",
+            name,
+            region.name,
+            region.name,
+            end - region_end
+        );
+    }
+    Ok(end)
+}
 
-                                    if symbol.is_global() && opt.shared {
-                                        // export GLOBAL symbols in dynsym
-                                        dynamic_symbols.push(DynamicSymbol {
-                                            name: name.to_string(),
-                                        });
-                                    }
-                                }
-                                _ => bail!(
-                                    "Symbol kind is {:?}, symbol section is {:?}",
-                                    symbol.kind(),
-                                    symbol.section(),
-                                ),
-                            }
+/// Values of symbols defined by linker scripts in the final layout, with
+/// the location counter at the end of the previous output section in
+/// SECTIONS, or the start or end of the output section they are in
+fn script_symbol_values(
+    script: &Script,
+    script_symbols: &BTreeSet<String>,
+    output_sections: &BTreeMap<String, OutputSection>,
+    section_address: &BTreeMap<String, u64>,
+    load_segments: &[LoadSegment],
+    symbols: &BTreeMap<String, Symbol>,
+) -> anyhow::Result<BTreeMap<String, u64>> {
+    let mut values = BTreeMap::new();
+    let mut dot = 0;
+    let mut last_location = None;
+    // end of the last existing output section before the index
+    let end_before = |index: usize| {
+        (0..index)
+            .rev()
+            .find(|index| script_section_exists(script, output_sections, *index))
+            .map(|index| {
+                let name = &script.sections[index].name;
+                (
+                    index,
+                    section_address[name] + output_sections[name].content.len() as u64,
+                )
+            })
+    };
+    for assignment in &script.assignments {
+        // unused PROVIDE
+        if assignment.symbol != "." && !script_symbols.contains(&assignment.symbol) {
+            continue;
+        }
+        let location = assignment.location;
+        if last_location != Some(location) {
+            let section = match location {
+                AssignmentLocation::Start(index) | AssignmentLocation::End(index)
+                    if script_section_exists(script, output_sections, index) =>
+                {
+                    Some(&script.sections[index].name)
+                }
+                _ => None,
+            };
+            match (location, section) {
+                (AssignmentLocation::Outside, _) => {}
+                (AssignmentLocation::Start(_), Some(name)) => dot = section_address[name],
+                (AssignmentLocation::End(_), Some(name)) => {
+                    dot = section_address[name] + output_sections[name].content.len() as u64
+                }
+                (
+                    AssignmentLocation::Before(index)
+                    | AssignmentLocation::Start(index)
+                    | AssignmentLocation::End(index),
+                    _,
+                ) => {
+                    // assignments at the end of the previous section may
+                    // have moved the location counter past it
+                    if let Some((previous, end)) = end_before(index) {
+                        if last_location != Some(AssignmentLocation::End(previous)) {
+                            dot = end;
                         }
                     }
                 }
-                _ => return Err(anyhow!("Unsupported format of file {}", name)),
             }
+            last_location = Some(location);
+        }
+        let context = SymbolContext {
+            memory: &script.memory,
+            dot,
+            values: &values,
+            symbols,
+            output_sections,
+            section_address,
+            load_segments,
+        };
+        let value = assignment.expr.eval(&context)?;
+        if assignment.symbol == "." {
+            dot = value;
+        } else {
+            values.insert(assignment.symbol.clone(), value);
         }
+    }
+    Ok(values)
+}
 
-        if opt.shared || self.dynamic_link {
-            // add _DYNAMIC symbol
-            symbols.insert(
-                "_DYNAMIC".to_string(),
-                Symbol {
-                    section_name: ".dynamic".to_string(),
-                    offset: 0,
-                    symbol_name_string_id: None,
-                    symbol_name_dynamic_string_id: None,
-                    is_global: false,
-                    is_plt: false,
-                },
+// steps of placing output sections in fixed_addresses()
+enum LayoutStep<'a> {
+    // assignment in SECTIONS, before or at the end of an output section
+    Assignment(&'a Assignment),
+    // output section, and its description in SECTIONS
+    Section(&'a str, Option<&'a OutputSectionDescription>),
+}
+
+/// Whether the output section of the index in SECTIONS of linker scripts
+/// exists, i.e. has input sections
+fn script_section_exists(
+    script: &Script,
+    output_sections: &BTreeMap<String, OutputSection>,
+    index: usize,
+) -> bool {
+    output_sections
+        .get(&script.sections[index].name)
+        .is_some_and(|section| section.script_order == Some(index))
+}
+
+/// Addresses of output sections fixed by --section-start, and by addresses,
+/// the location counter and memory regions in linker scripts: sections in
+/// SECTIONS in order, then other sections in file order go to the first
+/// region accepting them
+fn fixed_addresses(
+    opt: &Opt,
+    script: &Script,
+    output_sections: &BTreeMap<String, OutputSection>,
+    parts: &[Vec<String>; 3],
+    max_page_size: u64,
+) -> anyhow::Result<FixedAddresses> {
+    let mut fixed = FixedAddresses::default();
+    let mut steps = vec![];
+    for index in 0..=script.sections.len() {
+        steps.extend(
+            script
+                .assignments
+                .iter()
+                .filter(|assignment| assignment.location == AssignmentLocation::Before(index))
+                .map(LayoutStep::Assignment),
+        );
+        if index < script.sections.len() && script_section_exists(script, output_sections, index) {
+            let description = &script.sections[index];
+            steps.push(LayoutStep::Section(&description.name, Some(description)));
+            steps.extend(
+                script
+                    .assignments
+                    .iter()
+                    .filter(|assignment| {
+                        assignment.location == AssignmentLocation::End(index)
+                            && assignment.symbol == "."
+                    })
+                    .map(LayoutStep::Assignment),
             );
         }
+    }
+    steps.extend(
+        parts
+            .iter()
+            .flatten()
+            .filter(|name| output_sections[*name].script_order.is_none())
+            .map(|name| LayoutStep::Section(name, None)),
+    );
 
-        // sort dynamic symbols by gnu hash bucket
-        let bucket_count = dynamic_symbols.len();
-        dynamic_symbols.sort_by_key(|sym| {
-            let hash = object::elf::gnu_hash(sym.name.as_bytes());
-            hash % bucket_count as u32
-        });
+    // next free address of each region
+    let mut cursors: Vec<u64> = script.memory.iter().map(|region| region.origin).collect();
+    let region_index = |name: &str, region: &str| {
+        script
+            .memory
+            .iter()
+            .position(|memory| memory.name == region)
+            .ok_or(anyhow!(
+                "Unknown memory region {} for section {}",
+                region,
+                name
+            ))
+    };
+    let mut dot = 0;
+    // whether the location counter is assigned and places sections
+    let mut dot_assigned = false;
+    // symbols assigned so far, those depending on the final layout are
+    // evaluated in relocate()
+    let mut values = BTreeMap::new();
+    // region and physical - virtual address of the last section in a region
+    let mut last_region: Option<(usize, u64)> = None;
+    for step in steps {
+        let (name, description) = match step {
+            LayoutStep::Assignment(assignment) => {
+                let context = LayoutContext {
+                    memory: &script.memory,
+                    dot,
+                    values: &values,
+                };
+                if assignment.symbol == "." {
+                    dot = assignment.expr.eval(&context)?;
+                    dot_assigned |= matches!(assignment.location, AssignmentLocation::Before(_));
+                } else if let Ok(value) = assignment.expr.eval(&context) {
+                    values.insert(assignment.symbol.clone(), value);
+                }
+                continue;
+            }
+            LayoutStep::Section(name, description) => (name, description),
+        };
+        let output_section = &output_sections[name];
+        let size = output_section.content.len() as u64;
+        let alignment = output_section.alignment.max(1);
+        let address_expr = description.and_then(|description| description.address.as_ref());
+        let region = match description.and_then(|description| description.region.as_deref()) {
+            Some(region) => Some(region_index(name, region)?),
+            None if address_expr.is_none() && !script.memory.is_empty() => Some(
+                script
+                    .memory
+                    .iter()
+                    .position(|region| region_accepts(&region.attributes, output_section))
+                    .ok_or(anyhow!(
+                        "no memory region specified for loadable section `{}'",
+                        name
+                    ))?,
+            ),
+            None => None,
+        };
+        let context = LayoutContext {
+            memory: &script.memory,
+            dot,
+            values: &values,
+        };
+        let address = match (address_expr, region) {
+            (Some(address), _) => address.eval(&context)?,
+            (None, Some(region)) => cursors[region].next_multiple_of(alignment),
+            (None, None) if dot_assigned && description.is_some() => {
+                dot.next_multiple_of(alignment)
+            }
+            (None, None) => continue,
+        };
+        if let Some(region) = region {
+            cursors[region] = region_end(&script.memory[region], name, address, size)?;
+        }
+        let physical_address = match description {
+            Some(OutputSectionDescription {
+                load_address: Some(load_address),
+                ..
+            }) => load_address.eval(&context)?,
+            Some(OutputSectionDescription {
+                load_region: Some(load_region),
+                ..
+            }) => {
+                let region = region_index(name, load_region)?;
+                let physical_address = cursors[region].next_multiple_of(alignment);
+                cursors[region] = region_end(&script.memory[region], name, physical_address, size)?;
+                physical_address
+            }
+            // keeps the load address offset of the previous section in the
+            // same region, like GNU ld
+            _ => match last_region {
+                Some((last, delta)) if region == Some(last) => address.wrapping_add(delta),
+                _ => address,
+            },
+        };
+        last_region = region.map(|region| (region, physical_address.wrapping_sub(address)));
+        info!(
+            "Placing section {} at {:#x}, loaded at {:#x}",
+            name, address, physical_address
+        );
+        fixed
+            .addresses
+            .insert(name.to_string(), (address, physical_address));
+        dot = address + size;
+    }
+    for (name, address) in &opt.section_start {
+        fixed.addresses.insert(name.clone(), (*address, *address));
+    }
 
-        if !opt.shared && self.dynamic_link {
-            let mut interp = OutputSection {
-                name: ".interp".to_string(),
-                ..OutputSection::default()
+    for part in parts {
+        for pair in part.windows(2) {
+            let (Some(&(prev_address, prev_physical)), Some(&(address, physical))) =
+                (fixed.addresses.get(&pair[0]), fixed.addresses.get(&pair[1]))
+            else {
+                continue;
             };
-            interp
-                .content
-                .extend_from_slice(opt.dynamic_linker.as_ref().unwrap().as_bytes());
-            // NULL terminated string
-            interp.content.push(0);
-            output_sections.insert(".interp".to_string(), interp);
+            let prev_end = prev_address + output_sections[&pair[0]].content.len() as u64;
+            if physical.wrapping_sub(address) == prev_physical.wrapping_sub(prev_address)
+                && address >= prev_end
+                && address < prev_end.next_multiple_of(max_page_size)
+            {
+                fixed.joined.insert(pair[1].clone());
+            }
         }
-        Ok(())
     }
+    Ok(fixed)
+}
 
-    fn generate_plt(&mut self) -> anyhow::Result<()> {
-        let Linker {
-            output_sections,
-            symbols,
-            plt_dynamic_symbols,
-            output_relocations,
-            ..
-        } = self;
-
-        // handle dynamic symbols: construct .plt, .got.plt
-        if self.dynamic_link {
-            assert!(!output_sections.contains_key(".plt"));
-            let mut plt = OutputSection {
-                name: ".plt".to_string(),
-                is_executable: true,
-                ..OutputSection::default()
-            };
+/// Virtual address of a file offset in a loadable segment
+fn segment_address(load_segments: &[LoadSegment], offset: u64) -> u64 {
+    let segment = load_segments
+        .iter()
+        .rev()
+        .find(|segment| segment.offset <= offset)
+        .unwrap();
+    segment.address + offset - segment.offset
+}
 
-            // first entry in plt:
-            plt.content.extend(vec![
-                // ff 35 xx xx xx xx push .got.plt+8(%rip)
-                0xff, 0x35, 0x00, 0x00, 0x00, 0x00,
-                // ff 25 xx xx xx xx jmp *.got.plt+16(%rip)
-                0xff, 0x25, 0x00, 0x00, 0x00, 0x00, // 0f 1f 40 00       nop
-                0x0f, 0x1f, 0x40, 0x00,
-            ]);
-            // relocation for push .got.plt+8(rip)
-            plt.relocations.push(Relocation {
-                offset: 0x2,
-                kind: object::RelocationKind::Relative,
-                encoding: object::RelocationEncoding::Generic,
-                size: 32,
-                addend: 8 - 4,
-                target: RelocationTarget::Section((".got.plt".to_string(), 0)),
-            });
-            // relocation for jmp *.got.plt+16(%rip)
-            plt.relocations.push(Relocation {
-                offset: 0x8,
-                kind: object::RelocationKind::Relative,
-                encoding: object::RelocationEncoding::Generic,
-                size: 32,
-                addend: 16 - 4,
-                target: RelocationTarget::Section((".got.plt".to_string(), 0)),
-            });
-            output_sections.insert(".plt".to_string(), plt);
+/// Physical address of a file offset, see segment_address()
+fn segment_physical_address(load_segments: &[LoadSegment], offset: u64) -> u64 {
+    let segment = load_segments
+        .iter()
+        .rev()
+        .find(|segment| segment.offset <= offset)
+        .unwrap();
+    segment.physical_address + offset - segment.offset
+}
 
-            // got contents:
-            assert!(!output_sections.contains_key(".got.plt"));
-            let mut got_plt = OutputSection {
-                name: ".got.plt".to_string(),
-                ..OutputSection::default()
-            };
-            got_plt.content.extend(vec![
-                // 0: address of .dynamic section
-                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-                // 1: 0, reserved for ld.so
-                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-                // 2: 0, reserved for ld.so
-                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            ]);
-            // address of .dynamic section
-            got_plt.relocations.push(Relocation {
-                offset: 0x0,
-                kind: object::RelocationKind::Absolute,
-                encoding: object::RelocationEncoding::Generic,
-                size: 64,
-                addend: 0,
-                target: RelocationTarget::Section((".dynamic".to_string(), 0)),
-            });
-            output_sections.insert(".got.plt".to_string(), got_plt);
+/// Reserve output sections in the file, each from a new page, segments with
+/// different permissions do not share pages, sections with fixed addresses
+/// start new segments
+fn reserve_output_sections(
+    writer: &mut Writer,
+    output_sections: &mut BTreeMap<String, OutputSection>,
+    names: &[String],
+    fixed: &FixedAddresses,
+    load_segments: &mut Vec<LoadSegment>,
+    common_page_size: u64,
+    max_page_size: u64,
+) {
+    let has_tdata = output_sections.contains_key(".tdata");
+    let mut after_note = false;
+    for name in names {
+        let output_section = output_sections.get_mut(name).unwrap();
+        let flags = segment_flags(name, output_section);
+        let align = if load_segments
+            .last()
+            .is_some_and(|segment| segment.flags != flags)
+        {
+            max_page_size
+        } else if (name == ".tbss" && has_tdata) || (output_section.is_note && after_note) {
+            // keep .tbss next to .tdata to avoid padding in the TLS template,
+            // and notes together to share a PT_NOTE
+            output_section.alignment.max(1)
+        } else {
+            common_page_size
+        };
+        after_note = output_section.is_note;
+        let address = fixed.addresses.get(name).copied();
+        let joined = fixed.joined.contains(name);
+        output_section.offset = match address {
+            Some((address, _)) => {
+                // file offset and address are congruent modulo page size, and
+                // follow the last segment if joined to it
+                let offset = match load_segments.last() {
+                    Some(segment) if joined => segment.offset + address - segment.address,
+                    _ => {
+                        let len = writer.reserved_len() as u64;
+                        len + address.wrapping_sub(len) % max_page_size
+                    }
+                };
+                writer.reserve_until(offset as usize);
+                writer.reserve(output_section.content.len(), 1) as u64
+            }
+            None => writer.reserve(output_section.content.len(), align as usize) as u64,
+        };
+        extend_load_segments(
+            load_segments,
+            output_section.offset,
+            output_section.content.len() as u64,
+            flags,
+            address,
+            joined,
+        );
+    }
+}
 
-            // add _GLOBAL_OFFSET_TABLE_ symbol
-            symbols.insert(
-                "_GLOBAL_OFFSET_TABLE_".to_string(),
-                Symbol {
-                    section_name: ".got.plt".to_string(),
-                    offset: 0,
-                    symbol_name_string_id: None,
-                    symbol_name_dynamic_string_id: None,
-                    is_global: false,
-                    is_plt: false,
-                },
-            );
+/// Whether section name is prefix itself or prefix followed by a dot
+fn has_section_prefix(name: &str, prefix: &str) -> bool {
+    name.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
 
-            for (idx, dyn_sym) in plt_dynamic_symbols.iter().enumerate() {
-                // redirect the symbol to plt
-                let plt = output_sections.get_mut(".plt").unwrap();
-                let plt_offset = plt.content.len() as u64;
-
-                // each entry in plt:
-                // ff 25 xx xx xx xx jmp *.got.plt+yy(%rip)
-                plt.content.extend(vec![0xff, 0x25, 0x00, 0x00, 0x00, 0x00]);
-                // 68 xx xx xx xx    push index
-                plt.content.push(0x68);
-                plt.content.extend_from_slice(&(idx as u32).to_le_bytes());
-                // e9 xx xx xx xx    jmp plt_first_entry
-                plt.content.extend(vec![0xe9, 0x00, 0x00, 0x00, 0x00]);
-
-                // relocation for jmp *.got.plt+yy(%rip)
-                plt.relocations.push(Relocation {
-                    offset: 0x2 + plt_offset,
-                    kind: object::RelocationKind::Relative,
-                    encoding: object::RelocationEncoding::Generic,
-                    size: 32,
-                    // each got entry: 8 bytes
-                    // 24: got header
-                    addend: (idx as i64 * 8 + 24) - 4,
-                    target: RelocationTarget::Section((".got.plt".to_string(), 0)),
-                });
-                // relocation for jmp plt_first_entry
-                plt.relocations.push(Relocation {
-                    offset: 12 + plt_offset,
-                    kind: object::RelocationKind::Relative,
-                    encoding: object::RelocationEncoding::Generic,
-                    size: 32,
-                    addend: 0 - 4,
-                    target: RelocationTarget::Section((".plt".to_string(), 0)),
-                });
+/// Priority of input section of .init_array and .fini_array, lower comes
+/// first, .ctors.N and .dtors.N count down from 65535
+fn init_priority(name: &str) -> u64 {
+    let suffix = |prefix: &str| {
+        name.strip_prefix(prefix)
+            .and_then(|rest| rest.parse::<u64>().ok())
+    };
+    if let Some(priority) = suffix(".init_array.").or_else(|| suffix(".fini_array.")) {
+        priority
+    } else if let Some(priority) = suffix(".ctors.").or_else(|| suffix(".dtors.")) {
+        65535u64.saturating_sub(priority)
+    } else {
+        // after all sections with priority
+        65536
+    }
+}
 
-                // add entry in .got.plt
-                let got_plt = output_sections.get_mut(".got.plt").unwrap();
-                let got_offset = got_plt.content.len() as u64;
-                // 8 bytes for absolute address
-                got_plt.content.extend(vec![0; 8]);
-
-                // static relocation to the next instruction in plt in binary
-                got_plt.relocations.push(Relocation {
-                    offset: got_offset,
-                    kind: object::RelocationKind::Absolute,
-                    encoding: object::RelocationEncoding::Generic,
-                    size: 64,
-                    addend: plt_offset as i64 + 6, // point to push index
-                    target: RelocationTarget::Section((".plt".to_string(), 0)),
-                });
+/// Offset in output section of an offset into a merged input section,
+/// given the output offsets of its entries
+fn merged_offset(entsize: u64, entries: &[u64], offset: u64) -> Option<u64> {
+    let index = (offset / entsize) as usize;
+    match entries.get(index) {
+        Some(entry) => Some(entry + offset % entsize),
+        // end of section
+        None if index == entries.len() && offset.is_multiple_of(entsize) => {
+            Some(entries.last()? + entsize)
+        }
+        None => None,
+    }
+}
 
-                // add dynamic relocation R_X86_64_JUMP_SLOT to actual symbol
-                output_relocations
-                    .entry(".rela.plt".to_string())
-                    .or_default()
-                    .relocations
-                    .push(Rel {
-                        r_offset: got_offset,
-                        r_sym: (idx + 1) as u32,
-                        r_type: R_X86_64_JUMP_SLOT,
-                        r_addend: 0,
-                    });
+/// Split .eh_frame into CIE and FDE records, with relocations against
+/// the input section
+fn parse_eh_frame(
+    format: ElfFormat,
+    file_name: &str,
+    data: &[u8],
+    mut relocations: Vec<Relocation>,
+) -> anyhow::Result<Vec<EhFrameRecord>> {
+    let read_u32 = |offset: usize| -> anyhow::Result<u32> {
+        let bytes = data
+            .get(offset..offset + 4)
+            .ok_or(anyhow!("{}: truncated .eh_frame record", file_name))?;
+        Ok(format.load(bytes) as u32)
+    };
 
-                symbols.insert(
-                    dyn_sym.name.clone(),
-                    Symbol {
-                        section_name: ".plt".to_string(),
-                        offset: plt_offset,
-                        symbol_name_string_id: None,
-                        symbol_name_dynamic_string_id: None,
-                        is_global: true,
-                        is_plt: true,
-                    },
-                );
-            }
+    let mut records: Vec<EhFrameRecord> = vec![];
+    // offset into section => index of CIE
+    let mut cies: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let length = read_u32(offset)? as usize;
+        if length == 0 {
+            // terminator
+            break;
+        } else if length == 0xffffffff {
+            bail!("{}: 64-bit .eh_frame records are not supported", file_name);
+        }
+        let end = offset + 4 + length;
+        if end > data.len() {
+            bail!("{}: truncated .eh_frame record", file_name);
         }
 
-        Ok(())
+        // CIE id is zero, while FDE has the distance to its CIE
+        let id = read_u32(offset + 4)? as usize;
+        let cie = if id == 0 {
+            cies.insert(offset, records.len());
+            None
+        } else {
+            let cie = (offset + 4)
+                .checked_sub(id)
+                .and_then(|cie_offset| cies.get(&cie_offset))
+                .ok_or(anyhow!("{}: FDE with invalid CIE pointer", file_name))?;
+            Some(*cie)
+        };
+
+        let (inside, rest): (Vec<_>, Vec<_>) = relocations
+            .into_iter()
+            .partition(|relocation| (relocation.offset as usize) < end);
+        relocations = rest;
+        records.push(EhFrameRecord {
+            content: data[offset..end].to_vec(),
+            relocations: inside
+                .into_iter()
+                .map(|relocation| Relocation {
+                    offset: relocation.offset - offset as u64,
+                    ..relocation
+                })
+                .collect(),
+            cie,
+            is_live: true,
+        });
+        offset = end;
     }
+    Ok(records)
+}
 
-    fn reserve(&mut self, arena: &'a mut Arena<u8>) -> anyhow::Result<()> {
-        let Linker {
-            opt,
-            output_sections,
-            symbols,
-            dynamic_symbols,
+/// Offset of the .eh_frame record containing offset, or the end of records
+fn eh_frame_record_start(format: ElfFormat, data: &[u8], offset: u64) -> u64 {
+    let mut start = 0;
+    while let Some(bytes) = data.get(start as usize..start as usize + 4) {
+        let length = format.load(bytes);
+        if length == 0 || length == 0xffffffff || offset < start + 4 + length {
+            break;
+        }
+        start += 4 + length;
+    }
+    start
+}
+
+/// Index of the input section containing offset of an output section
+fn input_section_index(section: &OutputSection, offset: u64) -> Option<usize> {
+    // the last input section starting at or before offset
+    section
+        .input_sections
+        .partition_point(|input_section| input_section.offset <= offset)
+        .checked_sub(1)
+}
+
+/// Ranges of input sections in an output section, including padding after them
+fn input_section_ranges(section: &OutputSection) -> Vec<Range<u64>> {
+    let mut ranges = vec![];
+    for (index, input_section) in section.input_sections.iter().enumerate() {
+        let end = section
+            .input_sections
+            .get(index + 1)
+            .map_or(section.content.len() as u64, |next| next.offset);
+        ranges.push(input_section.offset..end);
+    }
+    ranges
+}
+
+/// Old range and new offset of an input section rearranged in its output
+/// section, the new offset is none if the input section is removed
+type SectionMove = (Range<u64>, Option<u64>);
+
+/// Offset after input sections are rearranged, none if removed; the offset
+/// belongs to the same input section as in input_section_index(), so that
+/// --gc-sections keeps what it has marked
+fn moved_offset(moves: &[SectionMove], offset: u64) -> Option<u64> {
+    let index = moves
+        .partition_point(|(range, _)| range.start <= offset)
+        .checked_sub(1)?;
+    let (range, start) = &moves[index];
+    start.map(|start| start + offset - range.start)
+}
+
+/// Output sections kept by --gc-sections even if unreferenced, like KEEP in
+/// the default GNU ld script
+fn is_kept_section(name: &str) -> bool {
+    matches!(
+        name,
+        ".init" | ".fini" | ".preinit_array" | ".init_array" | ".fini_array" | ".ctors" | ".dtors"
+    ) || name.starts_with(".note")
+}
+
+/// Offset of pc_begin in FDE, after length and CIE pointer
+const FDE_PC_BEGIN_OFFSET: u64 = 8;
+
+/// Pointer encodings of .eh_frame_hdr
+const DW_EH_PE_UDATA4: u8 = 0x03;
+const DW_EH_PE_SDATA4: u8 = 0x0b;
+const DW_EH_PE_PCREL: u8 = 0x10;
+const DW_EH_PE_DATAREL: u8 = 0x30;
+
+/// Pseudo section of absolute symbols, placed at address zero
+const ABSOLUTE_SECTION: &str = "*ABS*";
+
+/// Pseudo section of undefined weak symbols, placed at address zero
+const UNDEFINED_SECTION: &str = "*UND*";
+
+/// Section index of large common symbols, missing in object::elf
+const SHN_X86_64_LCOMMON: u16 = 0xff02;
+
+/// Default page size for x86_64
+const DEFAULT_PAGE_SIZE: u64 = 4096;
+
+/// File class and data encoding of the output, so that layout and emission
+/// do not assume ELF64 little endian
+#[derive(Debug, Clone, Copy)]
+struct ElfFormat {
+    endian: object::Endianness,
+    class: Class,
+}
+
+impl ElfFormat {
+    /// Format of x86-64 output, the only one produced for now
+    const X86_64: ElfFormat = ElfFormat {
+        endian: object::Endianness::Little,
+        class: Class { is_64: true },
+    };
+
+    /// ELF writer of this format
+    fn writer(self, buffer: &mut dyn object::write::WritableBuffer) -> Writer<'_> {
+        Writer::new(self.endian, self.class.is_64, buffer)
+    }
+
+    /// Size of addresses, GOT entries and word-sized relocations
+    fn word_size(self) -> usize {
+        if self.class.is_64 {
+            8
+        } else {
+            4
+        }
+    }
+
+    /// Size of a dynamic relocation entry, always with addend
+    fn rela_size(self) -> usize {
+        self.class.rel_size(true)
+    }
+
+    /// Store the low bytes of value in the data encoding, as many as dst holds
+    fn store(self, dst: &mut [u8], value: u64) {
+        let len = dst.len();
+        match self.endian {
+            object::Endianness::Little => dst.copy_from_slice(&value.to_le_bytes()[..len]),
+            object::Endianness::Big => dst.copy_from_slice(&value.to_be_bytes()[8 - len..]),
+        }
+    }
+
+    /// Load a value of src.len() bytes in the data encoding
+    fn load(self, src: &[u8]) -> u64 {
+        let mut bytes = [0; 8];
+        match self.endian {
+            object::Endianness::Little => {
+                bytes[..src.len()].copy_from_slice(src);
+                u64::from_le_bytes(bytes)
+            }
+            object::Endianness::Big => {
+                bytes[8 - src.len()..].copy_from_slice(src);
+                u64::from_be_bytes(bytes)
+            }
+        }
+    }
+
+    /// Encode a 32-bit word in the data encoding
+    fn u32_bytes(self, value: u32) -> [u8; 4] {
+        match self.endian {
+            object::Endianness::Little => value.to_le_bytes(),
+            object::Endianness::Big => value.to_be_bytes(),
+        }
+    }
+}
+
+/// Parameters of .gnu.hash
+struct GnuHashLayout {
+    // the second bit set in bloom filter is taken from hash >> bloom_shift
+    bloom_shift: u32,
+    // number of 64-bit bloom filter words, a power of two
+    bloom_count: u32,
+    bucket_count: u32,
+}
+
+/// Size .gnu.hash for the defined dynamic symbols like lld: 12 bloom bits
+/// and a quarter bucket per symbol, unless tuned by -z options
+fn gnu_hash_layout(opt: &Opt, symbol_count: usize) -> GnuHashLayout {
+    let symbol_count = symbol_count as u32;
+    GnuHashLayout {
+        bloom_shift: opt.z_options.gnu_hash_bloom_shift.unwrap_or(26),
+        bloom_count: (symbol_count * 12 / 64).max(1).next_power_of_two(),
+        bucket_count: opt
+            .z_options
+            .gnu_hash_buckets
+            .unwrap_or((symbol_count / 4).max(1)),
+    }
+}
+
+/// Number of R_X86_64_RELATIVE relocations, see DT_RELACOUNT
+fn relative_count(rela_dyn: &OutputRelocationSection) -> usize {
+    rela_dyn
+        .relocations
+        .iter()
+        .take_while(|rel| rel.r_type == R_X86_64_RELATIVE)
+        .count()
+}
+
+/// Conventional program interpreter of the emulation, for glibc or musl
+fn default_dynamic_linker(emulation: Option<&str>, musl: bool) -> anyhow::Result<&'static str> {
+    let (glibc, musl_interp) = match emulation.unwrap_or("elf_x86_64") {
+        "elf_x86_64" => ("/lib64/ld-linux-x86-64.so.2", "/lib/ld-musl-x86_64.so.1"),
+        "elf32_x86_64" => ("/libx32/ld-linux-x32.so.2", "/lib/ld-musl-x32.so.1"),
+        "elf_i386" => ("/lib/ld-linux.so.2", "/lib/ld-musl-i386.so.1"),
+        "aarch64linux" => ("/lib/ld-linux-aarch64.so.1", "/lib/ld-musl-aarch64.so.1"),
+        "aarch64linuxb" => (
+            "/lib/ld-linux-aarch64_be.so.1",
+            "/lib/ld-musl-aarch64_be.so.1",
+        ),
+        emulation => bail!(
+            "no default dynamic linker for emulation `{}`, use -dynamic-linker",
+            emulation
+        ),
+    };
+    Ok(if musl { musl_interp } else { glibc })
+}
+
+/// Compute DT_FLAGS value
+fn dynamic_flags(opt: &Opt, text_relocation: bool, static_tls: bool) -> u32 {
+    let mut flags = 0;
+    if opt.z_options.now {
+        flags |= DF_BIND_NOW;
+    }
+    if text_relocation {
+        flags |= DF_TEXTREL;
+    }
+    if opt.z_options.origin {
+        flags |= DF_ORIGIN;
+    }
+    if opt.shared && opt.symbolic == Symbolic::All {
+        flags |= DF_SYMBOLIC;
+    }
+    if static_tls {
+        flags |= DF_STATIC_TLS;
+    }
+    flags
+}
+
+/// Compute DT_FLAGS_1 value
+fn dynamic_flags_1(opt: &Opt) -> u32 {
+    let mut flags = 0;
+    if opt.pie {
+        flags |= DF_1_PIE;
+    }
+    if opt.z_options.now {
+        flags |= DF_1_NOW;
+    }
+    if opt.z_options.origin {
+        flags |= DF_1_ORIGIN;
+    }
+    if opt.z_options.global {
+        flags |= DF_1_GLOBAL;
+    }
+    if opt.z_options.initfirst {
+        flags |= DF_1_INITFIRST;
+    }
+    if opt.z_options.nodelete {
+        flags |= DF_1_NODELETE;
+    }
+    if opt.z_options.nodlopen {
+        flags |= DF_1_NOOPEN;
+    }
+    flags
+}
+
+/// Names of -init and -fini functions, _init and _fini by default
+fn init_fini_functions(opt: &Opt) -> [(u32, &str); 2] {
+    [
+        (DT_INIT, opt.init.as_deref().unwrap_or("_init")),
+        (DT_FINI, opt.fini.as_deref().unwrap_or("_fini")),
+    ]
+}
+
+/// Relocation kind, GOTPCRELX is handled as GOTPCREL as relaxing it is optional
+fn relocation_kind(relocation: &object::Relocation) -> object::RelocationKind {
+    match relocation.flags() {
+        object::RelocationFlags::Elf {
+            r_type: R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX,
+        } => object::RelocationKind::GotRelative,
+        _ => relocation.kind(),
+    }
+}
+
+/// Relocation size in bits, see relocation_kind
+fn relocation_size(relocation: &object::Relocation) -> u8 {
+    match relocation.flags() {
+        object::RelocationFlags::Elf {
+            r_type: R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX,
+        } => 32,
+        _ => relocation.size(),
+    }
+}
+
+/// ELF relocation type, for those without a generic kind like TLS ones
+fn relocation_type(relocation: &object::Relocation) -> u32 {
+    match relocation.flags() {
+        object::RelocationFlags::Elf { r_type } => r_type,
+        _ => R_X86_64_NONE,
+    }
+}
+
+/// Input file and section containing the offset of output section
+fn relocation_location(name: &str, output_section: &OutputSection, offset: u64) -> String {
+    match output_section
+        .input_sections
+        .iter()
+        .find(|input| input.offset <= offset && offset < input.offset + input.size)
+    {
+        Some(input) => format!(
+            "{} ({}+{:#x})",
+            input.file_name,
+            input.name,
+            offset - input.offset
+        ),
+        None => format!("({}+{:#x})", name, offset),
+    }
+}
+
+/// Range of relocation field
+#[derive(Debug, Clone, Copy)]
+enum Overflow {
+    // sign extended
+    Signed,
+    // zero extended
+    Unsigned,
+    // either, like data directives
+    Bitfield,
+}
+
+/// Check that the relocated value fits into the field of relocation
+fn check_overflow(
+    opt: &Opt,
+    name: &str,
+    output_section: &OutputSection,
+    relocation: &Relocation,
+    value: i64,
+    bits: u32,
+    overflow: Overflow,
+) -> anyhow::Result<()> {
+    let (min, max) = match overflow {
+        Overflow::Signed => (-(1i64 << (bits - 1)), (1i64 << (bits - 1)) - 1),
+        Overflow::Unsigned => (0, (1i64 << bits) - 1),
+        Overflow::Bitfield => (-(1i64 << (bits - 1)), (1i64 << bits) - 1),
+    };
+    if (min..=max).contains(&value) {
+        return Ok(());
+    }
+    let target = match &relocation.target {
+        RelocationTarget::Symbol(symbol_name) => format!("symbol `{}`", demangle(opt, symbol_name)),
+        RelocationTarget::Section((section_name, _)) => format!("section `{}`", section_name),
+    };
+    // position independent code avoids absolute addresses, and the large
+    // code model avoids 32-bit offsets
+    let hint = if relocation.kind == object::RelocationKind::Absolute {
+        "-fPIC"
+    } else {
+        "-mcmodel=large"
+    };
+    bail!(
+        "{}: relocation {} against {} out of range: {} is not in [{}, {}]; recompile with {}",
+        relocation_location(name, output_section, relocation.offset),
+        relocation_name(relocation),
+        target,
+        value,
+        min,
+        max,
+        hint
+    );
+}
+
+/// Whether the relocation refers to the plt entry of function
+fn is_plt_relocation(relocation: &Relocation) -> bool {
+    relocation.kind == object::RelocationKind::PltRelative || relocation.r_type == R_X86_64_PLTOFF64
+}
+
+/// Whether the relocation refers to the .got entry of symbol
+fn is_got_relocation(relocation: &Relocation) -> bool {
+    matches!(
+        relocation.kind,
+        object::RelocationKind::GotRelative | object::RelocationKind::Got
+    ) || matches!(
+        relocation.r_type,
+        R_X86_64_GOT64 | R_X86_64_GOTPCREL64 | R_X86_64_GOTPLT64
+    )
+}
+
+/// Whether the relocation is computed relative to _GLOBAL_OFFSET_TABLE_
+fn is_got_base_relocation(relocation: &Relocation) -> bool {
+    relocation.kind == object::RelocationKind::Got
+        || matches!(
+            relocation.r_type,
+            R_X86_64_GOTOFF64
+                | R_X86_64_GOTPC32
+                | R_X86_64_GOTPC64
+                | R_X86_64_GOT64
+                | R_X86_64_GOTPLT64
+                | R_X86_64_PLTOFF64
+        )
+        || matches!(&relocation.target, RelocationTarget::Symbol(name) if name == "_GLOBAL_OFFSET_TABLE_")
+}
+
+/// Name of relocation type for diagnostics
+fn relocation_name(relocation: &Relocation) -> &'static str {
+    match (relocation.kind, relocation.encoding, relocation.size) {
+        (object::RelocationKind::Absolute, object::RelocationEncoding::Generic, 64) => {
+            "R_X86_64_64"
+        }
+        (object::RelocationKind::Absolute, object::RelocationEncoding::Generic, 32) => {
+            "R_X86_64_32"
+        }
+        (object::RelocationKind::Absolute, object::RelocationEncoding::X86Signed, 32) => {
+            "R_X86_64_32S"
+        }
+        (object::RelocationKind::Absolute, object::RelocationEncoding::Generic, 16) => {
+            "R_X86_64_16"
+        }
+        (object::RelocationKind::Absolute, object::RelocationEncoding::Generic, 8) => "R_X86_64_8",
+        (object::RelocationKind::Relative, object::RelocationEncoding::Generic, 32) => {
+            "R_X86_64_PC32"
+        }
+        (object::RelocationKind::Relative, object::RelocationEncoding::Generic, 16) => {
+            "R_X86_64_PC16"
+        }
+        (object::RelocationKind::Relative, object::RelocationEncoding::Generic, 8) => {
+            "R_X86_64_PC8"
+        }
+        (object::RelocationKind::Got, object::RelocationEncoding::Generic, 32) => "R_X86_64_GOT32",
+        (object::RelocationKind::PltRelative, object::RelocationEncoding::Generic, 32) => {
+            "R_X86_64_PLT32"
+        }
+        (object::RelocationKind::GotRelative, object::RelocationEncoding::Generic, 32) => {
+            "R_X86_64_GOTPCREL"
+        }
+        _ => match relocation.r_type {
+            R_X86_64_TLSGD => "R_X86_64_TLSGD",
+            R_X86_64_TLSLD => "R_X86_64_TLSLD",
+            R_X86_64_DTPOFF32 => "R_X86_64_DTPOFF32",
+            R_X86_64_GOTTPOFF => "R_X86_64_GOTTPOFF",
+            R_X86_64_TPOFF32 => "R_X86_64_TPOFF32",
+            R_X86_64_GOTPC32_TLSDESC => "R_X86_64_GOTPC32_TLSDESC",
+            R_X86_64_TLSDESC_CALL => "R_X86_64_TLSDESC_CALL",
+            R_X86_64_PC64 => "R_X86_64_PC64",
+            R_X86_64_GOTOFF64 => "R_X86_64_GOTOFF64",
+            R_X86_64_GOTPC32 => "R_X86_64_GOTPC32",
+            R_X86_64_GOTPC64 => "R_X86_64_GOTPC64",
+            R_X86_64_GOT64 => "R_X86_64_GOT64",
+            R_X86_64_GOTPCREL64 => "R_X86_64_GOTPCREL64",
+            R_X86_64_GOTPLT64 => "R_X86_64_GOTPLT64",
+            R_X86_64_PLTOFF64 => "R_X86_64_PLTOFF64",
+            R_X86_64_SIZE32 => "R_X86_64_SIZE32",
+            R_X86_64_SIZE64 => "R_X86_64_SIZE64",
+            R_X86_64_COPY => "R_X86_64_COPY",
+            R_X86_64_GLOB_DAT => "R_X86_64_GLOB_DAT",
+            R_X86_64_JUMP_SLOT => "R_X86_64_JUMP_SLOT",
+            R_X86_64_RELATIVE => "R_X86_64_RELATIVE",
+            R_X86_64_IRELATIVE => "R_X86_64_IRELATIVE",
+            R_X86_64_DTPMOD64 => "R_X86_64_DTPMOD64",
+            R_X86_64_TLSDESC => "R_X86_64_TLSDESC",
+            _ => "unknown",
+        },
+    }
+}
+
+/// Fill .got entry at offset with the module id for __tls_get_addr
+fn add_tls_module_id(
+    format: ElfFormat,
+    opt: &Opt,
+    got: &mut OutputSection,
+    output_relocations: &mut BTreeMap<String, OutputRelocationSection>,
+    offset: u64,
+) {
+    if opt.shared {
+        // assigned by the dynamic linker
+        output_relocations
+            .entry(".rela.dyn".to_string())
+            .or_default()
+            .relocations
+            .push(DynamicRelocation {
+                section_name: ".got".to_string(),
+                offset,
+                symbol_index: 0,
+                r_type: R_X86_64_DTPMOD64,
+                addend: 0,
+                target: None,
+            });
+    } else {
+        // the executable is always the first module
+        let word = format.word_size();
+        format.store(&mut got.content[offset as usize..offset as usize + word], 1);
+    }
+}
+
+/// _GLOBAL_OFFSET_TABLE_ at the start of .got.plt
+fn global_offset_table_symbol() -> Symbol {
+    Symbol {
+        section_name: ".got.plt".to_string(),
+        offset: 0,
+        input_section: None,
+        symbol_name_string_id: None,
+        symbol_name_dynamic_string_id: None,
+        is_global: false,
+        is_weak: false,
+        is_plt: false,
+        st_type: STT_OBJECT,
+        size: 0,
+        file_symbol: None,
+        defined_by: None,
+        referenced_by: vec![],
+        canonical_plt: false,
+        in_symtab: true,
+        visibility: STV_DEFAULT,
+    }
+}
+
+/// Compute address of relocation target
+fn target_address(
+    target: &RelocationTarget,
+    symbols: &BTreeMap<String, Symbol>,
+    section_address: &BTreeMap<String, u64>,
+) -> u64 {
+    match target {
+        RelocationTarget::Section((name, offset)) => {
+            info!("Relocation is targeting section {}", name);
+            section_address[name] + offset
+        }
+        RelocationTarget::Symbol(name) => {
+            info!("Relocation is targeting symbol {}", name);
+            let symbol = &symbols[name];
+            section_address[&symbol.section_name] + symbol.offset
+        }
+    }
+}
+
+/// Default library paths searched for dependencies of shared libraries
+const DEFAULT_LIBRARY_PATHS: &[&str] = &[
+    "/lib/x86_64-linux-gnu",
+    "/usr/lib/x86_64-linux-gnu",
+    "/lib64",
+    "/usr/lib64",
+    "/lib",
+    "/usr/lib",
+];
+
+// dynamic information of shared library for dependency checking
+#[derive(Debug, Default)]
+pub struct SharedLibrary {
+    pub name: String,
+    // DT_SONAME entry
+    pub soname: Option<String>,
+    // DT_NEEDED entries
+    pub needed: Vec<String>,
+    // DT_RUNPATH/DT_RPATH entries
+    pub runpath: Vec<String>,
+    // defined dynamic symbols
+    pub defined: Vec<String>,
+    // undefined non-weak dynamic symbols
+    pub undefined: Vec<String>,
+}
+
+/// Read dynamic section and dynamic symbols of shared library
+fn read_shared_library<Elf: object::read::elf::FileHeader>(
+    name: &str,
+    elf: &ElfFile<Elf>,
+) -> anyhow::Result<SharedLibrary> {
+    let mut lib = SharedLibrary {
+        name: name.to_string(),
+        ..SharedLibrary::default()
+    };
+
+    let endian = elf.endian();
+    let data = elf.data();
+    let section_table = elf.elf_section_table();
+    if let Some((entries, link)) = section_table.dynamic(endian, data)? {
+        let strings = section_table.strings(endian, data, link)?;
+        for entry in entries {
+            match entry.tag32(endian) {
+                Some(DT_NEEDED) => {
+                    let needed = std::str::from_utf8(entry.string(endian, strings)?)?;
+                    lib.needed.push(needed.to_string());
+                }
+                Some(DT_SONAME) => {
+                    let soname = std::str::from_utf8(entry.string(endian, strings)?)?;
+                    lib.soname = Some(soname.to_string());
+                }
+                Some(DT_RUNPATH) | Some(DT_RPATH) => {
+                    let runpath = std::str::from_utf8(entry.string(endian, strings)?)?;
+                    lib.runpath
+                        .extend(runpath.split(':').map(|path| path.to_string()));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for symbol in elf.dynamic_symbols() {
+        let symbol_name = symbol.name()?;
+        if symbol_name.is_empty() {
+            continue;
+        }
+        if !symbol.is_undefined() {
+            lib.defined.push(symbol_name.to_string());
+        } else if !symbol.is_weak() {
+            lib.undefined.push(symbol_name.to_string());
+        }
+    }
+    Ok(lib)
+}
+
+/// Demangle C++ and Rust symbol names in diagnostics, unless --no-demangle
+fn demangle(opt: &Opt, name: &str) -> String {
+    if opt.demangle {
+        // Rust legacy symbols are Itanium mangled as well, so try Rust first
+        if let Ok(demangled) = rustc_demangle::try_demangle(name) {
+            return format!("{:#}", demangled);
+        }
+        if name.starts_with("_Z") {
+            if let Some(demangled) = cpp_demangle::Symbol::new(name)
+                .ok()
+                .and_then(|symbol| symbol.demangle().ok())
+            {
+                return demangled;
+            }
+        }
+    }
+    name.to_string()
+}
+
+/// Print definition or reference of symbols requested by --trace-symbol
+fn trace_symbol(opt: &Opt, file_name: &str, name: &str, is_definition: bool) {
+    if opt.trace_symbol.iter().any(|symbol| symbol == name) {
+        if is_definition {
+            println!("{}: definition of {}", file_name, demangle(opt, name));
+        } else {
+            println!("{}: reference to {}", file_name, demangle(opt, name));
+        }
+    }
+}
+
+/// Record that a file references the symbol
+fn add_reference(referenced_by: &mut BTreeMap<String, Vec<String>>, name: &str, file_name: &str) {
+    let files = referenced_by.entry(name.to_string()).or_default();
+    if !files.iter().any(|file| file == file_name) {
+        files.push(file_name.to_string());
+    }
+}
+
+/// Empty .comment, made of NUL-terminated strings
+fn comment_section() -> OutputSection {
+    OutputSection {
+        name: ".comment".to_string(),
+        is_strings: true,
+        alignment: 1,
+        ..OutputSection::default()
+    }
+}
+
+/// Append NUL-terminated strings in data, skipping empty and duplicate ones
+fn merge_strings(content: &mut Vec<u8>, data: &[u8]) {
+    for string in data.split(|byte| *byte == 0) {
+        if !string.is_empty() && !content.split(|byte| *byte == 0).any(|s| s == string) {
+            content.extend(string);
+            content.push(0);
+        }
+    }
+}
+
+/// Value of references from debug sections to discarded code like GNU ld:
+/// 1 in .debug_ranges and .debug_loc where 0 ends a list, 0 elsewhere
+fn debug_tombstone(section_name: &str) -> u64 {
+    match section_name {
+        ".debug_ranges" | ".debug_loc" => 1,
+        _ => 0,
+    }
+}
+
+/// Format one input section line in link map
+fn format_map_section(name: &str, address: u64, size: u64, file_name: &str) -> String {
+    let size = format!("{:#x}", size);
+    // long section names take a line of their own
+    if name.len() >= 14 {
+        format!(
+            " {}\n{:<15} 0x{:016x} {:>10} {}\n",
+            name, "", address, size, file_name
+        )
+    } else {
+        format!(
+            " {:<14} 0x{:016x} {:>10} {}\n",
+            name, address, size, file_name
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjectFile {
+    pub name: String,
+    /// --as-needed
+    pub as_needed: bool,
+    /// -R, --just-symbols
+    pub just_symbols: bool,
+    pub content: Vec<u8>,
+}
+
+// archive whose members are extracted on demand
+struct Archive<'data> {
+    name: String,
+    data: &'data [u8],
+    archive: object::read::archive::ArchiveFile<'data>,
+    // header offsets of extracted members
+    extracted: BTreeSet<u64>,
+}
+
+// parsed input file
+enum Input<'data> {
+    Object(String, object::File<'data>),
+    // -R, --just-symbols
+    JustSymbols(String, object::File<'data>),
+    Archive(Archive<'data>),
+}
+
+// we want our own Relocation & RelocationTarget struct for easier handling
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RelocationTarget {
+    // relocation against section with additional offset
+    Section((String, u64)),
+    // relocation against symbol
+    Symbol(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relocation {
+    // offset into the output section
+    offset: u64,
+    kind: object::RelocationKind,
+    encoding: object::RelocationEncoding,
+    size: u8,
+    // R_X86_64_* type in the input object, R_X86_64_NONE if created by
+    // the linker
+    r_type: u32,
+    addend: i64,
+    target: RelocationTarget,
+}
+
+#[derive(Debug)]
+pub struct Symbol {
+    // reside in which section
+    section_name: String,
+    // offset into the output section
+    offset: u64,
+    // index of the defining input section in the output section, kept up to
+    // date when input sections are rearranged, none for symbols in merged
+    // sections and linker-synthesized symbols
+    input_section: Option<usize>,
+    // indices in output .strtab
+    symbol_name_string_id: Option<StringId>,
+    // indices in output .dynstr
+    symbol_name_dynamic_string_id: Option<StringId>,
+    // local or global
+    is_global: bool,
+    // weak global
+    is_weak: bool,
+    // a plt symbol to dynamic library
+    is_plt: bool,
+    // STT_* type
+    st_type: u8,
+    // st_size
+    size: u64,
+    // index into file symbols of the object defining this symbol
+    file_symbol: Option<usize>,
+    // input file or archive member defining this symbol, none for linker-synthesized symbols
+    defined_by: Option<String>,
+    // input files referencing this symbol
+    referenced_by: Vec<String>,
+    // address of plt entry is used as the function address in executable
+    canonical_plt: bool,
+    // written to .symtab, false for local symbols discarded by -x/-X
+    in_symtab: bool,
+    // STV_* visibility
+    visibility: u8,
+}
+
+impl Symbol {
+    // hidden and internal globals become local in the output
+    fn is_global_binding(&self) -> bool {
+        self.is_global && self.visibility != STV_HIDDEN && self.visibility != STV_INTERNAL
+    }
+
+    // functions, for -Bsymbolic-functions
+    fn is_function(&self) -> bool {
+        self.st_type == STT_FUNC || self.st_type == STT_GNU_IFUNC
+    }
+
+    // undefined weak, resolved to zero or imported
+    fn is_undefined_weak(&self) -> bool {
+        self.is_weak && (self.is_plt || self.section_name == UNDEFINED_SECTION)
+    }
+
+    // indirect function, address is the resolver
+    fn is_ifunc(&self) -> bool {
+        self.st_type == STT_GNU_IFUNC
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DynamicSymbol {
+    name: String,
+    // (needed file, version name) of symbols imported from versioned shared library
+    version: Option<(String, String)>,
+    // st_size of data object defined in shared library, which can be copied
+    object_size: Option<u64>,
+    // thread-local variable defined in shared library, which has no plt entry
+    is_tls: bool,
+    // st_type and weak binding of the definition in shared library, taken
+    // by the symbol imported or copied from it
+    st_type: u8,
+    is_weak: bool,
+}
+
+// CIE or FDE in .eh_frame
+#[derive(Debug)]
+pub struct EhFrameRecord {
+    // including the length field
+    content: Vec<u8>,
+    // relocations with offsets into this record
+    relocations: Vec<Relocation>,
+    // index of the CIE in records of the same object, none for CIEs
+    cie: Option<usize>,
+    // FDE covering code kept in the output
+    is_live: bool,
+}
+
+// .eh_frame of an input object
+#[derive(Debug)]
+pub struct EhFrame {
+    file_name: String,
+    records: Vec<EhFrameRecord>,
+}
+
+// tentative definition from SHN_COMMON, allocated in .bss unless defined elsewhere
+#[derive(Debug)]
+pub struct CommonSymbol {
+    size: u64,
+    alignment: u64,
+    // defined in SHN_X86_64_LCOMMON by -mcmodel=medium/large, allocated in .lbss
+    large: bool,
+    // file with the largest definition
+    file_name: String,
+    // index into file symbols of that file
+    file_symbol: usize,
+}
+
+// an input section placed in output section
+#[derive(Debug, Clone)]
+pub struct InputSection {
+    // file name, archive members are named as archive(member)
+    pub file_name: String,
+    pub name: String,
+    // offset into the output section
+    pub offset: u64,
+    pub size: u64,
+    // SHF_GNU_RETAIN, kept by --gc-sections
+    pub retain: bool,
+}
+
+#[derive(Default, Debug)]
+pub struct OutputSection {
+    pub name: String,
+    pub content: Vec<u8>,
+    // input sections placed in this section
+    pub input_sections: Vec<InputSection>,
+    // offset from ELF load address
+    pub offset: u64,
+    // relocations in this section
+    pub relocations: Vec<Relocation>,
+    pub is_executable: bool,
+    pub is_writable: bool,
+    pub is_bss: bool,
+    // thread-local storage, part of the TLS template
+    pub is_tls: bool,
+    // SHT_NOTE, described by PT_NOTE
+    pub is_note: bool,
+    // SHF_MERGE | SHF_STRINGS, made of NUL-terminated strings
+    pub is_strings: bool,
+    // position in SECTIONS of linker scripts
+    pub script_order: Option<usize>,
+    // INSERT of linker scripts
+    pub insert: Option<Insert>,
+    // maximum alignment of input sections
+    pub alignment: u64,
+    // indices in output ELF
+    pub section_index: Option<SectionIndex>,
+    pub name_string_id: Option<StringId>,
+}
+
+// TLS template covering .tdata and .tbss, described by PT_TLS
+#[derive(Default, Debug)]
+pub struct TlsSegment {
+    // offset from ELF load address
+    pub offset: u64,
+    // initialized part, from .tdata
+    pub file_size: u64,
+    // initialized part plus zero-filled .tbss
+    pub mem_size: u64,
+    pub alignment: u64,
+}
+
+// loadable segment, described by PT_LOAD
+#[derive(Default, Debug)]
+pub struct LoadSegment {
+    // offset from ELF load address
+    pub offset: u64,
+    pub size: u64,
+    // virtual address, differs from load address + offset after a section
+    // at a fixed address
+    pub address: u64,
+    // physical address, differs from the virtual address after AT>region
+    pub physical_address: u64,
+    // PF_R, PF_W and PF_X
+    pub flags: u32,
+}
+
+// output sections at addresses fixed by --section-start and linker scripts
+#[derive(Default, Debug)]
+pub struct FixedAddresses {
+    // output section => (virtual address, physical address)
+    pub addresses: BTreeMap<String, (u64, u64)>,
+    // sections continuing the previous one in memory on the same page, which
+    // share its segment
+    pub joined: BTreeSet<String>,
+}
+
+// values of linker script expressions in section addresses
+struct LayoutContext<'a> {
+    memory: &'a [MemoryRegion],
+    // end of the last section placed by linker scripts
+    dot: u64,
+    // symbols assigned by linker scripts so far
+    values: &'a BTreeMap<String, u64>,
+}
+
+impl crate::script::Context for LayoutContext<'_> {
+    fn symbol(&self, name: &str) -> anyhow::Result<u64> {
+        match name {
+            "." => Ok(self.dot),
+            _ => self.values.get(name).copied().ok_or(anyhow!(
+                "Symbol {} is not supported in section addresses",
+                name
+            )),
+        }
+    }
+
+    fn function(&self, function: &str, name: &str) -> anyhow::Result<u64> {
+        memory_function(self.memory, function, name)
+    }
+}
+
+// values of linker script expressions in symbol assignments, from the final
+// layout
+struct SymbolContext<'a> {
+    memory: &'a [MemoryRegion],
+    // location counter
+    dot: u64,
+    // symbols assigned by linker scripts so far
+    values: &'a BTreeMap<String, u64>,
+    symbols: &'a BTreeMap<String, Symbol>,
+    output_sections: &'a BTreeMap<String, OutputSection>,
+    section_address: &'a BTreeMap<String, u64>,
+    load_segments: &'a [LoadSegment],
+}
+
+impl crate::script::Context for SymbolContext<'_> {
+    fn symbol(&self, name: &str) -> anyhow::Result<u64> {
+        if name == "." {
+            return Ok(self.dot);
+        }
+        if let Some(value) = self.values.get(name) {
+            return Ok(*value);
+        }
+        let symbol = self.symbols.get(name).ok_or(anyhow!(
+            "undefined symbol `{}' referenced in expression",
+            name
+        ))?;
+        Ok(self.section_address[&symbol.section_name] + symbol.offset)
+    }
+
+    fn function(&self, function: &str, name: &str) -> anyhow::Result<u64> {
+        match function {
+            "ADDR" | "LOADADDR" | "SIZEOF" => {
+                let output_section = self.output_sections.get(name).ok_or(anyhow!(
+                    "undefined section `{}' referenced in expression",
+                    name
+                ))?;
+                let address = self.section_address[name];
+                Ok(match function {
+                    "ADDR" => address,
+                    "LOADADDR" => {
+                        segment_physical_address(self.load_segments, output_section.offset)
+                    }
+                    _ => output_section.content.len() as u64,
+                })
+            }
+            "DEFINED" => {
+                Ok((self.values.contains_key(name) || self.symbols.contains_key(name)) as u64)
+            }
+            _ => memory_function(self.memory, function, name),
+        }
+    }
+}
+
+// notes of the same alignment, described by PT_NOTE
+#[derive(Default, Debug)]
+pub struct NoteSegment {
+    // offset from ELF load address
+    pub offset: u64,
+    pub size: u64,
+    pub alignment: u64,
+}
+
+// relocation to be applied by the dynamic linker
+#[derive(Debug)]
+pub struct DynamicRelocation {
+    // the relocated location is at offset of the output section
+    section_name: String,
+    offset: u64,
+    // index into .dynsym
+    symbol_index: u32,
+    r_type: u32,
+    addend: i64,
+    // address of target is added to addend if present
+    target: Option<RelocationTarget>,
+}
+
+#[derive(Default, Debug)]
+pub struct OutputRelocationSection {
+    pub relocations: Vec<DynamicRelocation>,
+    // offset from ELF load address
+    pub offset: u64,
+    // indices in output ELF
+    pub section_index: Option<SectionIndex>,
+    pub name_string_id: Option<StringId>,
+}
+
+// STT_FILE symbol preceding local symbols of an input object in .symtab
+#[derive(Debug)]
+pub struct FileSymbol {
+    name: String,
+    // recorded by STT_FILE in the object, kept even without local symbols
+    from_input: bool,
+    // indices in output .strtab
+    string_id: Option<StringId>,
+}
+
+// versions required from a shared library, emitted in .gnu.version_r
+#[derive(Default, Debug)]
+pub struct VersionNeed {
+    // soname of the library, same as DT_NEEDED
+    pub file: String,
+    pub versions: Vec<String>,
+    // indices in output ELF
+    pub file_string_id: Option<StringId>,
+    pub version_string_ids: Vec<StringId>,
+}
+
+#[derive(Default, Debug)]
+pub struct Needed {
+    pub name: String,
+    // indices in output ELF
+    pub name_string_id: Option<StringId>,
+}
+
+struct Linker<'a> {
+    opt: Opt,
+    files: Vec<ObjectFile>,
+    // --start-group/--end-group: ranges of indices into files
+    groups: Vec<Range<usize>>,
+
+    // section name => section
+    output_sections: BTreeMap<String, OutputSection>,
+
+    // symbol table: symbol name => symbol
+    symbols: BTreeMap<String, Symbol>,
+    // one per loaded object
+    file_symbols: Vec<FileSymbol>,
+
+    // --retain-symbols-file: symbols to keep in .symtab
+    retained_symbols: Option<BTreeSet<String>>,
+    // symbols defined by the linker, whose addresses come from the final layout
+    layout_symbols: Vec<String>,
+    // symbols assigned by linker scripts, evaluated after the final layout
+    script_symbols: BTreeSet<String>,
+
+    // symbol name => most constraining visibility of its references and definitions
+    global_visibility: BTreeMap<String, u8>,
+
+    // symbol name => file defining it
+    defined_in: BTreeMap<String, String>,
+    // symbol name => common symbol, merged from all files
+    common_symbols: BTreeMap<String, CommonSymbol>,
+    // (output section, entry size) => entry content => offset of SHF_MERGE entries
+    merged_entries: BTreeMap<(String, u64), BTreeMap<Vec<u8>, u64>>,
+    // .eh_frame of each object, merged into output .eh_frame after layout
+    eh_frames: Vec<EhFrame>,
+    // symbols standing for records of input .eh_frame targeted by section
+    // relocations: name => (index into eh_frames, offset of the record)
+    eh_frame_anchors: BTreeMap<String, (usize, u64)>,
+    // FDEs in output .eh_frame for .eh_frame_hdr: (offset, pc_begin target
+    // and addend)
+    eh_frame_fdes: Vec<(u64, RelocationTarget, i64)>,
+    // GNU properties with 4-byte values of each object, merged into output
+    // .note.gnu.property
+    gnu_properties: Vec<BTreeMap<u32, u32>>,
+    // symbol name => files referencing it
+    referenced_by: BTreeMap<String, Vec<String>>,
+    // symbol name => whether all references from objects are weak
+    weak_references: BTreeMap<String, bool>,
+
+    // symbol name => definition in the first shared library defining it
+    shared_definitions: BTreeMap<String, DynamicSymbol>,
+
+    // dynamic symbols are saved in two parts:
+    // plt dynamic symbols that are UNDEF
+    plt_dynamic_symbols: Vec<DynamicSymbol>,
+    // other defined dynamic symbols, sorted by hash bucket
+    dynamic_symbols: Vec<DynamicSymbol>,
+
+    // non-alloc input sections that are not copied to output
+    discarded_sections: Vec<InputSection>,
+    // non-alloc output sections like .comment, placed after the loaded image
+    nonalloc_sections: BTreeMap<String, OutputSection>,
+    // --separate-debug-file: debug sections written to the debug file
+    separate_debug_sections: BTreeMap<String, OutputSection>,
+
+    // -T: commands of linker scripts
+    script: Script,
+
+    // section address => offset
+    section_address: BTreeMap<String, u64>,
+
+    // class and data encoding of output
+    format: ElfFormat,
+    // elf writer
+    writer: Writer<'a>,
+
+    load_address: u64,
+
+    // -z max-page-size: alignment of segments
+    max_page_size: u64,
+    // -z common-page-size: alignment of sections
+    common_page_size: u64,
+
+    // dynamic, dynsym, dynstr, hash, gnu_hash
+    dynamic_section_index: SectionIndex,
+    dynamic_section_offset: u64,
+    dynsym_section_index: SectionIndex,
+    dynsym_section_offset: u64,
+    dynstr_section_offset: u64,
+    hash_section_offset: u64,
+    gnu_hash_section_offset: u64,
+    dynamic_entries_count: usize,
+    soname_dynamic_string_index: Option<StringId>,
+    runpath_dynamic_string_index: Option<StringId>,
+
+    // program header offset & len
+    phdr_offset: usize,
+    phdr_len: usize,
+
+    // dynamically link against shared libraries
+    dynamic_link: bool,
+    needed: Vec<Needed>,
+    // shared libraries in command line
+    shared_libraries: Vec<SharedLibrary>,
+    // symbol versions required from shared libraries
+    version_needs: Vec<VersionNeed>,
+    gnu_versym_section_offset: u64,
+    gnu_verneed_section_offset: u64,
+
+    // output relocations
+    output_relocations: BTreeMap<String, OutputRelocationSection>,
+
+    // whether the stack should be executable, see .note.GNU-stack
+    exec_stack: bool,
+
+    // whether all objects are marked IBT compatible, then plt entries start
+    // with endbr64
+    ibt: bool,
+
+    // thread-local storage template, if any
+    tls_segment: Option<TlsSegment>,
+    // PT_LOAD segments in file order
+    load_segments: Vec<LoadSegment>,
+    // PT_NOTE segments in file order
+    note_segments: Vec<NoteSegment>,
+
+    // whether dynamic relocations modify read-only sections
+    text_relocation: bool,
+    // whether shared library uses the static tls model, which prevents it
+    // from being loaded by dlopen() on some systems
+    static_tls: bool,
+}
+
+impl<'a> Linker<'a> {
+    fn link(opt: &Opt) -> anyhow::Result<()> {
+        info!("Link with options: {opt:?}");
+
+        let opt = path_resolution(opt)?;
+        info!("Options after path resolution: {opt:?}");
+        let static_pie = opt.static_pie;
+
+        let mut arena = Arena::new();
+        let mut buffer = vec![];
+        let mut linker = Linker {
+            opt,
+            files: vec![],
+            groups: vec![],
+            output_sections: BTreeMap::new(),
+            symbols: BTreeMap::new(),
+            file_symbols: vec![],
+            retained_symbols: None,
+            layout_symbols: vec![],
+            script_symbols: BTreeSet::new(),
+            global_visibility: BTreeMap::new(),
+            defined_in: BTreeMap::new(),
+            common_symbols: BTreeMap::new(),
+            merged_entries: BTreeMap::new(),
+            eh_frames: vec![],
+            eh_frame_anchors: BTreeMap::new(),
+            eh_frame_fdes: vec![],
+            gnu_properties: vec![],
+            referenced_by: BTreeMap::new(),
+            weak_references: BTreeMap::new(),
+            discarded_sections: vec![],
+            nonalloc_sections: BTreeMap::new(),
+            separate_debug_sections: BTreeMap::new(),
+            section_address: BTreeMap::new(),
+            format: ElfFormat::X86_64,
+            writer: ElfFormat::X86_64.writer(&mut buffer),
+            load_address: 0,
+            max_page_size: DEFAULT_PAGE_SIZE,
+            common_page_size: DEFAULT_PAGE_SIZE,
+            dynamic_section_index: SectionIndex(0),
+            dynamic_section_offset: 0,
+            dynamic_entries_count: 0,
+            dynsym_section_index: SectionIndex(0),
+            dynsym_section_offset: 0,
+            dynstr_section_offset: 0,
+            hash_section_offset: 0,
+            gnu_hash_section_offset: 0,
+            soname_dynamic_string_index: None,
+            runpath_dynamic_string_index: None,
+            phdr_offset: 0,
+            phdr_len: 0,
+            // static pie has a .dynamic section for its startup code
+            dynamic_link: static_pie,
+            needed: vec![],
+            shared_libraries: vec![],
+            version_needs: vec![],
+            gnu_versym_section_offset: 0,
+            gnu_verneed_section_offset: 0,
+            output_relocations: BTreeMap::new(),
+            dynamic_symbols: vec![],
+            shared_definitions: BTreeMap::new(),
+            plt_dynamic_symbols: vec![],
+            exec_stack: false,
+            ibt: false,
+            tls_segment: None,
+            load_segments: vec![],
+            note_segments: vec![],
+            text_relocation: false,
+            static_tls: false,
+            script: Script::default(),
+        };
+        linker.read_files()?;
+        linker.parse_files()?;
+        linker.allocate_common_symbols();
+        linker.handle_orphan_sections()?;
+        linker.gc_sections();
+        linker.sort_init_fini_arrays();
+        linker.order_sections()?;
+        linker.build_eh_frame();
+        linker.merge_gnu_properties();
+        linker.add_build_id_note();
+        linker.add_linker_ident();
+        linker.separate_debug_sections();
+        linker.check_shared_libraries()?;
+        linker.define_linker_symbols()?;
+        linker.import_undefined_weak();
+        linker.relax_tls()?;
+        linker.relax_gotpcrelx();
+        linker.generate_plt()?;
+        linker.resolve_undefined_weak();
+        linker.generate_got();
+        linker.collect_references();
+        linker.check_undefined_symbols()?;
+        linker.scan_relocations()?;
+        linker.reserve(&mut arena)?;
+        linker.relocate()?;
+        linker.write()?;
+        linker.write_map()?;
+
+        // the build ID covers the whole output, with zeros in its place
+        let build_id = linker.opt.build_id.clone().zip(
+            linker
+                .output_sections
+                .get(".note.gnu.build-id")
+                .map(|section| section.offset as usize + section.content.len()),
+        );
+        // the CRC of the debug file ends .gnu_debuglink
+        let debug_link = linker.opt.separate_debug_file.clone().zip(
+            linker
+                .nonalloc_sections
+                .get(".gnu_debuglink")
+                .map(|section| section.offset as usize + section.content.len()),
+        );
+        let debug_sections = std::mem::take(&mut linker.separate_debug_sections);
+        let format = linker.format;
+        let output = linker.opt.output.clone().unwrap();
+        if let Some((style, end)) = build_id {
+            let id = compute_build_id(&style, &buffer)?;
+            info!("Build ID is {:02x?}", id);
+            buffer[end - id.len()..end].copy_from_slice(&id);
+        }
+        if let Some((path, end)) = debug_link {
+            let debug = write_debug_file(format, &buffer, &debug_sections)?;
+            buffer[end - 4..end].copy_from_slice(&format.u32_bytes(crc32(&debug)));
+            info!("Writing debug file {}", path);
+            std::fs::write(&path, debug)?;
+        }
+
+        // done, save to file
+        info!("Writing to executable {}", output);
+        std::fs::write(&output, buffer)?;
+
+        // make executable
+        let mut perms = std::fs::metadata(&output)?.permissions();
+        perms.set_mode(0o755);
+        std::fs::set_permissions(&output, perms)?;
+
+        Ok(())
+    }
+
+    fn read_files(&mut self) -> anyhow::Result<()> {
+        let Linker {
+            opt,
+            files,
+            groups,
+            retained_symbols,
+            script,
+            ..
+        } = self;
+
+        // INPUT and GROUP of -T scripts follow other input files
+        let mut script_inputs = vec![];
+        for name in &opt.scripts {
+            info!("Reading linker script {}", name);
+            let content =
+                std::fs::read_to_string(name).context(format!("Reading file {}", name))?;
+            let mut parsed = parse_script(name, &content)?;
+            for section in &parsed.sections {
+                if let Some(Insert::Before(target) | Insert::After(target)) = &section.insert {
+                    if default_position(target).is_none() {
+                        bail!(
+                            "{}: INSERT target section {} is not in the default linker script",
+                            name,
+                            target
+                        );
+                    }
+                }
+            }
+            script_inputs.push((name, std::mem::take(&mut parsed.inputs)));
+            script.extend(parsed);
+        }
+
+        if let Some(retain_symbols_file) = &opt.retain_symbols_file {
+            // one symbol name per line
+            let content = std::fs::read_to_string(retain_symbols_file)
+                .context(format!("Reading file {}", retain_symbols_file))?;
+            *retained_symbols = Some(
+                content
+                    .lines()
+                    .map(|line| line.trim())
+                    .filter(|line| !line.is_empty())
+                    .map(|line| line.to_string())
+                    .collect(),
+            );
+        }
+
+        // read files
+        let mut group_start = None;
+        for obj_file in &opt.obj_file {
+            match obj_file {
+                ObjectFileOpt::File(file_opt) => read_input_file(
+                    opt,
+                    &file_opt.name,
+                    file_opt.as_needed,
+                    group_start.is_some(),
+                    files,
+                    groups,
+                    script,
+                )?,
+                ObjectFileOpt::JustSymbols(name) => {
+                    info!("Reading symbols of {}", name);
+                    files.push(ObjectFile {
+                        name: name.clone(),
+                        as_needed: false,
+                        just_symbols: true,
+                        content: std::fs::read(name).context(format!("Reading file {}", name))?,
+                    });
+                }
+                ObjectFileOpt::Library(_) => unreachable!("Path resolution is not working"),
+                ObjectFileOpt::StartGroup => {
+                    if group_start.is_some() {
+                        bail!("Nested --start-group is not allowed");
+                    }
+                    group_start = Some(files.len());
+                }
+                ObjectFileOpt::EndGroup => {
+                    let start = group_start
+                        .take()
+                        .ok_or(anyhow!("--end-group without --start-group"))?;
+                    groups.push(start..files.len());
+                }
+            }
+        }
+        if let Some(start) = group_start {
+            // unterminated group extends to the end
+            groups.push(start..files.len());
+        }
+        for (name, inputs) in script_inputs {
+            read_script_inputs(opt, name, inputs, false, false, files, groups, script)?;
+        }
+
+        Ok(())
+    }
+
+    fn parse_files(&mut self) -> anyhow::Result<()> {
+        // parsed objects borrow file content, take files out of self meanwhile
+        let files = std::mem::take(&mut self.files);
+        let mut inputs = vec![];
+        for file in &files {
+            info!("Parsing {}", file.name);
+            if file.name.ends_with(".a") && !file.just_symbols {
+                // archive
+                let archive = object::read::archive::ArchiveFile::parse(file.content.as_slice())
+                    .context(format!("Parsing file {} as archive", file.name))?;
+                inputs.push(Input::Archive(Archive {
+                    name: file.name.clone(),
+                    data: file.content.as_slice(),
+                    archive,
+                    extracted: BTreeSet::new(),
+                }));
+            } else {
+                // object
+                let obj = object::File::parse(file.content.as_slice())
+                    .context(format!("Parsing file {} as object", file.name))?;
+                if file.just_symbols {
+                    inputs.push(Input::JustSymbols(file.name.clone(), obj));
+                } else {
+                    inputs.push(Input::Object(file.name.clone(), obj));
+                }
+            }
+        }
+
+        // load inputs in command line order, archive members are extracted
+        // only when they define a currently undefined symbol
+        let mut index = 0;
+        while index < inputs.len() {
+            // archives in --start-group/--end-group are searched repeatedly
+            let end = self
+                .groups
+                .iter()
+                .find(|group| group.start == index)
+                .map_or(index + 1, |group| group.end.max(index + 1));
+            for input in &mut inputs[index..end] {
+                match input {
+                    Input::Object(name, obj) => self.load_object(name, obj)?,
+                    Input::JustSymbols(name, obj) => self.load_just_symbols(name, obj)?,
+                    Input::Archive(archive) => {
+                        self.extract_archive(archive)?;
+                    }
+                }
+            }
+            if end - index > 1 {
+                loop {
+                    let mut extracted = false;
+                    for input in &mut inputs[index..end] {
+                        if let Input::Archive(archive) = input {
+                            extracted |= self.extract_archive(archive)?;
+                        }
+                    }
+                    if !extracted {
+                        break;
+                    }
+                }
+            }
+            index = end;
+        }
+        drop(inputs);
+        self.files = files;
+
+        let Linker {
+            opt,
+            output_sections,
+            symbols,
+            dynamic_symbols,
+            shared_definitions,
+            plt_dynamic_symbols,
+            global_visibility,
+            version_needs,
+            defined_in,
+            referenced_by,
+            shared_libraries,
+            ..
+        } = self;
+
+        // apply visibility, hidden and internal symbols are not exported
+        for (name, visibility) in global_visibility.iter() {
+            if let Some(symbol) = symbols.get_mut(name) {
+                symbol.visibility = *visibility;
+            }
+        }
+
+        // export surviving global definitions in dynsym, including those of
+        // archive members extracted after the definition was first seen
+        if opt.shared {
+            for (name, symbol) in symbols.iter() {
+                // symbols from --just-symbols files are not part of the output
+                if symbol.is_global_binding() && symbol.file_symbol.is_some() {
+                    dynamic_symbols.push(DynamicSymbol {
+                        name: name.clone(),
+                        version: None,
+                        object_size: None,
+                        is_tls: false,
+                        st_type: STT_NOTYPE,
+                        is_weak: false,
+                    });
+                }
+            }
+        }
+
+        // symbols referenced by relocations in object files
+        let referenced: BTreeSet<String> = output_sections
+            .values()
+            .flat_map(|section| section.relocations.iter())
+            .filter_map(|relocation| match &relocation.target {
+                RelocationTarget::Symbol(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+
+        // import referenced symbols from shared libraries on demand, while
+        // definitions in object files interpose those of shared libraries:
+        // they are exported instead for the shared libraries to bind to them
+        // at runtime
+        for (name, sym) in shared_definitions.iter() {
+            match symbols.get(name) {
+                Some(symbol)
+                    if symbol.is_global_binding()
+                        && !dynamic_symbols.iter().any(|s| s.name == *name) =>
+                {
+                    info!("Definition of {} interposes shared library", name);
+                    dynamic_symbols.push(DynamicSymbol {
+                        name: name.clone(),
+                        version: None,
+                        object_size: None,
+                        is_tls: false,
+                        st_type: STT_NOTYPE,
+                        is_weak: false,
+                    });
+                }
+                None if referenced.contains(name) => {
+                    info!("Importing {} from shared library", name);
+                    plt_dynamic_symbols.push(sym.clone());
+                }
+                _ => {}
+            }
+        }
+
+        // definitions referenced by shared libraries are exported for them to
+        // bind to at runtime, even without --export-dynamic
+        if !opt.shared {
+            for (name, files) in referenced_by.iter() {
+                if symbols
+                    .get(name)
+                    .is_some_and(|symbol| !symbol.is_plt && symbol.is_global_binding())
+                    && files
+                        .iter()
+                        .any(|file| shared_libraries.iter().any(|lib| lib.name == *file))
+                    && !dynamic_symbols.iter().any(|s| s.name == *name)
+                {
+                    info!("Exporting {} referenced by shared library", name);
+                    dynamic_symbols.push(DynamicSymbol {
+                        name: name.clone(),
+                        version: None,
+                        object_size: None,
+                        is_tls: false,
+                        st_type: STT_NOTYPE,
+                        is_weak: false,
+                    });
+                }
+            }
+        }
+
+        // collect versions required by imported symbols
+        for (file, version) in plt_dynamic_symbols
+            .iter()
+            .filter_map(|sym| sym.version.as_ref())
+        {
+            let index = match version_needs.iter().position(|need| need.file == *file) {
+                Some(index) => index,
+                None => {
+                    version_needs.push(VersionNeed {
+                        file: file.clone(),
+                        ..VersionNeed::default()
+                    });
+                    version_needs.len() - 1
+                }
+            };
+            if !version_needs[index].versions.contains(version) {
+                version_needs[index].versions.push(version.clone());
+            }
+        }
+
+        // copy relocations: data objects of shared libraries referenced by
+        // executable are copied to .bss, and the copy is exported instead
+        if !opt.shared {
+            let (copied, imported) =
+                std::mem::take(plt_dynamic_symbols)
+                    .into_iter()
+                    .partition(|sym: &DynamicSymbol| {
+                        sym.object_size.is_some()
+                            && referenced.contains(&sym.name)
+                            && !symbols.contains_key(&sym.name)
+                    });
+            *plt_dynamic_symbols = imported;
+            for sym in copied {
+                let size = sym.object_size.unwrap();
+                let bss = output_sections
+                    .entry(".bss".to_string())
+                    .or_insert_with(|| OutputSection {
+                        name: ".bss".to_string(),
+                        is_writable: true,
+                        is_bss: true,
+                        ..OutputSection::default()
+                    });
+                // align like the compiler does for objects of this size
+                let align = size.next_power_of_two().min(16);
+                let offset = (bss.content.len() as u64).next_multiple_of(align);
+                bss.content.resize((offset + size) as usize, 0);
+                bss.input_sections.push(InputSection {
+                    file_name: defined_in[&sym.name].clone(),
+                    name: ".dynbss".to_string(),
+                    offset,
+                    size,
+                    retain: false,
+                });
+                info!(
+                    "Copying {} of size {} to .bss+{:#x}",
+                    sym.name, size, offset
+                );
+
+                symbols.insert(
+                    sym.name.clone(),
+                    Symbol {
+                        section_name: ".bss".to_string(),
+                        offset,
+                        input_section: None,
+                        symbol_name_string_id: None,
+                        symbol_name_dynamic_string_id: None,
+                        is_global: true,
+                        is_weak: sym.is_weak,
+                        is_plt: false,
+                        st_type: STT_OBJECT,
+                        size,
+                        file_symbol: None,
+                        defined_by: Some(defined_in[&sym.name].clone()),
+                        referenced_by: vec![],
+                        canonical_plt: false,
+                        in_symtab: true,
+                        visibility: STV_DEFAULT,
+                    },
+                );
+                dynamic_symbols.push(sym);
+            }
+        }
+
+        if let Some(execstack) = opt.z_options.execstack {
+            // -z execstack/noexecstack overrides
+            self.exec_stack = execstack;
+        }
+
+        if opt.shared || self.dynamic_link {
+            // add _DYNAMIC symbol
+            symbols.insert(
+                "_DYNAMIC".to_string(),
+                Symbol {
+                    section_name: ".dynamic".to_string(),
+                    offset: 0,
+                    input_section: None,
+                    symbol_name_string_id: None,
+                    symbol_name_dynamic_string_id: None,
+                    is_global: false,
+                    is_weak: false,
+                    is_plt: false,
+                    st_type: STT_OBJECT,
+                    size: 0,
+                    file_symbol: None,
+                    defined_by: None,
+                    referenced_by: vec![],
+                    canonical_plt: false,
+                    in_symtab: true,
+                    visibility: STV_DEFAULT,
+                },
+            );
+        }
+
+        // sort dynamic symbols by gnu hash bucket, symbols of the same
+        // bucket form a chain
+        let GnuHashLayout { bucket_count, .. } = gnu_hash_layout(opt, dynamic_symbols.len());
+        dynamic_symbols.sort_by_key(|sym| {
+            let hash = object::elf::gnu_hash(sym.name.as_bytes());
+            hash % bucket_count
+        });
+
+        if !opt.shared && self.dynamic_link && !opt.static_pie && !opt.no_dynamic_linker {
+            let mut interp = OutputSection {
+                name: ".interp".to_string(),
+                ..OutputSection::default()
+            };
+            let dynamic_linker = match &opt.dynamic_linker {
+                Some(dynamic_linker) => dynamic_linker.as_str(),
+                None => {
+                    // shared libraries from a musl sysroot imply the musl
+                    // dynamic linker
+                    let musl = shared_libraries.iter().any(|library| {
+                        library.name.contains("musl")
+                            || library.soname.as_ref().is_some_and(|s| s.contains("musl"))
+                    });
+                    let dynamic_linker = default_dynamic_linker(opt.emulation.as_deref(), musl)?;
+                    info!("Using default dynamic linker {}", dynamic_linker);
+                    dynamic_linker
+                }
+            };
+            interp.content.extend_from_slice(dynamic_linker.as_bytes());
+            // NULL terminated string
+            interp.content.push(0);
+            output_sections.insert(".interp".to_string(), interp);
+        }
+        Ok(())
+    }
+
+    /// Whether symbol is referenced but not defined yet
+    fn is_undefined(&self, name: &str) -> bool {
+        // the entry point, -u and --require-defined symbols count as undefined
+        // like GNU ld, while weak references do not extract archive members
+        (self.referenced_by.contains_key(name)
+            || (!self.opt.shared && name == "_start")
+            || self.opt.require_defined.iter().any(|symbol| symbol == name)
+            || self.opt.undefined.iter().any(|symbol| symbol == name))
+            && !self.defined_in.contains_key(name)
+            && !self.is_weak_reference(name)
+    }
+
+    /// Whether symbol is only referenced by undefined weak symbols
+    fn is_weak_reference(&self, name: &str) -> bool {
+        self.weak_references.get(name).copied().unwrap_or(false)
+    }
+
+    /// Extract archive members that define currently undefined symbols
+    /// until no more members are needed, returns whether any was extracted
+    fn extract_archive(&mut self, archive: &mut Archive) -> anyhow::Result<bool> {
+        let mut res = false;
+        loop {
+            let mut extracted = false;
+            let symbols = archive.archive.symbols()?.ok_or(anyhow!(
+                "{}: archive has no index; run ranlib to add one",
+                archive.name
+            ))?;
+            for symbol in symbols {
+                let symbol = symbol?;
+                let offset = symbol.offset();
+                if archive.extracted.contains(&offset.0)
+                    || !self.is_undefined(std::str::from_utf8(symbol.name())?)
+                {
+                    continue;
+                }
+
+                archive.extracted.insert(offset.0);
+                let member = archive.archive.member(offset)?;
+                let name = format!("{}({})", archive.name, std::str::from_utf8(member.name())?);
+                info!(
+                    "Extracting {} for symbol {}",
+                    name,
+                    std::str::from_utf8(symbol.name())?
+                );
+                let obj = object::File::parse(member.data(archive.data)?)
+                    .context(format!("Parsing file {} as object", name))?;
+                self.load_object(&name, &obj)?;
+                extracted = true;
+            }
+            if !extracted {
+                break;
+            }
+            res = true;
+        }
+        Ok(res)
+    }
+
+    /// Define global symbols of -R file at their absolute addresses,
+    /// without copying any sections
+    fn load_just_symbols(&mut self, file_name: &str, obj: &object::File) -> anyhow::Result<()> {
+        let Linker {
+            opt,
+            symbols,
+            defined_in,
+            ..
+        } = self;
+
+        if opt.trace {
+            println!("{}", file_name);
+        }
+        for symbol in obj.symbols() {
+            if !symbol.is_global()
+                || symbol.is_undefined()
+                || symbol.kind() == object::SymbolKind::Section
+                || symbol.kind() == object::SymbolKind::File
+            {
+                continue;
+            }
+            let name = symbol.name()?;
+            trace_symbol(opt, file_name, name, true);
+            if symbols.contains_key(name) {
+                if !opt.z_options.muldefs {
+                    bail!(
+                        "{}: multiple definition of `{}`; {}: first defined here",
+                        file_name,
+                        demangle(opt, name),
+                        symbols[name].defined_by.as_deref().unwrap_or_default()
+                    );
+                }
+                continue;
+            }
+            info!(
+                "Defining absolute symbol {} = {:#x}",
+                name,
+                symbol.address()
+            );
+            defined_in.insert(name.to_string(), file_name.to_string());
+            symbols.insert(
+                name.to_string(),
+                Symbol {
+                    section_name: ABSOLUTE_SECTION.to_string(),
+                    offset: symbol.address(),
+                    input_section: None,
+                    symbol_name_string_id: None,
+                    symbol_name_dynamic_string_id: None,
+                    is_global: true,
+                    is_weak: symbol.is_weak(),
+                    is_plt: false,
+                    st_type: symbol_type(&symbol),
+                    size: symbol.size(),
+                    file_symbol: None,
+                    defined_by: Some(file_name.to_string()),
+                    referenced_by: vec![],
+                    canonical_plt: false,
+                    in_symtab: true,
+                    visibility: STV_DEFAULT,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Load sections and symbols of an object file or shared library
+    fn load_object(&mut self, name: &str, obj: &object::File) -> anyhow::Result<()> {
+        let format = self.format;
+        let Linker {
+            opt,
+            output_sections,
+            symbols,
+            shared_definitions,
+            discarded_sections,
+            nonalloc_sections,
+            defined_in,
+            common_symbols,
+            merged_entries,
+            eh_frames,
+            eh_frame_anchors,
+            gnu_properties,
+            referenced_by,
+            weak_references,
+            global_visibility,
+            script,
+            ..
+        } = self;
+
+        let _span = info_span!("file", name).entered();
+        if opt.trace {
+            println!("{}", name);
+        }
+        match obj {
+            object::File::Elf64(elf) => {
+                if elf.kind() == ObjectKind::Dynamic {
+                    // linked against dynamic library, recorded by its soname
+                    let library = read_shared_library(name, elf)?;
+                    let needed = library.soname.clone().unwrap_or(name.to_string());
+                    if self.needed.iter().any(|entry| entry.name == needed) {
+                        info!("Skipping {} already linked as {}", name, needed);
+                        return Ok(());
+                    }
+                    self.dynamic_link = true;
+                    self.needed.push(Needed {
+                        name: needed.clone(),
+                        name_string_id: None,
+                    });
+                    self.shared_libraries.push(library);
+
+                    // walk through its dynamic symbols
+                    let file_name = name;
+                    let versions = elf.elf_section_table().versions(elf.endian(), elf.data())?;
+                    for symbol in elf.dynamic_symbols() {
+                        let name = symbol.name()?;
+                        let mut version = None;
+                        if let (Some(versions), false) = (&versions, symbol.is_undefined()) {
+                            let index = versions.version_index(elf.endian(), symbol.index());
+                            if index.is_hidden() {
+                                // only the default version is used for unversioned references
+                                info!("Skipping non-default version of {}", name);
+                                continue;
+                            }
+                            version = versions
+                                .version(index)?
+                                .map(|version| std::str::from_utf8(version.name()))
+                                .transpose()?
+                                .map(|version| version.to_string());
+                            if symbol.section() == object::SymbolSection::Absolute
+                                && version.as_deref() == Some(name)
+                            {
+                                // symbol naming a version definition
+                                continue;
+                            }
+                        }
+                        trace_symbol(opt, file_name, name, !symbol.is_undefined());
+                        if !symbol.is_undefined() {
+                            info!(
+                                "Defining dynamic symbol {} with version {:?}",
+                                name, version
+                            );
+                            if shared_definitions.contains_key(name) {
+                                // bound to the earlier shared library
+                                info!("Ignoring later definition of {}", name);
+                                continue;
+                            }
+                            shared_definitions.insert(
+                                name.to_string(),
+                                DynamicSymbol {
+                                    name: name.to_string(),
+                                    // versions are required from the soname
+                                    // like DT_NEEDED
+                                    version: version.map(|version| (needed.clone(), version)),
+                                    object_size: (symbol.kind() == object::SymbolKind::Data
+                                        && symbol.size() > 0)
+                                        .then_some(symbol.size()),
+                                    is_tls: symbol.kind() == object::SymbolKind::Tls,
+                                    st_type: symbol.elf_symbol().st_type(),
+                                    is_weak: symbol.is_weak(),
+                                },
+                            );
+                            // definitions in object files take precedence
+                            defined_in
+                                .entry(name.to_string())
+                                .or_insert(file_name.to_string());
+                        } else if !name.is_empty() {
+                            add_reference(referenced_by, name, file_name);
+                        }
+                    }
+                    return Ok(());
+                }
+
+                // .note.GNU-stack tells whether this object requires an
+                // executable stack, its absence means yes
+                match elf.section_by_name(".note.GNU-stack") {
+                    Some(section) => {
+                        if let object::SectionFlags::Elf { sh_flags } = section.flags() {
+                            if ((sh_flags as u32) & object::elf::SHF_EXECINSTR) != 0 {
+                                info!("Object requires executable stack");
+                                self.exec_stack = true;
+                            }
+                        }
+                    }
+                    None => {
+                        warn!("Missing .note.GNU-stack section implies executable stack");
+                        self.exec_stack = true;
+                    }
+                }
+
+                let file_name = name;
+
+                // name of STT_FILE symbol: the source file if recorded, or the object
+                let source_name = elf
+                    .symbols()
+                    .find(|symbol| symbol.kind() == object::SymbolKind::File)
+                    .and_then(|symbol| symbol.name().ok())
+                    .filter(|name| !name.is_empty());
+                self.file_symbols.push(FileSymbol {
+                    name: source_name.unwrap_or(file_name).to_string(),
+                    from_input: source_name.is_some(),
+                    string_id: None,
+                });
+                let file_symbol = self.file_symbols.len() - 1;
+
+                // input section index => (output section, offset into it)
+                let mut placements: BTreeMap<usize, (String, u64)> = BTreeMap::new();
+                // input section index => (non-alloc output section, offset into it)
+                let mut nonalloc_placements: BTreeMap<usize, (String, u64)> = BTreeMap::new();
+                // input section index => (entry size, output offsets of its entries)
+                let mut merged: BTreeMap<usize, (u64, Vec<u64>)> = BTreeMap::new();
+                // input section index => index of InputSection in its output
+                // section, for symbols defined in it
+                let mut input_indices: BTreeMap<usize, usize> = BTreeMap::new();
+                // input section indices of .eh_frame, pushed to eh_frames in
+                // order after the existing ones
+                let mut eh_frame_sections: BTreeSet<usize> = BTreeSet::new();
+                let eh_frame_base = eh_frames.len();
+                // input section index => size of reversed .ctors and .dtors
+                let mut reversed_sections: BTreeMap<usize, u64> = BTreeMap::new();
+                // GNU properties of this object
+                let mut properties: BTreeMap<u32, u32> = BTreeMap::new();
+                for section in elf.sections() {
+                    let name = section.name()?;
+                    if !name.is_empty() {
+                        let _span = info_span!("section", name).entered();
+                        let data = section.data()?;
+                        let (is_executable, is_writable, is_tls, retain, is_merge) = match section
+                            .flags()
+                        {
+                            object::SectionFlags::Elf { sh_flags } => {
+                                if ((sh_flags as u32) & object::elf::SHF_ALLOC) == 0 {
+                                    if name == ".comment" {
+                                        // merged with those of other objects
+                                        let out = nonalloc_sections
+                                            .entry(name.to_string())
+                                            .or_insert_with(comment_section);
+                                        merge_strings(&mut out.content, data);
+                                        continue;
+                                    }
+                                    if name.starts_with(".debug")
+                                        && !opt.strip_debug
+                                        && !opt.strip_all
+                                    {
+                                        // DWARF, relocated after layout
+                                        let data = section.uncompressed_data()?;
+                                        let out = nonalloc_sections
+                                            .entry(name.to_string())
+                                            .or_insert_with(OutputSection::default);
+                                        out.name = name.to_string();
+                                        let offset = (out.content.len() as u64)
+                                            .next_multiple_of(section.align().max(1));
+                                        out.content.resize(offset as usize, 0);
+                                        out.content.extend(data.iter());
+                                        out.input_sections.push(InputSection {
+                                            file_name: file_name.to_string(),
+                                            name: name.to_string(),
+                                            offset,
+                                            size: data.len() as u64,
+                                            retain: false,
+                                        });
+                                        out.is_strings |=
+                                            ((sh_flags as u32) & object::elf::SHF_STRINGS) != 0;
+                                        out.alignment = out.alignment.max(section.align());
+                                        nonalloc_placements
+                                            .insert(section.index().0, (name.to_string(), offset));
+                                        continue;
+                                    }
+                                    // non-alloc, skip
+                                    // symbol tables and relocations are not discarded
+                                    if section.kind() != object::SectionKind::Metadata {
+                                        discarded_sections.push(InputSection {
+                                            file_name: file_name.to_string(),
+                                            name: name.to_string(),
+                                            offset: 0,
+                                            size: section.size(),
+                                            retain: false,
+                                        });
+                                    }
+                                    continue;
+                                } else {
+                                    (
+                                        ((sh_flags as u32) & object::elf::SHF_EXECINSTR) != 0,
+                                        ((sh_flags as u32) & object::elf::SHF_WRITE) != 0,
+                                        ((sh_flags as u32) & object::elf::SHF_TLS) != 0,
+                                        ((sh_flags as u32) & object::elf::SHF_GNU_RETAIN) != 0,
+                                        ((sh_flags as u32) & object::elf::SHF_MERGE) != 0
+                                            && ((sh_flags as u32) & object::elf::SHF_STRINGS) == 0,
+                                    )
+                                }
+                            }
+                            _ => unimplemented!(),
+                        };
+
+                        if name == ".eh_frame" {
+                            // parsed into records, built after layout is known
+                            eh_frame_sections.insert(section.index().0);
+                            continue;
+                        }
+
+                        if name == ".note.gnu.property" {
+                            // merged with those of other objects
+                            if let Some(mut notes) = section
+                                .elf_section_header()
+                                .notes(elf.endian(), elf.data())?
+                            {
+                                while let Some(note) = notes.next()? {
+                                    let Some(mut iter) = note.gnu_properties(elf.endian()) else {
+                                        continue;
+                                    };
+                                    while let Some(property) = iter.next()? {
+                                        if property.pr_data().len() == 4 {
+                                            properties.insert(
+                                                property.pr_type(),
+                                                property.data_u32(elf.endian())?,
+                                            );
+                                        }
+                                    }
+                                }
+                            }
+                            continue;
+                        }
+
+                        if name == ".preinit_array" && opt.shared {
+                            // only run by the dynamic linker for executables
+                            bail!(
+                                "{}: .preinit_array section is not allowed in shared libraries",
+                                file_name
+                            );
+                        }
+
+                        // .ctors and .dtors are run backwards, unlike .init_array
+                        // and .fini_array
+                        let reversed: Vec<u8>;
+                        let data = if (has_section_prefix(name, ".ctors")
+                            || has_section_prefix(name, ".dtors"))
+                            && data.len() as u64 == section.size()
+                            && section.size().is_multiple_of(8)
+                        {
+                            reversed = data.chunks(8).rev().flatten().copied().collect();
+                            reversed_sections.insert(section.index().0, section.size());
+                            &reversed[..]
+                        } else {
+                            data
+                        };
+
+                        // copy to output, after existing content from this and
+                        // other object files
+                        // SECTIONS of linker scripts come first, then the
+                        // default linker script
+                        let script_section = script.find_output_section(file_name, name);
+                        let (section_name, keep, is_orphan) = match script_section {
+                            Some((index, keep)) => {
+                                (script.sections[index].name.as_str(), keep, false)
+                            }
+                            None => match DEFAULT_LAYOUT.find_output_section(file_name, name) {
+                                Some((index, keep)) => {
+                                    (DEFAULT_LAYOUT.sections[index].name.as_str(), keep, false)
+                                }
+                                None => (name, false, true),
+                            },
+                        };
+                        // --unique: a separate output section of the same name
+                        let unique = opt.unique.iter().any(|pattern| match pattern.as_str() {
+                            "" => is_orphan,
+                            pattern => glob_match(pattern, name),
+                        });
+                        let output_name = &if unique {
+                            unique_section_key(section_name, output_sections.len())
+                        } else {
+                            section_name.to_string()
+                        };
+                        let retain = retain || keep;
+                        let out = output_sections
+                            .entry(output_name.to_string())
+                            .or_insert_with(OutputSection::default);
+                        out.name = section_name.to_string();
+                        out.script_order = script_section.map(|(index, _keep)| index);
+                        out.insert = script_section
+                            .and_then(|(index, _keep)| script.sections[index].insert.clone());
+                        let offset =
+                            (out.content.len() as u64).next_multiple_of(section.align().max(1));
+                        out.content.resize(offset as usize, 0);
+                        let entsize = section.elf_section_header().sh_entsize(elf.endian());
+                        if is_merge
+                            && entsize > 0
+                            && data.len() as u64 == section.size()
+                            && section.size().is_multiple_of(entsize)
+                        {
+                            // SHF_MERGE: only copy entries not seen in other
+                            // sections of the same entry size
+                            let known = merged_entries
+                                .entry((output_name.to_string(), entsize))
+                                .or_default();
+                            let mut entries = vec![];
+                            for entry in data.chunks(entsize as usize) {
+                                let entry_offset =
+                                    *known.entry(entry.to_vec()).or_insert_with(|| {
+                                        let entry_offset = out.content.len() as u64;
+                                        out.content.extend(entry);
+                                        entry_offset
+                                    });
+                                entries.push(entry_offset);
+                            }
+                            info!(
+                                "Merged section {} of {} bytes into {} bytes",
+                                name,
+                                section.size(),
+                                out.content.len() as u64 - offset
+                            );
+                            merged.insert(section.index().0, (entsize, entries));
+                        } else {
+                            out.content.extend(data);
+                        }
+                        if (data.len() as u64) < section.size() {
+                            // handle bss, extend with zero
+                            out.content.resize(
+                                out.content.len() - data.len() + section.size() as usize,
+                                0,
+                            );
+                        }
+                        out.input_sections.push(InputSection {
+                            file_name: file_name.to_string(),
+                            name: name.to_string(),
+                            offset,
+                            size: out.content.len() as u64 - offset,
+                            retain,
+                        });
+                        out.is_executable |= is_executable;
+                        out.is_writable |= is_writable;
+                        out.is_tls |= is_tls;
+                        out.is_note |= section.kind() == object::SectionKind::Note;
+                        out.is_bss |= matches!(
+                            section.kind(),
+                            object::SectionKind::UninitializedData
+                                | object::SectionKind::UninitializedTls
+                        );
+                        out.alignment = out.alignment.max(section.align());
+                        if !merged.contains_key(&section.index().0) {
+                            input_indices.insert(section.index().0, out.input_sections.len() - 1);
+                        }
+                        placements.insert(section.index().0, (output_name.to_string(), offset));
+                    }
+                }
+                gnu_properties.push(properties);
+
+                // output section and offset of a location in an input section
+                let output_offset = |index: usize, offset: u64| -> Option<(String, u64)> {
+                    let (name, start) = placements.get(&index)?;
+                    match merged.get(&index) {
+                        Some((entsize, entries)) => {
+                            Some((name.clone(), merged_offset(*entsize, entries, offset)?))
+                        }
+                        None => Some((name.clone(), start + offset)),
+                    }
+                };
+
+                // relocations, once all sections they may target are placed
+                for section in elf.sections() {
+                    let nonalloc_placement = nonalloc_placements.get(&section.index().0);
+                    let placement = placements.get(&section.index().0).or(nonalloc_placement);
+                    let is_eh_frame = eh_frame_sections.contains(&section.index().0);
+                    if placement.is_none() && !is_eh_frame {
+                        continue;
+                    }
+                    // offsets into the input section
+                    let mut relocations = vec![];
+                    for (offset, relocation) in section.relocations() {
+                        match relocation.target() {
+                            object::RelocationTarget::Symbol(symbol_id) => {
+                                let symbol = elf.symbol_by_index(symbol_id)?;
+                                if symbol.kind() == object::SymbolKind::Section {
+                                    // relocation to a section
+                                    let section_index = symbol.section_index().unwrap();
+                                    // the addend locates the entry of merged sections
+                                    let mut addend = relocation.addend();
+                                    let entry = match merged.get(&section_index.0) {
+                                        Some((entsize, _)) if addend >= 0 => {
+                                            addend as u64 - addend as u64 % entsize
+                                        }
+                                        _ => 0,
+                                    };
+                                    addend -= entry as i64;
+                                    if eh_frame_sections.contains(&section_index.0) {
+                                        // records are merged after layout, the
+                                        // target record is located by a symbol
+                                        let data = elf.section_by_index(section_index)?.data()?;
+                                        let record = eh_frame_record_start(
+                                            format,
+                                            data,
+                                            addend.max(0) as u64,
+                                        );
+                                        addend -= record as i64;
+                                        let index = eh_frame_base
+                                            + eh_frame_sections.range(..section_index.0).count();
+                                        let name = format!(".eh_frame.{}+{:#x}", index, record);
+                                        info!("Found relocation targeting {}", name);
+                                        eh_frame_anchors.insert(name.clone(), (index, record));
+                                        symbols.entry(name.clone()).or_insert(Symbol {
+                                            section_name: ".eh_frame".to_string(),
+                                            offset: 0,
+                                            input_section: None,
+                                            symbol_name_string_id: None,
+                                            symbol_name_dynamic_string_id: None,
+                                            is_global: false,
+                                            is_weak: false,
+                                            is_plt: false,
+                                            st_type: STT_NOTYPE,
+                                            size: 0,
+                                            file_symbol: Some(file_symbol),
+                                            defined_by: Some(file_name.to_string()),
+                                            referenced_by: vec![],
+                                            canonical_plt: false,
+                                            in_symtab: false,
+                                            visibility: STV_DEFAULT,
+                                        });
+                                        relocations.push(Relocation {
+                                            offset,
+                                            kind: relocation_kind(&relocation),
+                                            encoding: relocation.encoding(),
+                                            size: relocation_size(&relocation),
+                                            r_type: relocation_type(&relocation),
+                                            addend,
+                                            target: RelocationTarget::Symbol(name),
+                                        });
+                                        continue;
+                                    }
+                                    let target =
+                                        output_offset(section_index.0, entry).or_else(|| {
+                                            let (name, start) =
+                                                nonalloc_placements.get(&section_index.0)?;
+                                            Some((name.clone(), start + entry))
+                                        });
+                                    let target = match (target, nonalloc_placement) {
+                                        (Some(target), _) => target,
+                                        // debug info of discarded sections
+                                        (None, Some((name, _))) => {
+                                            addend = 0;
+                                            (ABSOLUTE_SECTION.to_string(), debug_tombstone(name))
+                                        }
+                                        (None, None) => bail!(
+                                            "{}: relocation against non-alloc section {}",
+                                            file_name,
+                                            elf.section_by_index(section_index)?.name()?
+                                        ),
+                                    };
+                                    info!("Found relocation targeting section {}", target.0);
+
+                                    relocations.push(Relocation {
+                                        offset,
+                                        kind: relocation_kind(&relocation),
+                                        encoding: relocation.encoding(),
+                                        size: relocation_size(&relocation),
+                                        r_type: relocation_type(&relocation),
+                                        addend,
+                                        target: RelocationTarget::Section(target),
+                                    });
+                                } else if let (false, Some(target)) = (
+                                    symbol.is_global(),
+                                    symbol.section_index().and_then(|section_index| {
+                                        merged.contains_key(&section_index.0).then(|| {
+                                            output_offset(section_index.0, symbol.address())
+                                        })?
+                                    }),
+                                ) {
+                                    // local symbol in merged section, which is
+                                    // only valid in this object
+                                    info!(
+                                        "Found relocation targeting merged symbol {}",
+                                        symbol.name()?
+                                    );
+
+                                    relocations.push(Relocation {
+                                        offset,
+                                        kind: relocation_kind(&relocation),
+                                        encoding: relocation.encoding(),
+                                        size: relocation_size(&relocation),
+                                        r_type: relocation_type(&relocation),
+                                        addend: relocation.addend(),
+                                        target: RelocationTarget::Section(target),
+                                    });
+                                } else {
+                                    // relocation to a symbol
+                                    let symbol_name = symbol.name()?;
+                                    info!("Found relocation targeting symbol {}", symbol_name);
+
+                                    relocations.push(Relocation {
+                                        offset,
+                                        kind: relocation_kind(&relocation),
+                                        encoding: relocation.encoding(),
+                                        size: relocation_size(&relocation),
+                                        r_type: relocation_type(&relocation),
+                                        addend: relocation.addend(),
+                                        target: RelocationTarget::Symbol(symbol_name.to_string()),
+                                    });
+                                }
+                            }
+                            _ => unimplemented!(),
+                        };
+                    }
+
+                    match placement {
+                        Some((output_name, section_offset)) => {
+                            let out = match nonalloc_placement {
+                                Some(_) => nonalloc_sections.get_mut(output_name).unwrap(),
+                                None => output_sections.get_mut(output_name).unwrap(),
+                            };
+                            let reversed_size = reversed_sections.get(&section.index().0);
+                            for mut relocation in relocations {
+                                if let Some(size) = reversed_size {
+                                    relocation.offset = size - 8 - relocation.offset;
+                                }
+                                relocation.offset += section_offset;
+                                out.relocations.push(relocation);
+                            }
+                        }
+                        None => {
+                            eh_frames.push(EhFrame {
+                                file_name: file_name.to_string(),
+                                records: parse_eh_frame(
+                                    format,
+                                    file_name,
+                                    section.data()?,
+                                    relocations,
+                                )?,
+                            });
+                        }
+                    }
+                }
+
+                for symbol in elf.symbols() {
+                    if symbol.is_global() {
+                        trace_symbol(opt, file_name, symbol.name()?, !symbol.is_undefined());
+                    }
+
+                    if symbol.is_undefined() && symbol.is_global() {
+                        add_reference(referenced_by, symbol.name()?, file_name);
+                        *weak_references
+                            .entry(symbol.name()?.to_string())
+                            .or_insert(true) &= symbol.is_weak();
+                    }
+
+                    let visibility = symbol_visibility(&symbol);
+                    if symbol.is_global() && visibility != STV_DEFAULT {
+                        let merged = global_visibility
+                            .entry(symbol.name()?.to_string())
+                            .or_insert(STV_DEFAULT);
+                        *merged = merge_visibility(*merged, visibility);
+                    }
+
+                    let is_large_common =
+                        symbol.elf_symbol().st_shndx(elf.endian()) == SHN_X86_64_LCOMMON;
+                    if symbol.is_common() || is_large_common {
+                        // merged with other common symbols, the largest size
+                        // and alignment win, allocated after all files are loaded
+                        let name = symbol.name()?;
+                        info!("Defining common symbol {}", name);
+                        // for common symbols, st_value is the alignment
+                        let common =
+                            common_symbols
+                                .entry(name.to_string())
+                                .or_insert(CommonSymbol {
+                                    size: 0,
+                                    alignment: 1,
+                                    large: false,
+                                    file_name: file_name.to_string(),
+                                    file_symbol,
+                                });
+                        common.large |= is_large_common;
+                        if symbol.size() > common.size {
+                            common.size = symbol.size();
+                            common.file_name = file_name.to_string();
+                            common.file_symbol = file_symbol;
+                        }
+                        common.alignment = common.alignment.max(symbol.address());
+                        // do not extract archive members for it
+                        defined_in
+                            .entry(name.to_string())
+                            .or_insert(file_name.to_string());
+                        continue;
+                    }
+
+                    if !symbol.is_undefined()
+                        && symbol.kind() != object::SymbolKind::Section
+                        && symbol.kind() != object::SymbolKind::File
+                    {
+                        let name = symbol.name()?;
+                        let (section_name, offset, input_section) = match symbol.section() {
+                            object::SymbolSection::Section(section_index) => {
+                                let Some((section_name, offset)) =
+                                    output_offset(section_index.0, symbol.address())
+                                else {
+                                    info!("Ignoring symbol {} in non-alloc section", name);
+                                    continue;
+                                };
+                                info!("Defining symbol {} from section {}", name, section_name);
+                                let input_section = input_indices.get(&section_index.0).copied();
+                                (section_name, offset, input_section)
+                            }
+                            object::SymbolSection::Absolute => {
+                                info!(
+                                    "Defining absolute symbol {} = {:#x}",
+                                    name,
+                                    symbol.address()
+                                );
+                                (ABSOLUTE_SECTION.to_string(), symbol.address(), None)
+                            }
+
+                            _ => bail!(
+                                "Symbol kind is {:?}, symbol section is {:?}",
+                                symbol.kind(),
+                                symbol.section(),
+                            ),
+                        };
+
+                        // resolve against existing global definition
+                        if let Some(existing) = symbols.get(name) {
+                            if symbol.is_global() && existing.is_global {
+                                if symbol.is_weak() {
+                                    // keep existing definition
+                                    info!("Ignoring weak definition of {}", name);
+                                    continue;
+                                } else if !existing.is_weak {
+                                    if !opt.z_options.muldefs {
+                                        bail!(
+                                            "{}: multiple definition of `{}`; {}: first defined here",
+                                            file_name,
+                                            demangle(opt, name),
+                                            existing.defined_by.as_deref().unwrap_or_default()
+                                        );
+                                    }
+                                    // the first definition wins
+                                    eprintln!(
+                                        "warning: {}: multiple definition of `{}`; {}: first defined here",
+                                        file_name,
+                                        demangle(opt, name),
+                                        existing.defined_by.as_deref().unwrap_or_default()
+                                    );
+                                    continue;
+                                }
+                                // weak definition is overridden
+                            }
+                        }
+                        if symbol.is_global() {
+                            defined_in.insert(name.to_string(), file_name.to_string());
+                        }
+
+                        symbols.insert(
+                            name.to_string(),
+                            Symbol {
+                                section_name,
+                                offset,
+                                input_section,
+                                symbol_name_string_id: None,
+                                symbol_name_dynamic_string_id: None,
+                                is_global: symbol.is_global(),
+                                is_weak: symbol.is_weak(),
+                                is_plt: false,
+                                st_type: symbol_type(&symbol),
+                                size: symbol.size(),
+                                file_symbol: Some(file_symbol),
+                                defined_by: Some(file_name.to_string()),
+                                referenced_by: vec![],
+                                canonical_plt: false,
+                                in_symtab: symbol.is_global() || !discard_local(opt, name),
+                                visibility: STV_DEFAULT,
+                            },
+                        );
+                    }
+                }
+            }
+            _ => return Err(anyhow!("Unsupported format of file {}", name)),
+        }
+        Ok(())
+    }
+
+    /// Allocate common symbols at the end of .bss like GNU ld, unless
+    /// defined by an object file
+    fn allocate_common_symbols(&mut self) {
+        let Linker {
+            opt,
+            output_sections,
+            symbols,
+            dynamic_symbols,
+            defined_in,
+            common_symbols,
+            global_visibility,
+            ..
+        } = self;
+
+        for (name, common) in common_symbols.iter() {
+            if symbols.contains_key(name) {
+                // real definition takes precedence
+                info!("Common symbol {} is overridden by definition", name);
+                continue;
+            }
+
+            let section_name = if common.large { ".lbss" } else { ".bss" };
+            let out = output_sections
+                .entry(section_name.to_string())
+                .or_insert_with(OutputSection::default);
+            out.name = section_name.to_string();
+            let offset = (out.content.len() as u64).next_multiple_of(common.alignment.max(1));
+            out.input_sections.push(InputSection {
+                file_name: common.file_name.clone(),
+                name: "COMMON".to_string(),
+                offset,
+                size: common.size,
+                retain: false,
+            });
+            let input_section = out.input_sections.len() - 1;
+            out.content.resize((offset + common.size) as usize, 0);
+            out.is_writable = true;
+            out.is_bss = true;
+            out.alignment = out.alignment.max(common.alignment);
+            info!(
+                "Allocating common symbol {} at {}+{:#x}",
+                name, section_name, offset
+            );
+
+            defined_in.insert(name.clone(), common.file_name.clone());
+            symbols.insert(
+                name.clone(),
+                Symbol {
+                    section_name: section_name.to_string(),
+                    offset,
+                    input_section: Some(input_section),
+                    symbol_name_string_id: None,
+                    symbol_name_dynamic_string_id: None,
+                    is_global: true,
+                    is_weak: false,
+                    is_plt: false,
+                    st_type: STT_OBJECT,
+                    size: common.size,
+                    file_symbol: Some(common.file_symbol),
+                    defined_by: Some(common.file_name.clone()),
+                    referenced_by: vec![],
+                    canonical_plt: false,
+                    in_symtab: true,
+                    visibility: global_visibility.get(name).copied().unwrap_or(STV_DEFAULT),
+                },
+            );
+            if opt.shared && symbols[name].is_global_binding() {
+                // export GLOBAL symbols in dynsym
+                dynamic_symbols.push(DynamicSymbol {
+                    name: name.clone(),
+                    version: None,
+                    object_size: None,
+                    is_tls: false,
+                    st_type: STT_NOTYPE,
+                    is_weak: false,
+                });
+            }
+        }
+    }
+
+    /// Sort input sections of .init_array and .fini_array by priority
+    fn sort_init_fini_arrays(&mut self) {
+        let mut layouts = BTreeMap::new();
+        for name in [".init_array", ".fini_array"] {
+            let Some(section) = self.output_sections.get(name) else {
+                continue;
+            };
+            let mut order: Vec<usize> = (0..section.input_sections.len()).collect();
+            order.sort_by_key(|index| init_priority(&section.input_sections[*index].name));
+            if order.iter().enumerate().any(|(i, index)| i != *index) {
+                info!("Sorting input sections of {} by priority", name);
+                layouts.insert(name.to_string(), order);
+            }
+        }
+        self.rearrange_sections(layouts);
+    }
+
+    /// --symbol-ordering-file: move input sections defining the listed
+    /// symbols to the front of their output sections, in the listed order
+    fn order_sections(&mut self) -> anyhow::Result<()> {
+        let Linker {
+            opt,
+            output_sections,
+            symbols,
+            ..
+        } = self;
+
+        let Some(symbol_ordering_file) = &opt.symbol_ordering_file else {
+            return Ok(());
+        };
+        // one symbol name per line
+        let content = std::fs::read_to_string(symbol_ordering_file)
+            .context(format!("Reading file {}", symbol_ordering_file))?;
+
+        // (output section, input section index) => priority, lower comes first
+        let mut priorities: BTreeMap<(String, usize), usize> = BTreeMap::new();
+        for (priority, name) in content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .enumerate()
+        {
+            let Some(symbol) = symbols.get(name) else {
+                eprintln!("warning: symbol ordering file: no such symbol: {}", name);
+                continue;
+            };
+            // absolute and linker-defined symbols have no input section
+            let Some(index) = output_sections
+                .get(&symbol.section_name)
+                .and_then(|section| input_section_index(section, symbol.offset))
+            else {
+                continue;
+            };
+            priorities
+                .entry((symbol.section_name.clone(), index))
+                .or_insert(priority);
+        }
+
+        let mut layouts = BTreeMap::new();
+        for (name, section) in output_sections.iter() {
+            // unlisted input sections follow in their original order
+            let mut order: Vec<usize> = (0..section.input_sections.len()).collect();
+            order.sort_by_key(|index| {
+                priorities
+                    .get(&(name.clone(), *index))
+                    .copied()
+                    .unwrap_or(usize::MAX)
+            });
+            if order.iter().enumerate().any(|(i, index)| i != *index) {
+                info!("Reordering input sections of {}", name);
+                layouts.insert(name.clone(), order);
+            }
+        }
+        self.rearrange_sections(layouts);
+        Ok(())
+    }
+
+    /// --orphan-handling: report or discard sections unknown to the default
+    /// layout, which are placed next to sections with similar flags
+    fn handle_orphan_sections(&mut self) -> anyhow::Result<()> {
+        let Linker {
+            opt,
+            output_sections,
+            ..
+        } = self;
+
+        let mut errors = vec![];
+        let mut layouts = BTreeMap::new();
+        for (name, section) in output_sections.iter() {
+            if default_position(name).is_some() || section.script_order.is_some() {
+                continue;
+            }
+            let anchor = orphan_anchor(name, section);
+            for input_section in &section.input_sections {
+                match opt.orphan_handling {
+                    OrphanHandling::Place => info!(
+                        "Placing orphan section {} from {} after {}",
+                        input_section.name, input_section.file_name, anchor
+                    ),
+                    OrphanHandling::Warn => eprintln!(
+                        "warning: orphan section `{}' from `{}' being placed in section `{}'",
+                        input_section.name, input_section.file_name, section.name
+                    ),
+                    OrphanHandling::Error => errors.push(format!(
+                        "unplaced orphan section `{}' from `{}'",
+                        input_section.name, input_section.file_name
+                    )),
+                    OrphanHandling::Discard => info!(
+                        "Discarding orphan section {} from {}",
+                        input_section.name, input_section.file_name
+                    ),
+                }
+            }
+            if opt.orphan_handling == OrphanHandling::Discard {
+                layouts.insert(name.clone(), vec![]);
+            }
+        }
+        if !errors.is_empty() {
+            bail!("{}", errors.join("\n"));
+        }
+        self.rearrange_sections(layouts);
+        Ok(())
+    }
+
+    /// --gc-sections: remove input sections unreachable from the entry point,
+    /// exported symbols and sections that must be kept
+    fn gc_sections(&mut self) {
+        let Linker {
+            opt,
+            output_sections,
+            symbols,
+            dynamic_symbols,
+            referenced_by,
+            shared_libraries,
+            eh_frames,
+            ..
+        } = self;
+        if !opt.gc_sections {
+            return;
+        }
+
+        // the input section defining a symbol
+        let defining_section = |name: &str| -> Option<(String, usize)> {
+            let symbol = symbols.get(name)?;
+            let section = output_sections.get(&symbol.section_name)?;
+            Some((
+                symbol.section_name.clone(),
+                symbol
+                    .input_section
+                    .or_else(|| input_section_index(section, symbol.offset))?,
+            ))
+        };
+        // the input section targeted by a relocation
+        let target_section = |target: &RelocationTarget| -> Option<(String, usize)> {
+            match target {
+                RelocationTarget::Section((name, offset)) => output_sections
+                    .get(name)
+                    .and_then(|section| input_section_index(section, *offset))
+                    .map(|index| (name.clone(), index)),
+                RelocationTarget::Symbol(name) => defining_section(name),
+            }
+        };
+
+        // roots: entry point, -u and --require-defined symbols, exported
+        // symbols and symbols referenced by shared libraries
+        let mut worklist: Vec<(String, usize)> = vec![];
+        let mut root_symbols: Vec<&str> = vec![];
+        if !opt.shared {
+            root_symbols.push("_start");
+        }
+        root_symbols.extend(opt.undefined.iter().map(|name| name.as_str()));
+        root_symbols.extend(opt.require_defined.iter().map(|name| name.as_str()));
+        root_symbols.extend(init_fini_functions(opt).map(|(_, name)| name));
+        root_symbols.extend(dynamic_symbols.iter().map(|symbol| symbol.name.as_str()));
+        for (name, files) in referenced_by.iter() {
+            if files
+                .iter()
+                .any(|file| shared_libraries.iter().any(|library| library.name == *file))
+            {
+                root_symbols.push(name);
+            }
+        }
+        worklist.extend(root_symbols.into_iter().filter_map(defining_section));
+
+        // roots: SHF_GNU_RETAIN, sections kept by the default linker script
+        // and sections referenced by __start_SECTION or __stop_SECTION
+        for (name, section) in output_sections.iter() {
+            let kept = is_kept_section(name)
+                || (is_c_identifier(name)
+                    && (referenced_by.contains_key(&format!("__start_{}", name))
+                        || referenced_by.contains_key(&format!("__stop_{}", name))));
+            for (index, input_section) in section.input_sections.iter().enumerate() {
+                if kept || input_section.retain {
+                    worklist.push((name.clone(), index));
+                }
+            }
+        }
+
+        // FDEs do not keep the code they cover, but LSDA and personality
+        // referenced by FDEs and CIEs are kept with the code
+        let mut fde_references: BTreeMap<(String, usize), Vec<(String, usize)>> = BTreeMap::new();
+        for eh_frame in eh_frames.iter() {
+            for record in &eh_frame.records {
+                let Some(cie) = record.cie else {
+                    continue;
+                };
+                let Some(pc_begin) = record
+                    .relocations
+                    .iter()
+                    .find(|relocation| relocation.offset == FDE_PC_BEGIN_OFFSET)
+                    .and_then(|relocation| target_section(&relocation.target))
+                else {
+                    continue;
+                };
+                let references = record
+                    .relocations
+                    .iter()
+                    .filter(|relocation| relocation.offset != FDE_PC_BEGIN_OFFSET)
+                    .chain(&eh_frame.records[cie].relocations)
+                    .filter_map(|relocation| target_section(&relocation.target));
+                fde_references
+                    .entry(pc_begin)
+                    .or_default()
+                    .extend(references);
+            }
+        }
+
+        // mark input sections reachable by relocations
+        let mut marked: BTreeSet<(String, usize)> = BTreeSet::new();
+        while let Some((name, index)) = worklist.pop() {
+            if !marked.insert((name.clone(), index)) {
+                continue;
+            }
+            let section = &output_sections[&name];
+            let range = &input_section_ranges(section)[index];
+            for relocation in &section.relocations {
+                if range.contains(&relocation.offset) {
+                    worklist.extend(target_section(&relocation.target));
+                }
+            }
+            if let Some(references) = fde_references.get(&(name, index)) {
+                worklist.extend(references.iter().cloned());
+            }
+        }
+
+        // sweep unmarked input sections
+        let mut layouts = BTreeMap::new();
+        for (name, section) in output_sections.iter() {
+            let mut kept = vec![];
+            for (index, input_section) in section.input_sections.iter().enumerate() {
+                if marked.contains(&(name.clone(), index)) {
+                    kept.push(index);
+                } else if opt.print_gc_sections {
+                    eprintln!(
+                        "removing unused section '{}' in file '{}'",
+                        input_section.name, input_section.file_name
+                    );
+                } else {
+                    info!(
+                        "Removing unused section {} in file {}",
+                        input_section.name, input_section.file_name
+                    );
+                }
+            }
+            if kept.len() != section.input_sections.len() {
+                layouts.insert(name.clone(), kept);
+            }
+        }
+        self.rearrange_sections(layouts);
+    }
+
+    /// Rebuild output sections from the given input sections in order, those
+    /// left out are removed with their relocations and symbols, and so are
+    /// output sections left empty
+    fn rearrange_sections(&mut self, layouts: BTreeMap<String, Vec<usize>>) {
+        let Linker {
+            output_sections,
+            nonalloc_sections,
+            symbols,
+            eh_frames,
+            ..
+        } = self;
+
+        // output section => moves of its input sections
+        let mut moves: BTreeMap<String, Vec<SectionMove>> = BTreeMap::new();
+        // output section => new indices of its input sections
+        let mut new_indices: BTreeMap<String, Vec<Option<usize>>> = BTreeMap::new();
+        for (name, layout) in layouts {
+            let section = output_sections.get_mut(&name).unwrap();
+            let ranges = input_section_ranges(section);
+            let mut content = vec![];
+            let mut input_sections = vec![];
+            let mut moved: Vec<_> = ranges.iter().map(|range| (range.clone(), None)).collect();
+            let mut indices = vec![None; ranges.len()];
+            for index in layout {
+                let range = ranges[index].clone();
+                moved[index].1 = Some(content.len() as u64);
+                indices[index] = Some(input_sections.len());
+                input_sections.push(InputSection {
+                    offset: content.len() as u64,
+                    ..section.input_sections[index].clone()
+                });
+                content
+                    .extend_from_slice(&section.content[range.start as usize..range.end as usize]);
+            }
+            section.content = content;
+            section.input_sections = input_sections;
+            new_indices.insert(name.clone(), indices);
+            moves.insert(name, moved);
+        }
+
+        // fix offsets into the rearranged sections
+        for section in output_sections.values_mut() {
+            if let Some(moved) = moves.get(&section.name) {
+                section.relocations.retain_mut(|relocation| {
+                    match moved_offset(moved, relocation.offset) {
+                        Some(offset) => {
+                            relocation.offset = offset;
+                            true
+                        }
+                        None => false,
+                    }
+                });
+            }
+            for relocation in &mut section.relocations {
+                if let RelocationTarget::Section((name, offset)) = &mut relocation.target {
+                    if let Some(moved) = moves.get(name) {
+                        *offset = moved_offset(moved, *offset).unwrap_or(*offset);
+                    }
+                }
+            }
+        }
+        for (name, section) in nonalloc_sections.iter_mut() {
+            for relocation in &mut section.relocations {
+                if let RelocationTarget::Section((target, offset)) = &mut relocation.target {
+                    if let Some(moved) = moves.get(target) {
+                        match moved_offset(moved, *offset) {
+                            Some(moved_offset) => *offset = moved_offset,
+                            // debug info of removed code
+                            None => {
+                                relocation.target = RelocationTarget::Section((
+                                    ABSOLUTE_SECTION.to_string(),
+                                    debug_tombstone(name),
+                                ));
+                                relocation.addend = 0;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        for record in eh_frames
+            .iter_mut()
+            .flat_map(|eh_frame| eh_frame.records.iter_mut())
+        {
+            for relocation in &mut record.relocations {
+                if let RelocationTarget::Section((name, offset)) = &mut relocation.target {
+                    if let Some(moved) = moves.get(name) {
+                        match moved_offset(moved, *offset) {
+                            Some(moved_offset) => *offset = moved_offset,
+                            // the code covered by FDE is removed
+                            None => record.is_live = false,
+                        }
+                    }
+                }
+            }
+        }
+        symbols.retain(|name, symbol| {
+            let Some(moved) = moves.get(&symbol.section_name) else {
+                return true;
+            };
+            // symbols at the boundary of input sections stay in the one
+            // defining them
+            let offset = match symbol.input_section {
+                Some(index) => {
+                    let (range, start) = &moved[index];
+                    symbol.input_section = new_indices[&symbol.section_name][index];
+                    start.map(|start| start + symbol.offset - range.start)
+                }
+                None => moved_offset(moved, symbol.offset),
+            };
+            match offset {
+                Some(offset) => {
+                    symbol.offset = offset;
+                    true
+                }
+                None => {
+                    info!("Removing symbol {} in removed section", name);
+                    false
+                }
+            }
+        });
+        output_sections.retain(|name, section| {
+            !moves.contains_key(name) || !section.input_sections.is_empty()
+        });
+    }
+
+    /// Merge .note.gnu.property of objects like GNU ld: AND properties are
+    /// kept if all objects have them, OR properties if any of them does
+    fn merge_gnu_properties(&mut self) {
+        let pr_types: BTreeSet<u32> = self
+            .gnu_properties
+            .iter()
+            .flat_map(|properties| properties.keys().copied())
+            .collect();
+        let mut desc = vec![];
+        for pr_type in pr_types {
+            let mut values = self
+                .gnu_properties
+                .iter()
+                .map(|properties| properties.get(&pr_type).copied());
+            let value = match pr_type {
+                GNU_PROPERTY_UINT32_AND_LO..=GNU_PROPERTY_UINT32_AND_HI
+                | GNU_PROPERTY_X86_UINT32_AND_LO..=GNU_PROPERTY_X86_UINT32_AND_HI => {
+                    values.try_fold(u32::MAX, |acc, value| value.map(|value| acc & value))
+                }
+                GNU_PROPERTY_UINT32_OR_LO..=GNU_PROPERTY_UINT32_OR_HI
+                | GNU_PROPERTY_X86_UINT32_OR_LO..=GNU_PROPERTY_X86_UINT32_OR_HI => {
+                    Some(values.flatten().fold(0, |acc, value| acc | value))
+                }
+                GNU_PROPERTY_X86_UINT32_OR_AND_LO..=GNU_PROPERTY_X86_UINT32_OR_AND_HI => {
+                    values.try_fold(0, |acc, value| value.map(|value| acc | value))
+                }
+                _ => {
+                    info!("Ignoring unknown GNU property {:#x}", pr_type);
+                    None
+                }
+            };
+            let Some(value) = value.filter(|value| *value != 0) else {
+                continue;
+            };
+            info!("Merged GNU property {:#x} is {:#x}", pr_type, value);
+            if pr_type == GNU_PROPERTY_X86_FEATURE_1_AND {
+                self.ibt = value & GNU_PROPERTY_X86_FEATURE_1_IBT != 0;
+            }
+            // pr_type, pr_datasz, pr_data padded to 8 bytes
+            for word in [pr_type, 4, value, 0] {
+                desc.extend(self.format.u32_bytes(word));
+            }
+        }
+        if desc.is_empty() {
+            return;
+        }
+
+        // n_namesz, n_descsz, n_type and name
+        let mut content = vec![];
+        for word in [4, desc.len() as u32, NT_GNU_PROPERTY_TYPE_0] {
+            content.extend(self.format.u32_bytes(word));
+        }
+        content.extend(ELF_NOTE_GNU);
+        content.push(0);
+        content.extend(desc);
+        self.output_sections.insert(
+            ".note.gnu.property".to_string(),
+            OutputSection {
+                name: ".note.gnu.property".to_string(),
+                content,
+                is_note: true,
+                alignment: 8,
+                ..OutputSection::default()
+            },
+        );
+    }
+
+    /// --build-id: add .note.gnu.build-id filled with zeros, the ID is
+    /// computed once the output is written
+    fn add_build_id_note(&mut self) {
+        let size = match &self.opt.build_id {
+            None => return,
+            Some(BuildId::Sha1) => 20,
+            Some(BuildId::Md5 | BuildId::Uuid) => 16,
+            Some(BuildId::Hex(bytes)) => bytes.len(),
+        };
+        // n_namesz, n_descsz, n_type and name
+        let mut content = vec![];
+        for word in [4, size as u32, NT_GNU_BUILD_ID] {
+            content.extend(self.format.u32_bytes(word));
+        }
+        content.extend(ELF_NOTE_GNU);
+        content.push(0);
+        content.resize(content.len() + size, 0);
+        self.output_sections.insert(
+            ".note.gnu.build-id".to_string(),
+            OutputSection {
+                name: ".note.gnu.build-id".to_string(),
+                content,
+                is_note: true,
+                alignment: 4,
+                ..OutputSection::default()
+            },
+        );
+    }
+
+    /// Record the linker version in .comment, after those of objects
+    fn add_linker_ident(&mut self) {
+        let ident = format!("Linker: cold {}", env!("CARGO_PKG_VERSION"));
+        let out = self
+            .nonalloc_sections
+            .entry(".comment".to_string())
+            .or_insert_with(comment_section);
+        merge_strings(&mut out.content, ident.as_bytes());
+    }
+
+    /// --separate-debug-file: move debug sections to the debug file, which is
+    /// found by .gnu_debuglink of the output
+    fn separate_debug_sections(&mut self) {
+        let Some(path) = &self.opt.separate_debug_file else {
+            return;
+        };
+        let names: Vec<String> = self
+            .nonalloc_sections
+            .keys()
+            .filter(|name| name.starts_with(".debug"))
+            .cloned()
+            .collect();
+        for name in names {
+            let section = self.nonalloc_sections.remove(&name).unwrap();
+            self.separate_debug_sections.insert(name, section);
+        }
+
+        // file name padded to 4 bytes, then CRC32 of the debug file filled
+        // once it is written
+        let file_name = Path::new(path).file_name().unwrap_or_default();
+        let mut content = file_name.as_encoded_bytes().to_vec();
+        content.push(0);
+        content.resize(content.len().next_multiple_of(4) + 4, 0);
+        self.nonalloc_sections.insert(
+            ".gnu_debuglink".to_string(),
+            OutputSection {
+                name: ".gnu_debuglink".to_string(),
+                content,
+                alignment: 4,
+                ..OutputSection::default()
+            },
+        );
+    }
+
+    /// Merge .eh_frame of objects, with duplicate CIEs removed and FDEs of
+    /// removed code discarded
+    fn build_eh_frame(&mut self) {
+        let Linker {
+            opt,
+            format,
+            output_sections,
+            symbols,
+            eh_frames,
+            eh_frame_anchors,
+            eh_frame_fdes,
+            ..
+        } = self;
+
+        let mut out = OutputSection {
+            name: ".eh_frame".to_string(),
+            alignment: 8,
+            ..OutputSection::default()
+        };
+        // CIEs already written and their offsets
+        let mut written_cies: Vec<(&EhFrameRecord, u64)> = vec![];
+        for (eh_frame_index, eh_frame) in eh_frames.iter().enumerate() {
+            let start = out.content.len() as u64;
+            // CIE index => offset in output
+            let mut cie_offsets: BTreeMap<usize, u64> = BTreeMap::new();
+            // record index => offset in output
+            let mut record_offsets: BTreeMap<usize, u64> = BTreeMap::new();
+            for (index, record) in eh_frame.records.iter().enumerate() {
+                let Some(cie) = record.cie else {
+                    // CIEs are written on demand of FDEs
+                    continue;
+                };
+                let pc_begin = record
+                    .relocations
+                    .iter()
+                    .find(|relocation| relocation.offset == FDE_PC_BEGIN_OFFSET);
+                let pc_begin_defined =
+                    pc_begin.is_some_and(|relocation| match &relocation.target {
+                        RelocationTarget::Section(_) => true,
+                        RelocationTarget::Symbol(name) => symbols.contains_key(name),
+                    });
+                if !record.is_live || !pc_begin_defined {
+                    info!("Discarding FDE of removed code in {}", eh_frame.file_name);
+                    continue;
+                }
+
+                let cie_offset = *cie_offsets.entry(cie).or_insert_with(|| {
+                    let cie = &eh_frame.records[cie];
+                    if let Some((_, offset)) = written_cies.iter().find(|(written, _)| {
+                        written.content == cie.content && written.relocations == cie.relocations
+                    }) {
+                        return *offset;
+                    }
+                    let offset = out.content.len() as u64;
+                    out.content.extend(&cie.content);
+                    out.relocations
+                        .extend(cie.relocations.iter().map(|relocation| Relocation {
+                            offset: relocation.offset + offset,
+                            ..relocation.clone()
+                        }));
+                    written_cies.push((cie, offset));
+                    offset
+                });
+                record_offsets.insert(cie, cie_offset);
+
+                // CIE pointer: distance from the field to the CIE
+                let offset = out.content.len() as u64;
+                out.content.extend(&record.content);
+                format.store(
+                    &mut out.content[offset as usize + 4..offset as usize + 8],
+                    offset + 4 - cie_offset,
+                );
+                out.relocations
+                    .extend(record.relocations.iter().map(|relocation| Relocation {
+                        offset: relocation.offset + offset,
+                        ..relocation.clone()
+                    }));
+                record_offsets.insert(index, offset);
+                if let Some(pc_begin) = pc_begin {
+                    eh_frame_fdes.push((offset, pc_begin.target.clone(), pc_begin.addend));
+                }
+            }
+            if out.content.len() as u64 > start {
+                out.input_sections.push(InputSection {
+                    file_name: eh_frame.file_name.clone(),
+                    name: ".eh_frame".to_string(),
+                    offset: start,
+                    size: out.content.len() as u64 - start,
+                    retain: false,
+                });
+            }
+
+            // records targeted by relocations are located in the output, or
+            // the start of records of the object if removed, and the end of
+            // them stays the end
+            for (name, (index, offset)) in eh_frame_anchors.iter() {
+                if *index != eh_frame_index {
+                    continue;
+                }
+                let mut input_offset = 0;
+                let mut output_offset = out.content.len() as u64;
+                for (index, record) in eh_frame.records.iter().enumerate() {
+                    if input_offset == *offset {
+                        output_offset = record_offsets.get(&index).copied().unwrap_or(start);
+                        break;
+                    }
+                    input_offset += record.content.len() as u64;
+                }
+                symbols.get_mut(name).unwrap().offset = output_offset;
+            }
+        }
+
+        if !out.content.is_empty() || !eh_frame_anchors.is_empty() {
+            // terminated by a zero length like crtend.o does, for
+            // __register_frame_info() walking records from the start
+            out.content.extend([0; 4]);
+            info!("Merged .eh_frame of {} bytes", out.content.len());
+            if opt.eh_frame_hdr {
+                // version, encodings of .eh_frame pointer, FDE count and
+                // the table, then the binary search table of FDEs
+                let mut content = vec![0; 12 + 8 * eh_frame_fdes.len()];
+                content[..4].copy_from_slice(&[
+                    1,
+                    DW_EH_PE_PCREL | DW_EH_PE_SDATA4,
+                    DW_EH_PE_UDATA4,
+                    DW_EH_PE_DATAREL | DW_EH_PE_SDATA4,
+                ]);
+                output_sections.insert(
+                    ".eh_frame_hdr".to_string(),
+                    OutputSection {
+                        name: ".eh_frame_hdr".to_string(),
+                        content,
+                        alignment: 4,
+                        ..OutputSection::default()
+                    },
+                );
+            }
+            output_sections.insert(out.name.clone(), out);
+        }
+    }
+
+    /// Check that undefined symbols of shared libraries are satisfied,
+    /// searching their dependencies in -rpath-link and friends
+    fn check_shared_libraries(&mut self) -> anyhow::Result<()> {
+        let Linker {
+            opt,
+            symbols,
+            shared_libraries,
+            ..
+        } = self;
+
+        // shared libraries are allowed to have undefined symbols
+        if opt.shared || shared_libraries.is_empty() {
+            return Ok(());
+        }
+
+        // symbols defined in this link
+        let mut defined: BTreeSet<String> = symbols
+            .iter()
+            .filter(|(_name, symbol)| !symbol.is_plt)
+            .map(|(name, _symbol)| name.clone())
+            .collect();
+        let mut loaded: BTreeSet<PathBuf> = BTreeSet::new();
+        for lib in shared_libraries.iter() {
+            defined.extend(lib.defined.iter().cloned());
+            if let Ok(path) = Path::new(&lib.name).canonicalize() {
+                loaded.insert(path);
+            }
+        }
+
+        // walk through dependencies of shared libraries, they do not affect
+        // the output
+        let mut complete: Vec<bool> = vec![true; shared_libraries.len()];
+        for (index, lib) in shared_libraries.iter().enumerate() {
+            let mut queue: Vec<(String, String, Vec<String>)> = lib
+                .needed
+                .iter()
+                .map(|needed| (needed.clone(), lib.name.clone(), lib.runpath.clone()))
+                .collect();
+            while let Some((needed, needed_by, runpath)) = queue.pop() {
+                let _span = info_span!("needed", name = needed).entered();
+                let path = if needed.contains('/') {
+                    PathBuf::from(&needed)
+                } else {
+                    // $ORIGIN refers to the directory containing the library
+                    let origin = Path::new(&needed_by)
+                        .parent()
+                        .map(|dir| format!("{}", dir.display()))
+                        .unwrap_or_default();
+                    let mut paths = vec![];
+                    paths.extend(opt.rpath_link.iter().cloned());
+                    paths.extend(opt.rpath.iter().cloned());
+                    paths.extend(runpath.iter().map(|path| {
+                        // absolute runpath lives in sysroot
+                        let path = path.replace("$ORIGIN", &origin);
+                        match &opt.sysroot {
+                            Some(sysroot) if path.starts_with('/') => {
+                                format!("{}{}", sysroot, path)
+                            }
+                            _ => path,
+                        }
+                    }));
+                    if let Ok(ld_library_path) = std::env::var("LD_LIBRARY_PATH") {
+                        paths.extend(ld_library_path.split(':').map(|path| path.to_string()));
+                    }
+                    paths.extend(opt.search_dir.iter().cloned());
+                    paths.extend(DEFAULT_LIBRARY_PATHS.iter().map(|path| {
+                        format!("{}{}", opt.sysroot.as_deref().unwrap_or_default(), path)
+                    }));
+                    match lookup_file(&needed, &paths)
+                        .or_else(|_| lookup_ld_so_cache(&needed, opt.sysroot.as_deref()))
+                    {
+                        Ok(path) => path,
+                        Err(_) => {
+                            eprintln!(
+                                "warning: {needed}, needed by {needed_by}, not found (try using -rpath or -rpath-link)"
+                            );
+                            complete[index] = false;
+                            continue;
+                        }
+                    }
+                };
+
+                let Ok(canonical_path) = path.canonicalize() else {
+                    eprintln!("warning: {needed}, needed by {needed_by}, not found");
+                    complete[index] = false;
+                    continue;
+                };
+                if !loaded.insert(canonical_path) {
+                    // already seen
+                    continue;
+                }
+
+                let name = format!("{}", path.display());
+                info!("Reading dependency {name}");
+                let content = std::fs::read(&path).context(format!("Reading file {}", name))?;
+                let dep = match object::File::parse(content.as_slice())
+                    .context(format!("Parsing file {} as object", name))?
+                {
+                    object::File::Elf64(elf) => read_shared_library(&name, &elf)?,
+                    _ => bail!("Unsupported format of file {}", name),
+                };
+                defined.extend(dep.defined.iter().cloned());
+                queue.extend(
+                    dep.needed
+                        .iter()
+                        .map(|needed| (needed.clone(), dep.name.clone(), dep.runpath.clone())),
+                );
+            }
+        }
+
+        // all undefined symbols should be defined somewhere
+        for (lib, complete) in shared_libraries.iter().zip(complete) {
+            if !complete {
+                // some dependencies are missing, the check is unreliable
+                continue;
+            }
+            for name in &lib.undefined {
+                if !defined.contains(name) {
+                    bail!(
+                        "{}: undefined reference to `{}`",
+                        lib.name,
+                        demangle(opt, name)
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Locally bound symbols need no .got entry, the mov, call and jmp
+    /// instructions of R_X86_64_GOTPCRELX and R_X86_64_REX_GOTPCRELX are
+    /// rewritten to lea, direct call and direct jmp, see the x86-64 psABI
+    fn relax_gotpcrelx(&mut self) {
+        let Linker {
+            opt,
+            output_sections,
+            symbols,
+            ..
+        } = self;
+        if !opt.relax {
+            return;
+        }
+
+        for (name, output_section) in output_sections.iter_mut() {
+            for relocation in &mut output_section.relocations {
+                if !matches!(
+                    relocation.r_type,
+                    R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX
+                ) {
+                    continue;
+                }
+                // the pc-relative address must be known at link time
+                if let RelocationTarget::Symbol(symbol_name) = &relocation.target {
+                    // imported from shared library, or undefined
+                    let Some(symbol) = symbols.get(symbol_name) else {
+                        continue;
+                    };
+                    if symbol.section_name == UNDEFINED_SECTION
+                        || symbol.section_name == ABSOLUTE_SECTION
+                        || symbol.is_ifunc()
+                        || is_preemptible(opt, symbol)
+                    {
+                        continue;
+                    }
+                }
+
+                let offset = relocation.offset as usize;
+                let Some(code) = offset
+                    .checked_sub(2)
+                    .and_then(|start| output_section.content.get_mut(start..offset + 4))
+                else {
+                    continue;
+                };
+                match code[..2] {
+                    // 8b /r  mov foo@GOTPCREL(%rip), %reg
+                    // => 8d /r  lea foo(%rip), %reg
+                    [0x8b, modrm] if modrm & 0xc7 == 0x05 => {
+                        code[0] = 0x8d;
+                    }
+                    // ff 15  call *foo@GOTPCREL(%rip)
+                    // => 67 e8  addr32 call foo
+                    [0xff, 0x15] => {
+                        code[..2].copy_from_slice(&[0x67, 0xe8]);
+                    }
+                    // ff 25  jmp *foo@GOTPCREL(%rip)
+                    // => e9 xx xx xx xx 90  jmp foo; nop
+                    [0xff, 0x25] => {
+                        code.copy_within(2..6, 1);
+                        code[0] = 0xe9;
+                        code[5] = 0x90;
+                        // the same addend, as the end of instruction moves
+                        // along
+                        relocation.offset -= 1;
+                    }
+                    _ => continue,
+                }
+                info!(
+                    "Relaxing {} at {}+{:#x} to R_X86_64_PC32",
+                    relocation_name(relocation),
+                    name,
+                    offset
+                );
+                relocation.kind = object::RelocationKind::Relative;
+                relocation.encoding = object::RelocationEncoding::Generic;
+                relocation.size = 32;
+                relocation.r_type = R_X86_64_PC32;
+            }
+        }
+    }
+
+    /// Executables know the static tls layout, the general-dynamic,
+    /// local-dynamic, tls descriptor and initial-exec code sequences are
+    /// rewritten to the initial-exec or local-exec ones, see the x86-64 psABI
+    fn relax_tls(&mut self) -> anyhow::Result<()> {
+        let Linker {
+            opt,
+            output_sections,
+            symbols,
+            plt_dynamic_symbols,
+            ..
+        } = self;
+        if opt.shared {
+            return Ok(());
+        }
+
+        for (name, output_section) in output_sections.iter_mut() {
+            // offsets of relocations of the removed __tls_get_addr calls
+            let mut removed = BTreeSet::new();
+            for relocation in &mut output_section.relocations {
+                if relocation.kind != object::RelocationKind::Unknown {
+                    continue;
+                }
+                // locally defined variables use local-exec, imported ones
+                // use initial-exec
+                let local = match &relocation.target {
+                    RelocationTarget::Symbol(symbol_name) => {
+                        if symbols.contains_key(symbol_name) {
+                            true
+                        } else if plt_dynamic_symbols
+                            .iter()
+                            .any(|sym| sym.name == *symbol_name && sym.is_tls)
+                        {
+                            false
+                        } else {
+                            // reported as undefined
+                            continue;
+                        }
+                    }
+                    RelocationTarget::Section(_) => true,
+                };
+                let offset = relocation.offset as usize;
+                let content = &mut output_section.content;
+                let from = relocation_name(relocation);
+                let target = match &relocation.target {
+                    RelocationTarget::Symbol(symbol_name) => demangle(opt, symbol_name),
+                    RelocationTarget::Section((section_name, _)) => section_name.clone(),
+                };
+                let failed = |to: &str| {
+                    anyhow!(
+                        "TLS transition from {} to {} against `{}` failed at {}+{:#x}",
+                        from,
+                        to,
+                        target,
+                        name,
+                        offset
+                    )
+                };
+                match relocation.r_type {
+                    R_X86_64_TLSGD => {
+                        // 66 48 8d 3d xx xx xx xx  leaq x@tlsgd(%rip), %rdi
+                        // 66 66 48 e8 xx xx xx xx  call __tls_get_addr@plt
+                        // or 66 48 ff 15 xx xx xx xx  call *__tls_get_addr@gotpcrel(%rip)
+                        let to = if local {
+                            "R_X86_64_TPOFF32"
+                        } else {
+                            "R_X86_64_GOTTPOFF"
+                        };
+                        let code = offset
+                            .checked_sub(4)
+                            .and_then(|start| content.get_mut(start..start + 16))
+                            .ok_or_else(|| failed(to))?;
+                        if code[..4] != [0x66, 0x48, 0x8d, 0x3d]
+                            || (code[8..12] != [0x66, 0x66, 0x48, 0xe8]
+                                && code[8..12] != [0x66, 0x48, 0xff, 0x15])
+                        {
+                            return Err(failed(to));
+                        }
+                        if local {
+                            // 64 48 8b 04 25 00 00 00 00  mov %fs:0, %rax
+                            // 48 8d 80 xx xx xx xx        lea x@tpoff(%rax), %rax
+                            code.copy_from_slice(&[
+                                0x64, 0x48, 0x8b, 0x04, 0x25, 0x00, 0x00, 0x00, 0x00, 0x48, 0x8d,
+                                0x80, 0x00, 0x00, 0x00, 0x00,
+                            ]);
+                            relocation.r_type = R_X86_64_TPOFF32;
+                            relocation.addend += 4;
+                        } else {
+                            // 64 48 8b 04 25 00 00 00 00  mov %fs:0, %rax
+                            // 48 03 05 xx xx xx xx        add x@gottpoff(%rip), %rax
+                            code.copy_from_slice(&[
+                                0x64, 0x48, 0x8b, 0x04, 0x25, 0x00, 0x00, 0x00, 0x00, 0x48, 0x03,
+                                0x05, 0x00, 0x00, 0x00, 0x00,
+                            ]);
+                            relocation.r_type = R_X86_64_GOTTPOFF;
+                        }
+                        // the call is gone
+                        removed.insert(relocation.offset + 8);
+                        relocation.offset += 8;
+                    }
+                    R_X86_64_TLSLD => {
+                        // 48 8d 3d xx xx xx xx  leaq x@tlsld(%rip), %rdi
+                        // e8 xx xx xx xx        call __tls_get_addr@plt
+                        // or ff 15 xx xx xx xx  call *__tls_get_addr@gotpcrel(%rip)
+                        let to = "R_X86_64_TPOFF32";
+                        let start = offset.checked_sub(3).ok_or_else(|| failed(to))?;
+                        let code = content.get_mut(start..).ok_or_else(|| failed(to))?;
+                        if !code.starts_with(&[0x48, 0x8d, 0x3d]) {
+                            return Err(failed(to));
+                        }
+                        if code.get(7) == Some(&0xe8) {
+                            // 66 66 66 64 48 8b 04 25 00 00 00 00  mov %fs:0, %rax
+                            code[..12].copy_from_slice(&[
+                                0x66, 0x66, 0x66, 0x64, 0x48, 0x8b, 0x04, 0x25, 0x00, 0x00, 0x00,
+                                0x00,
+                            ]);
+                            removed.insert(relocation.offset + 5);
+                        } else if code.get(7..9) == Some(&[0xff, 0x15]) {
+                            // 66 66 66 66 64 48 8b 04 25 00 00 00 00  mov %fs:0, %rax
+                            code[..13].copy_from_slice(&[
+                                0x66, 0x66, 0x66, 0x66, 0x64, 0x48, 0x8b, 0x04, 0x25, 0x00, 0x00,
+                                0x00, 0x00,
+                            ]);
+                            removed.insert(relocation.offset + 6);
+                        } else {
+                            return Err(failed(to));
+                        }
+                        // variables are then located relative to the thread
+                        // pointer, see below
+                    }
+                    R_X86_64_GOTPC32_TLSDESC => {
+                        // 48 8d 05 xx xx xx xx  leaq x@tlsdesc(%rip), %rax
+                        let to = if local {
+                            "R_X86_64_TPOFF32"
+                        } else {
+                            "R_X86_64_GOTTPOFF"
+                        };
+                        let code = offset
+                            .checked_sub(3)
+                            .and_then(|start| content.get_mut(start..start + 3))
+                            .ok_or_else(|| failed(to))?;
+                        if (code[0] & 0xfb) != 0x48 || code[1] != 0x8d || (code[2] & 0xc7) != 0x05 {
+                            return Err(failed(to));
+                        }
+                        let reg = (code[2] >> 3) & 7;
+                        if local {
+                            // 48 c7 c0 xx xx xx xx  mov $x@tpoff, %rax
+                            code.copy_from_slice(&[0x48 | ((code[0] >> 2) & 1), 0xc7, 0xc0 | reg]);
+                            relocation.r_type = R_X86_64_TPOFF32;
+                            relocation.addend += 4;
+                        } else {
+                            // 48 8b 05 xx xx xx xx  mov x@gottpoff(%rip), %rax
+                            code[1] = 0x8b;
+                            relocation.r_type = R_X86_64_GOTTPOFF;
+                        }
+                    }
+                    R_X86_64_TLSDESC_CALL => {
+                        // ff 10  call *x@tlscall(%rax) => 66 90  xchg %ax, %ax
+                        let to = "nop";
+                        let code = content
+                            .get_mut(offset..offset + 2)
+                            .ok_or_else(|| failed(to))?;
+                        if code != [0xff, 0x10] {
+                            return Err(failed(to));
+                        }
+                        code.copy_from_slice(&[0x66, 0x90]);
+                    }
+                    R_X86_64_GOTTPOFF if local => {
+                        // 48 8b 05 xx xx xx xx  mov x@gottpoff(%rip), %rax
+                        // => 48 c7 c0 xx xx xx xx  mov $x@tpoff, %rax
+                        // 48 03 05 xx xx xx xx  add x@gottpoff(%rip), %rax
+                        // => 48 81 c0 xx xx xx xx  add $x@tpoff, %rax
+                        let to = "R_X86_64_TPOFF32";
+                        let code = offset
+                            .checked_sub(3)
+                            .and_then(|start| content.get_mut(start..start + 3))
+                            .ok_or_else(|| failed(to))?;
+                        if (code[0] & 0xfb) != 0x48 || (code[2] & 0xc7) != 0x05 {
+                            return Err(failed(to));
+                        }
+                        let opcode = match code[1] {
+                            0x8b => 0xc7,
+                            0x03 => 0x81,
+                            _ => return Err(failed(to)),
+                        };
+                        // the register moves from modrm.reg to modrm.rm
+                        let reg = (code[2] >> 3) & 7;
+                        code.copy_from_slice(&[0x48 | ((code[0] >> 2) & 1), opcode, 0xc0 | reg]);
+                        relocation.r_type = R_X86_64_TPOFF32;
+                        relocation.addend += 4;
+                    }
+                    _ => {}
+                }
+            }
+            output_section.relocations.retain(|relocation| {
+                !(removed.contains(&relocation.offset)
+                    && relocation.kind != object::RelocationKind::Unknown)
+                    && relocation.r_type != R_X86_64_TLSLD
+                    && relocation.r_type != R_X86_64_TLSDESC_CALL
+            });
+            // all local-dynamic sequences now leave the thread pointer
+            for relocation in &mut output_section.relocations {
+                match relocation.r_type {
+                    R_X86_64_DTPOFF32 => relocation.r_type = R_X86_64_TPOFF32,
+                    R_X86_64_DTPOFF64 => relocation.r_type = R_X86_64_TPOFF64,
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn generate_plt(&mut self) -> anyhow::Result<()> {
+        let ibt = self.ibt;
+        let format = self.format;
+        let Linker {
+            opt,
+            output_sections,
+            symbols,
+            defined_in,
+            weak_references,
+            plt_dynamic_symbols,
+            dynamic_symbols,
+            output_relocations,
+            ..
+        } = self;
+
+        // calls to preemptible definitions in shared library go through plt,
+        // unless bound locally by -Bsymbolic or -Bsymbolic-functions
+        let mut preemptible_calls = BTreeSet::new();
+        for output_section in output_sections.values() {
+            for relocation in &output_section.relocations {
+                if let (true, RelocationTarget::Symbol(name)) =
+                    (is_plt_relocation(relocation), &relocation.target)
+                {
+                    if symbols
+                        .get(name)
+                        .is_some_and(|sym| is_preemptible(opt, sym))
+                        && dynamic_symbols.iter().any(|sym| sym.name == *name)
+                    {
+                        preemptible_calls.insert(name.clone());
+                    }
+                }
+            }
+        }
+
+        // R_X86_64_COPY: the dynamic linker copies the initial value from
+        // the shared library, numbered after all imported symbols are known
+        for (idx, sym) in dynamic_symbols.iter().enumerate() {
+            // only copied symbols keep the object size from shared library
+            if sym.object_size.is_some() {
+                let symbol = &symbols[&sym.name];
+                output_relocations
+                    .entry(".rela.dyn".to_string())
+                    .or_default()
+                    .relocations
+                    .push(DynamicRelocation {
+                        section_name: symbol.section_name.clone(),
+                        offset: symbol.offset,
+                        symbol_index: (1 + plt_dynamic_symbols.len() + idx) as u32,
+                        r_type: R_X86_64_COPY,
+                        addend: 0,
+                        target: None,
+                    });
+            }
+        }
+
+        // handle dynamic symbols: construct .plt, .got.plt
+        if self.dynamic_link || !plt_dynamic_symbols.is_empty() || !preemptible_calls.is_empty() {
+            // address-taken functions in non-pic executable: the plt entry
+            // becomes the canonical address, shared by the dynamic libraries
+            let mut canonical = BTreeSet::new();
+            if !opt.shared && !opt.pie {
+                for output_section in output_sections.values() {
+                    for relocation in &output_section.relocations {
+                        // .got entries are filled with the real address
+                        if is_plt_relocation(relocation) || is_got_relocation(relocation) {
+                            continue;
+                        }
+                        if let RelocationTarget::Symbol(name) = &relocation.target {
+                            if plt_dynamic_symbols
+                                .iter()
+                                .any(|sym| sym.name == *name && !sym.is_tls)
+                            {
+                                info!("Using plt entry as canonical address of {}", name);
+                                canonical.insert(name.clone());
+                            }
+                        }
+                    }
+                }
+            }
+            // symbols having .got entries for GOTPCREL references
+            let got_referenced: BTreeSet<String> = output_sections
+                .values()
+                .flat_map(|section| section.relocations.iter())
+                .filter(|relocation| is_got_relocation(relocation))
+                .filter_map(|relocation| match &relocation.target {
+                    RelocationTarget::Symbol(name) => Some(name.clone()),
+                    _ => None,
+                })
+                .collect();
+            // calls jump through .got filled at load time by .plt.got, without
+            // lazy binding slots, under -z now or if the symbol has a .got
+            // entry anyway, except for canonical addresses that .got entries
+            // would resolve to
+            let eager = opt.z_options.now;
+            let use_plt_got = |name: &String| {
+                (eager || got_referenced.contains(name)) && !canonical.contains(name)
+            };
+            // imported symbols referenced only by .got entries and dynamic
+            // R_X86_64_64 need no plt entry, unlike calls, other pc-relative
+            // references and canonical addresses
+            let plt_referenced: BTreeSet<String> = output_sections
+                .values()
+                .flat_map(|section| section.relocations.iter())
+                .filter(|relocation| {
+                    !is_got_relocation(relocation)
+                        && !matches!(
+                            (relocation.kind, relocation.encoding, relocation.size),
+                            (
+                                object::RelocationKind::Absolute,
+                                object::RelocationEncoding::Generic,
+                                64
+                            )
+                        )
+                })
+                .filter_map(|relocation| match &relocation.target {
+                    RelocationTarget::Symbol(name) => Some(name.clone()),
+                    _ => None,
+                })
+                .collect();
+            let needs_plt = |sym: &DynamicSymbol| {
+                !sym.is_tls && (plt_referenced.contains(&sym.name) || canonical.contains(&sym.name))
+            };
+            let mut plt_names = plt_dynamic_symbols
+                .iter()
+                .filter(|sym| needs_plt(sym))
+                .map(|sym| &sym.name)
+                .chain(preemptible_calls.iter())
+                .peekable();
+            // .plt is only created for lazy binding entries
+            let lazy = plt_names.any(|name| !use_plt_got(name));
+
+            assert!(!output_sections.contains_key(".plt"));
+            let mut plt = OutputSection {
+                name: ".plt".to_string(),
+                is_executable: true,
+                ..OutputSection::default()
+            };
+
+            // first entry in plt:
+            plt.content.extend(vec![
+                // ff 35 xx xx xx xx push .got.plt+8(%rip)
+                0xff, 0x35, 0x00, 0x00, 0x00, 0x00,
+                // ff 25 xx xx xx xx jmp *.got.plt+16(%rip)
+                0xff, 0x25, 0x00, 0x00, 0x00, 0x00, // 0f 1f 40 00       nop
+                0x0f, 0x1f, 0x40, 0x00,
+            ]);
+            // relocation for push .got.plt+8(rip)
+            plt.relocations.push(Relocation {
+                offset: 0x2,
+                kind: object::RelocationKind::Relative,
+                encoding: object::RelocationEncoding::Generic,
+                size: 32,
+                r_type: R_X86_64_NONE,
+                addend: 8 - 4,
+                target: RelocationTarget::Section((".got.plt".to_string(), 0)),
+            });
+            // relocation for jmp *.got.plt+16(%rip)
+            plt.relocations.push(Relocation {
+                offset: 0x8,
+                kind: object::RelocationKind::Relative,
+                encoding: object::RelocationEncoding::Generic,
+                size: 32,
+                r_type: R_X86_64_NONE,
+                addend: 16 - 4,
+                target: RelocationTarget::Section((".got.plt".to_string(), 0)),
+            });
+            if lazy {
+                output_sections.insert(".plt".to_string(), plt);
+            }
+
+            // got contents:
+            assert!(!output_sections.contains_key(".got.plt"));
+            let mut got_plt = OutputSection {
+                name: ".got.plt".to_string(),
+                is_writable: true,
+                ..OutputSection::default()
+            };
+            got_plt.content.extend(vec![
+                // 0: address of .dynamic section
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                // 1: 0, reserved for ld.so
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                // 2: 0, reserved for ld.so
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ]);
+            // address of .dynamic section
+            got_plt.relocations.push(Relocation {
+                offset: 0x0,
+                kind: object::RelocationKind::Absolute,
+                encoding: object::RelocationEncoding::Generic,
+                size: 64,
+                r_type: R_X86_64_NONE,
+                addend: 0,
+                target: RelocationTarget::Section((".dynamic".to_string(), 0)),
+            });
+            output_sections.insert(".got.plt".to_string(), got_plt);
+
+            // add _GLOBAL_OFFSET_TABLE_ symbol
+            symbols.insert(
+                "_GLOBAL_OFFSET_TABLE_".to_string(),
+                global_offset_table_symbol(),
+            );
+
+            for (idx, dyn_sym) in plt_dynamic_symbols.iter().enumerate() {
+                // thread-local variables are only accessed via tls
+                // relocations, and others bound by .got entries or dynamic
+                // relocations stay undefined
+                if !needs_plt(dyn_sym) {
+                    info!("Importing {} without plt entry", dyn_sym.name);
+                    symbols.insert(
+                        dyn_sym.name.clone(),
+                        Symbol {
+                            section_name: UNDEFINED_SECTION.to_string(),
+                            offset: 0,
+                            input_section: None,
+                            symbol_name_string_id: None,
+                            symbol_name_dynamic_string_id: None,
+                            is_global: true,
+                            is_weak: weak_references.get(&dyn_sym.name).copied().unwrap_or(false),
+                            is_plt: true,
+                            // resolved to the function an ifunc selects
+                            st_type: match dyn_sym.st_type {
+                                STT_GNU_IFUNC => STT_FUNC,
+                                st_type => st_type,
+                            },
+                            size: 0,
+                            file_symbol: None,
+                            defined_by: defined_in.get(&dyn_sym.name).cloned(),
+                            referenced_by: vec![],
+                            canonical_plt: false,
+                            in_symtab: true,
+                            visibility: STV_DEFAULT,
+                        },
+                    );
+                    continue;
+                }
+
+                // redirect the symbol to plt
+                let (section_name, plt_offset) = if use_plt_got(&dyn_sym.name) {
+                    (
+                        ".plt.got",
+                        add_plt_got_entry(output_sections, &dyn_sym.name, ibt),
+                    )
+                } else {
+                    add_plt_entry(
+                        format,
+                        output_sections,
+                        output_relocations,
+                        (idx + 1) as u32,
+                        ibt,
+                    )
+                };
+
+                symbols.insert(
+                    dyn_sym.name.clone(),
+                    Symbol {
+                        section_name: section_name.to_string(),
+                        offset: plt_offset,
+                        input_section: None,
+                        symbol_name_string_id: None,
+                        symbol_name_dynamic_string_id: None,
+                        is_global: true,
+                        is_weak: weak_references.get(&dyn_sym.name).copied().unwrap_or(false),
+                        is_plt: true,
+                        st_type: STT_FUNC,
+                        size: 0,
+                        file_symbol: None,
+                        defined_by: defined_in.get(&dyn_sym.name).cloned(),
+                        referenced_by: vec![],
+                        canonical_plt: false,
+                        in_symtab: true,
+                        visibility: STV_DEFAULT,
+                    },
+                );
+            }
+
+            for name in &canonical {
+                symbols.get_mut(name).unwrap().canonical_plt = true;
+            }
+
+            // the definition stays in place, while calls are redirected to plt
+            let mut plt_offsets = BTreeMap::new();
+            for name in &preemptible_calls {
+                if use_plt_got(name) {
+                    let plt_offset = add_plt_got_entry(output_sections, name, ibt);
+                    plt_offsets.insert(name.clone(), (".plt.got", plt_offset));
+                    continue;
+                }
+                let position = dynamic_symbols
+                    .iter()
+                    .position(|sym| sym.name == *name)
+                    .unwrap();
+                let symbol_index = 1 + plt_dynamic_symbols.len() + position;
+                let entry = add_plt_entry(
+                    format,
+                    output_sections,
+                    output_relocations,
+                    symbol_index as u32,
+                    ibt,
+                );
+                plt_offsets.insert(name.clone(), entry);
+            }
+            for output_section in output_sections.values_mut() {
+                for relocation in &mut output_section.relocations {
+                    if !is_plt_relocation(relocation) {
+                        continue;
+                    }
+                    if let RelocationTarget::Symbol(name) = &relocation.target {
+                        if let Some((plt_name, plt_offset)) = plt_offsets.get(name) {
+                            relocation.target =
+                                RelocationTarget::Section((plt_name.to_string(), *plt_offset));
+                        }
+                    }
+                }
+            }
+        }
+
+        // non-preemptible ifunc: references go through .iplt, whose .igot.plt
+        // slot is filled with the result of the resolver by R_X86_64_IRELATIVE
+        let mut ifuncs = BTreeSet::new();
+        for output_section in output_sections.values() {
+            for relocation in &output_section.relocations {
+                if let RelocationTarget::Symbol(name) = &relocation.target {
+                    if symbols.get(name).is_some_and(|sym| sym.is_ifunc()) {
+                        ifuncs.insert(name.clone());
+                    }
+                }
+            }
+        }
+        // without dynamic linker, libc applies .rela.iplt at startup
+        let rela_name = if !opt.shared && !self.dynamic_link {
+            ".rela.iplt"
+        } else {
+            ".rela.dyn"
+        };
+        let mut iplt_offsets = BTreeMap::new();
+        for name in &ifuncs {
+            info!("Creating iplt entry for ifunc {}", name);
+            let igot_plt = output_sections
+                .entry(".igot.plt".to_string())
+                .or_insert_with(|| OutputSection {
+                    name: ".igot.plt".to_string(),
+                    is_writable: true,
+                    ..OutputSection::default()
+                });
+            let igot_offset = igot_plt.content.len() as u64;
+            igot_plt.content.extend(vec![0; 8]);
+
+            let iplt = output_sections
+                .entry(".iplt".to_string())
+                .or_insert_with(|| OutputSection {
+                    name: ".iplt".to_string(),
+                    is_executable: true,
+                    ..OutputSection::default()
+                });
+            let iplt_offset = iplt.content.len() as u64;
+            let jmp_offset = if ibt {
+                iplt.content.extend(vec![
+                    // f3 0f 1e fa       endbr64
+                    0xf3, 0x0f, 0x1e, 0xfa, // ff 25 xx xx xx xx jmp *.igot.plt+yy(%rip)
+                    0xff, 0x25, 0x00, 0x00, 0x00, 0x00,
+                    // 66 0f 1f 44 00 00 nopw 0x0(%rax,%rax,1)
+                    0x66, 0x0f, 0x1f, 0x44, 0x00, 0x00,
+                ]);
+                4
+            } else {
+                iplt.content.extend(vec![
+                    // ff 25 xx xx xx xx jmp *.igot.plt+yy(%rip)
+                    0xff, 0x25, 0x00, 0x00, 0x00, 0x00,
+                    // 66 0f 1f 44 00 00 nopw 0x0(%rax,%rax,1)
+                    0x66, 0x0f, 0x1f, 0x44, 0x00, 0x00, // 0f 1f 40 00       nopl 0x0(%rax)
+                    0x0f, 0x1f, 0x40, 0x00,
+                ]);
+                0
+            };
+            // relocation for jmp *.igot.plt+yy(%rip)
+            iplt.relocations.push(Relocation {
+                offset: iplt_offset + jmp_offset + 2,
+                kind: object::RelocationKind::Relative,
+                encoding: object::RelocationEncoding::Generic,
+                size: 32,
+                r_type: R_X86_64_NONE,
+                addend: igot_offset as i64 - 4,
+                target: RelocationTarget::Section((".igot.plt".to_string(), 0)),
+            });
+
+            // R_X86_64_IRELATIVE: indirect (B + A)
+            output_relocations
+                .entry(rela_name.to_string())
+                .or_default()
+                .relocations
+                .push(DynamicRelocation {
+                    section_name: ".igot.plt".to_string(),
+                    offset: igot_offset,
+                    symbol_index: 0,
+                    r_type: R_X86_64_IRELATIVE,
+                    addend: 0,
+                    target: Some(RelocationTarget::Symbol(name.clone())),
+                });
+            iplt_offsets.insert(name.clone(), iplt_offset);
+        }
+        // all references are redirected, the .iplt entry is the canonical address
+        for output_section in output_sections.values_mut() {
+            for relocation in &mut output_section.relocations {
+                if let RelocationTarget::Symbol(name) = &relocation.target {
+                    if let Some(iplt_offset) = iplt_offsets.get(name) {
+                        relocation.target =
+                            RelocationTarget::Section((".iplt".to_string(), *iplt_offset));
+                    }
+                }
+            }
+        }
+
+        // define __rela_iplt_start and __rela_iplt_end for libc
+        if !opt.shared
+            && ["__rela_iplt_start", "__rela_iplt_end"]
+                .iter()
+                .any(|name| self.referenced_by.contains_key(*name) && !symbols.contains_key(*name))
+        {
+            let rela_iplt = output_relocations
+                .entry(".rela.iplt".to_string())
+                .or_default();
+            let size = rela_iplt.relocations.len() * format.rela_size();
+            for (name, offset) in [("__rela_iplt_start", 0), ("__rela_iplt_end", size)] {
+                symbols.insert(
+                    name.to_string(),
+                    Symbol {
+                        section_name: ".rela.iplt".to_string(),
+                        offset: offset as u64,
+                        input_section: None,
+                        symbol_name_string_id: None,
+                        symbol_name_dynamic_string_id: None,
+                        is_global: false,
+                        is_weak: false,
+                        is_plt: false,
+                        st_type: STT_NOTYPE,
+                        size: 0,
+                        file_symbol: None,
+                        defined_by: None,
+                        referenced_by: vec![],
+                        canonical_plt: false,
+                        in_symtab: true,
+                        visibility: STV_DEFAULT,
+                    },
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Define symbols provided by the linker when they are referenced but
+    /// not defined, their addresses are assigned in relocate()
+    fn define_linker_symbols(&mut self) -> anyhow::Result<()> {
+        // assignments of linker scripts override definitions of objects,
+        // PROVIDE only defines referenced symbols that are not defined
+        for assignment in &self.script.assignments {
+            let name = &assignment.symbol;
+            if name == "."
+                || (assignment.provide
+                    && (!self.referenced_by.contains_key(name)
+                        || (self.symbols.contains_key(name)
+                            && !self.script_symbols.contains(name))))
+            {
+                continue;
+            }
+            info!("Defining symbol {} from linker script", name);
+            self.symbols.insert(
+                name.to_string(),
+                Symbol {
+                    section_name: ABSOLUTE_SECTION.to_string(),
+                    offset: 0,
+                    input_section: None,
+                    symbol_name_string_id: None,
+                    symbol_name_dynamic_string_id: None,
+                    is_global: true,
+                    is_weak: false,
+                    is_plt: false,
+                    st_type: STT_NOTYPE,
+                    size: 0,
+                    file_symbol: None,
+                    defined_by: None,
+                    referenced_by: vec![],
+                    canonical_plt: false,
+                    in_symtab: true,
+                    visibility: if assignment.hidden {
+                        STV_HIDDEN
+                    } else {
+                        STV_DEFAULT
+                    },
+                },
+            );
+            self.script_symbols.insert(name.to_string());
+        }
+
+        for name in LAYOUT_SYMBOLS {
+            if !self.referenced_by.contains_key(*name) || self.symbols.contains_key(*name) {
+                continue;
+            }
+            info!("Defining linker symbol {}", name);
+            self.symbols.insert(
+                name.to_string(),
+                Symbol {
+                    section_name: ABSOLUTE_SECTION.to_string(),
+                    offset: 0,
+                    input_section: None,
+                    symbol_name_string_id: None,
+                    symbol_name_dynamic_string_id: None,
+                    is_global: true,
+                    is_weak: false,
+                    is_plt: false,
+                    st_type: STT_NOTYPE,
+                    size: 0,
+                    file_symbol: None,
+                    defined_by: None,
+                    referenced_by: vec![],
+                    canonical_plt: false,
+                    in_symtab: true,
+                    visibility: STV_DEFAULT,
+                },
+            );
+            self.layout_symbols.push(name.to_string());
+        }
+
+        // __start_SECTION and __stop_SECTION bound output sections whose names
+        // are valid C identifiers, e.g. for registration macros
+        for (section_name, output_section) in &self.output_sections {
+            if !is_c_identifier(section_name) {
+                continue;
+            }
+            for (name, offset) in [
+                (format!("__start_{}", section_name), 0),
+                (
+                    format!("__stop_{}", section_name),
+                    output_section.content.len() as u64,
+                ),
+            ] {
+                if !self.referenced_by.contains_key(&name) || self.symbols.contains_key(&name) {
+                    continue;
+                }
+                info!("Defining linker symbol {}", name);
+                self.symbols.insert(
+                    name,
+                    Symbol {
+                        section_name: section_name.clone(),
+                        offset,
+                        input_section: None,
+                        symbol_name_string_id: None,
+                        symbol_name_dynamic_string_id: None,
+                        is_global: true,
+                        is_weak: false,
+                        is_plt: false,
+                        st_type: STT_NOTYPE,
+                        size: 0,
+                        file_symbol: None,
+                        defined_by: None,
+                        referenced_by: vec![],
+                        canonical_plt: false,
+                        in_symtab: true,
+                        visibility: STV_DEFAULT,
+                    },
+                );
+            }
+        }
+
+        // __init_array_start and friends bound the arrays iterated by the
+        // startup code, they are equal when the array is absent
+        for (section_name, prefix) in [
+            (".preinit_array", "__preinit_array"),
+            (".init_array", "__init_array"),
+            (".fini_array", "__fini_array"),
+        ] {
+            let (section, size) = match self.output_sections.get(section_name) {
+                Some(output_section) => (section_name, output_section.content.len() as u64),
+                None => (ABSOLUTE_SECTION, 0),
+            };
+            for (name, offset) in [
+                (format!("{}_start", prefix), 0),
+                (format!("{}_end", prefix), size),
+            ] {
+                if !self.referenced_by.contains_key(&name) || self.symbols.contains_key(&name) {
+                    continue;
+                }
+                info!("Defining linker symbol {}", name);
+                self.symbols.insert(
+                    name,
+                    Symbol {
+                        section_name: section.to_string(),
+                        offset,
+                        input_section: None,
+                        symbol_name_string_id: None,
+                        symbol_name_dynamic_string_id: None,
+                        is_global: true,
+                        is_weak: false,
+                        is_plt: false,
+                        st_type: STT_NOTYPE,
+                        size: 0,
+                        file_symbol: None,
+                        defined_by: None,
+                        referenced_by: vec![],
+                        canonical_plt: false,
+                        in_symtab: true,
+                        visibility: STV_HIDDEN,
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Report all relocations against symbols that are not defined anywhere
+    /// Undefined weak symbols in position independent output are imported
+    /// like those of shared libraries, for the dynamic linker to bind them
+    /// if a definition appears at runtime, unless there is no dynamic linker
+    fn import_undefined_weak(&mut self) {
+        if !(self.opt.shared || (self.opt.pie && self.dynamic_link)) || self.opt.static_pie {
+            return;
+        }
+        for (name, weak) in &self.weak_references {
+            if *weak
+                && !self.symbols.contains_key(name)
+                && !self.plt_dynamic_symbols.iter().any(|sym| sym.name == *name)
+            {
+                info!("Importing undefined weak symbol {}", name);
+                self.plt_dynamic_symbols.push(DynamicSymbol {
+                    name: name.clone(),
+                    version: None,
+                    object_size: None,
+                    is_tls: false,
+                    st_type: STT_NOTYPE,
+                    is_weak: false,
+                });
+            }
+        }
+    }
+
+    /// Resolve the remaining undefined weak symbols to zero
+    fn resolve_undefined_weak(&mut self) {
+        let Linker {
+            symbols,
+            weak_references,
+            ..
+        } = self;
+
+        for (name, weak) in weak_references.iter() {
+            if !*weak || symbols.contains_key(name) {
+                continue;
+            }
+            info!("Resolving undefined weak symbol {} to zero", name);
+            symbols.insert(
+                name.clone(),
+                Symbol {
+                    section_name: UNDEFINED_SECTION.to_string(),
+                    offset: 0,
+                    input_section: None,
+                    symbol_name_string_id: None,
+                    symbol_name_dynamic_string_id: None,
+                    is_global: true,
+                    is_weak: true,
+                    is_plt: false,
+                    st_type: STT_NOTYPE,
+                    size: 0,
+                    file_symbol: None,
+                    defined_by: None,
+                    referenced_by: vec![],
+                    canonical_plt: false,
+                    in_symtab: true,
+                    visibility: STV_DEFAULT,
+                },
+            );
+        }
+    }
+
+    /// Allocate .got entries for GOTPCREL references: symbols bound at load
+    /// time are filled by R_X86_64_GLOB_DAT, others hold the address
+    fn generate_got(&mut self) {
+        let format = self.format;
+        let word = format.word_size();
+        let mut static_tls = false;
+        let Linker {
+            opt,
+            output_sections,
+            output_relocations,
+            symbols,
+            plt_dynamic_symbols,
+            dynamic_symbols,
+            ..
+        } = self;
+
+        // relocations relative to _GLOBAL_OFFSET_TABLE_ need it without
+        // dynamic linking as well, with the reserved .got.plt entries
+        if !symbols.contains_key("_GLOBAL_OFFSET_TABLE_")
+            && output_sections
+                .values()
+                .flat_map(|section| section.relocations.iter())
+                .any(is_got_base_relocation)
+        {
+            info!("Creating .got.plt for _GLOBAL_OFFSET_TABLE_");
+            assert!(!output_sections.contains_key(".got.plt"));
+            output_sections.insert(
+                ".got.plt".to_string(),
+                OutputSection {
+                    name: ".got.plt".to_string(),
+                    content: vec![0; 3 * word],
+                    is_writable: true,
+                    alignment: word as u64,
+                    ..OutputSection::default()
+                },
+            );
+            symbols.insert(
+                "_GLOBAL_OFFSET_TABLE_".to_string(),
+                global_offset_table_symbol(),
+            );
+        }
+
+        // (target, R_X86_64_GLOB_DAT, R_X86_64_TPOFF64, R_X86_64_DTPMOD64 or
+        // R_X86_64_TLSDESC) => offset of its .got entry
+        let mut got_entries: BTreeMap<(RelocationTarget, u32), u64> = BTreeMap::new();
+        // offset of the .got entry of local-dynamic tls, shared by all
+        // variables of this module
+        let mut tls_ld_entry = None;
+        let mut got = OutputSection {
+            name: ".got".to_string(),
+            is_writable: true,
+            alignment: word as u64,
+            ..OutputSection::default()
+        };
+        for output_section in output_sections.values_mut() {
+            for relocation in &mut output_section.relocations {
+                // entries hold either the address, the offset from the thread
+                // pointer for initial-exec tls, the module id and the offset
+                // in its tls block for __tls_get_addr, or the tls descriptor
+                let got_type = match (relocation.kind, relocation.r_type) {
+                    _ if is_got_relocation(relocation) => R_X86_64_GLOB_DAT,
+                    (object::RelocationKind::Unknown, R_X86_64_GOTTPOFF) => R_X86_64_TPOFF64,
+                    (object::RelocationKind::Unknown, R_X86_64_TLSGD) => R_X86_64_DTPMOD64,
+                    (object::RelocationKind::Unknown, R_X86_64_GOTPC32_TLSDESC) => R_X86_64_TLSDESC,
+                    (object::RelocationKind::Unknown, R_X86_64_TLSLD) => {
+                        let offset = *tls_ld_entry.get_or_insert_with(|| {
+                            let offset = got.content.len() as u64;
+                            got.content.resize(offset as usize + 2 * word, 0);
+                            info!("Allocating .got entry of local-dynamic tls");
+                            add_tls_module_id(format, opt, &mut got, output_relocations, offset);
+                            offset
+                        });
+                        // G + GOT + A - P is the pc-relative address of the entry
+                        relocation.kind = object::RelocationKind::Relative;
+                        relocation.encoding = object::RelocationEncoding::Generic;
+                        relocation.size = 32;
+                        relocation.target = RelocationTarget::Section((".got".to_string(), offset));
+                        continue;
+                    }
+                    _ => continue,
+                };
+                let symbol = match &relocation.target {
+                    RelocationTarget::Symbol(name) => match symbols.get(name) {
+                        Some(symbol) => Some(symbol),
+                        // reported as undefined
+                        None => continue,
+                    },
+                    RelocationTarget::Section(_) => None,
+                };
+
+                let target = relocation.target.clone();
+                let offset = *got_entries
+                    .entry((target.clone(), got_type))
+                    .or_insert_with(|| {
+                        let offset = got.content.len() as u64;
+                        // module id and offset, or the resolver function and
+                        // its argument of tls descriptor take two entries
+                        if got_type == R_X86_64_DTPMOD64 || got_type == R_X86_64_TLSDESC {
+                            got.content.resize(offset as usize + 2 * word, 0);
+                        } else {
+                            got.content.resize(offset as usize + word, 0);
+                        }
+                        let name = match &target {
+                            RelocationTarget::Symbol(name) => name.as_str(),
+                            RelocationTarget::Section(_) => "",
+                        };
+                        // imported and preemptible symbols are bound at load time
+                        let dynamic_index = match symbol {
+                            Some(symbol) if symbol.is_plt || symbol.is_undefined_weak() => {
+                                plt_dynamic_symbols
+                                    .iter()
+                                    .position(|sym| sym.name == name)
+                                    .map(|index| 1 + index)
+                            }
+                            Some(symbol) if is_preemptible(opt, symbol) => dynamic_symbols
+                                .iter()
+                                .position(|sym| sym.name == name)
+                                .map(|index| 1 + plt_dynamic_symbols.len() + index),
+                            _ => None,
+                        };
+                        if let Some(index) = dynamic_index {
+                            info!("Allocating .got entry of {} filled at load time", name);
+                            let rela_dyn = output_relocations
+                                .entry(".rela.dyn".to_string())
+                                .or_default();
+                            rela_dyn.relocations.push(DynamicRelocation {
+                                section_name: ".got".to_string(),
+                                offset,
+                                symbol_index: index as u32,
+                                r_type: got_type,
+                                addend: 0,
+                                target: None,
+                            });
+                            if got_type == R_X86_64_DTPMOD64 {
+                                rela_dyn.relocations.push(DynamicRelocation {
+                                    section_name: ".got".to_string(),
+                                    offset: offset + word as u64,
+                                    symbol_index: index as u32,
+                                    r_type: R_X86_64_DTPOFF64,
+                                    addend: 0,
+                                    target: None,
+                                });
+                            }
+                            return offset;
+                        }
+
+                        info!("Allocating .got entry of {:?}", target);
+                        let (slot, kind, r_type) = match got_type {
+                            _ if got_type == R_X86_64_TLSDESC
+                                || (got_type == R_X86_64_TPOFF64 && opt.shared) =>
+                            {
+                                // the tls block of shared library is placed at
+                                // load time, and the descriptor is always
+                                // filled by the dynamic linker, the addend is
+                                // the offset into the template
+                                output_relocations
+                                    .entry(".rela.dyn".to_string())
+                                    .or_default()
+                                    .relocations
+                                    .push(DynamicRelocation {
+                                        section_name: ".got".to_string(),
+                                        offset,
+                                        symbol_index: 0,
+                                        r_type: got_type,
+                                        addend: 0,
+                                        target: Some(target),
+                                    });
+                                return offset;
+                            }
+                            // the offset from the thread pointer known at
+                            // link time
+                            R_X86_64_TPOFF64 => {
+                                (offset, object::RelocationKind::Unknown, R_X86_64_TPOFF64)
+                            }
+                            // the offset into the tls block known at link time
+                            R_X86_64_DTPMOD64 => {
+                                add_tls_module_id(
+                                    format,
+                                    opt,
+                                    &mut got,
+                                    output_relocations,
+                                    offset,
+                                );
+                                (
+                                    offset + word as u64,
+                                    object::RelocationKind::Unknown,
+                                    R_X86_64_DTPOFF64,
+                                )
+                            }
+                            // the address, relocated like other data
+                            _ => (offset, object::RelocationKind::Absolute, R_X86_64_NONE),
+                        };
+                        got.relocations.push(Relocation {
+                            offset: slot,
+                            kind,
+                            encoding: object::RelocationEncoding::Generic,
+                            size: 8 * word as u8,
+                            r_type,
+                            addend: 0,
+                            target,
+                        });
+                        offset
+                    });
+                static_tls |= got_type == R_X86_64_TPOFF64;
+                relocation.target = RelocationTarget::Section((".got".to_string(), offset));
+                match relocation.r_type {
+                    // G + A is the offset of the entry from
+                    // _GLOBAL_OFFSET_TABLE_, computed from its address
+                    _ if relocation.kind == object::RelocationKind::Got => {}
+                    R_X86_64_GOT64 | R_X86_64_GOTPLT64 => {}
+                    // G + GOT + A - P in 64 bits
+                    R_X86_64_GOTPCREL64 => relocation.r_type = R_X86_64_PC64,
+                    // G + GOT + A - P is the pc-relative address of the entry
+                    _ => {
+                        relocation.kind = object::RelocationKind::Relative;
+                        relocation.encoding = object::RelocationEncoding::Generic;
+                        relocation.size = 32;
+                    }
+                }
+            }
+        }
+        if !got.content.is_empty() {
+            assert!(!output_sections.contains_key(".got"));
+            output_sections.insert(".got".to_string(), got);
+        }
+        self.static_tls = static_tls && self.opt.shared;
+    }
+
+    /// Attach referencing files to the global symbols they resolve to
+    fn collect_references(&mut self) {
+        for (name, files) in &self.referenced_by {
+            if let Some(symbol) = self.symbols.get_mut(name) {
+                if symbol.is_global {
+                    symbol.referenced_by = files.clone();
+                }
+            }
+        }
+    }
+
+    fn check_undefined_symbols(&self) -> anyhow::Result<()> {
+        let mut errors = vec![];
+        for (name, output_section) in &self.output_sections {
+            for relocation in &output_section.relocations {
+                let RelocationTarget::Symbol(symbol_name) = &relocation.target else {
+                    continue;
+                };
+                if self.symbols.contains_key(symbol_name) {
+                    continue;
+                }
+
+                errors.push(format!(
+                    "undefined reference to `{}` referenced by {}",
+                    demangle(&self.opt, symbol_name),
+                    relocation_location(name, output_section, relocation.offset)
+                ));
+            }
+        }
+
+        // --require-defined: defined by objects in this link
+        for name in &self.opt.require_defined {
+            if !self
+                .symbols
+                .get(name)
+                .is_some_and(|symbol| !symbol.is_plt && symbol.section_name != UNDEFINED_SECTION)
+            {
+                errors.push(format!(
+                    "required symbol `{}` not defined",
+                    demangle(&self.opt, name)
+                ));
+            }
+        }
+
+        if !errors.is_empty() {
+            bail!("{}", errors.join("\n"));
+        }
+        Ok(())
+    }
+
+    fn scan_relocations(&mut self) -> anyhow::Result<()> {
+        let Linker {
+            opt,
+            output_sections,
+            output_relocations,
+            symbols,
+            plt_dynamic_symbols,
+            dynamic_symbols,
+            ..
+        } = self;
+
+        // only position independent output is relocated at load time
+        if !opt.shared && !opt.pie {
+            return Ok(());
+        }
+
+        // find relocations that must be applied by the dynamic linker
+        for (name, output_section) in output_sections.iter() {
+            // lazy binding slots are relocated by the dynamic linker itself
+            if name == ".got.plt" {
+                continue;
+            }
+            let _span = info_span!("section", name = name).entered();
+            for relocation in &output_section.relocations {
+                let is_absolute_64 = matches!(
+                    (relocation.kind, relocation.encoding, relocation.size),
+                    (
+                        object::RelocationKind::Absolute,
+                        object::RelocationEncoding::Generic,
+                        64
+                    )
+                );
+                if let RelocationTarget::Symbol(symbol_name) = &relocation.target {
+                    // symbols bound at load time: imported symbols, and
+                    // preemptible definitions of shared library, while
+                    // protected and -Bsymbolic ones are bound locally
+                    let symbol = &symbols[symbol_name];
+                    let dynamic_index =
+                        if symbol.is_undefined_weak() || (symbol.is_plt && !symbol.canonical_plt) {
+                            plt_dynamic_symbols
+                                .iter()
+                                .position(|sym| sym.name == *symbol_name)
+                                .map(|index| 1 + index)
+                        } else if is_preemptible(opt, symbol) {
+                            dynamic_symbols
+                                .iter()
+                                .position(|sym| sym.name == *symbol_name)
+                                .map(|index| 1 + plt_dynamic_symbols.len() + index)
+                        } else {
+                            None
+                        };
+                    if let Some(index) = dynamic_index {
+                        if is_absolute_64 {
+                            // R_X86_64_64 against the dynamic symbol
+                            if !output_section.is_writable {
+                                warn!("Creating text relocation in read-only section {}", name);
+                                self.text_relocation = true;
+                            }
+                            output_relocations
+                                .entry(".rela.dyn".to_string())
+                                .or_default()
+                                .relocations
+                                .push(DynamicRelocation {
+                                    section_name: name.clone(),
+                                    offset: relocation.offset,
+                                    symbol_index: index as u32,
+                                    r_type: R_X86_64_64,
+                                    addend: relocation.addend,
+                                    target: None,
+                                });
+                            continue;
+                        } else if !symbol.is_plt
+                            && (matches!(
+                                relocation.kind,
+                                object::RelocationKind::Absolute | object::RelocationKind::Relative
+                            ) || matches!(relocation.r_type, R_X86_64_PC64 | R_X86_64_GOTOFF64))
+                        {
+                            bail!(
+                                "relocation {} against symbol `{}` can not be used when making a shared object; recompile with -fPIC",
+                                relocation_name(relocation),
+                                demangle(opt, symbol_name)
+                            );
+                        }
+                    }
+
+                    // absolute symbols do not move with the load address
+                    if symbols.get(symbol_name).is_some_and(|symbol| {
+                        symbol.is_plt
+                            || symbol.section_name == ABSOLUTE_SECTION
+                            || symbol.section_name == UNDEFINED_SECTION
+                    }) {
+                        continue;
+                    }
+                }
+
+                if !is_absolute_64 {
+                    // narrower absolute addresses can not be relocated at load
+                    // time
+                    if relocation.kind == object::RelocationKind::Absolute {
+                        let target = match &relocation.target {
+                            RelocationTarget::Symbol(symbol_name) => demangle(opt, symbol_name),
+                            RelocationTarget::Section((section_name, _)) => section_name.clone(),
+                        };
+                        bail!(
+                            "relocation {} against `{}` can not be used when making a {} object; recompile with -fPIC",
+                            relocation_name(relocation),
+                            target,
+                            if opt.shared { "shared" } else { "PIE" }
+                        );
+                    }
+                    continue;
+                }
+                if !opt.shared && !self.dynamic_link {
+                    if !output_section.is_writable {
+                        warn!("Text relocation cannot be applied without dynamic section");
+                    }
+                    continue;
+                }
+                if !output_section.is_writable {
+                    if opt.z_options.text {
+                        bail!(
+                            "Relocation at {}+{:#x} against read-only section requires dynamic relocation, which is disallowed by -z text",
+                            name,
+                            relocation.offset
+                        );
+                    }
+                    warn!("Creating text relocation in read-only section {}", name);
+                    self.text_relocation = true;
+                }
+
+                // the address moves with the load address
+                // R_X86_64_RELATIVE: B + A
+                output_relocations
+                    .entry(".rela.dyn".to_string())
+                    .or_default()
+                    .relocations
+                    .push(DynamicRelocation {
+                        section_name: name.clone(),
+                        offset: relocation.offset,
+                        symbol_index: 0,
+                        r_type: R_X86_64_RELATIVE,
+                        addend: relocation.addend,
+                        target: Some(relocation.target.clone()),
+                    });
+            }
+        }
+
+        // R_X86_64_RELATIVE go first, counted by DT_RELACOUNT for the
+        // dynamic linker to apply them without symbol lookup
+        if let Some(rela_dyn) = output_relocations.get_mut(".rela.dyn") {
+            rela_dyn
+                .relocations
+                .sort_by_key(|rel| rel.r_type != R_X86_64_RELATIVE);
+        }
+
+        Ok(())
+    }
+
+    fn reserve(&mut self, arena: &'a mut Arena<u8>) -> anyhow::Result<()> {
+        let format = self.format;
+        let Linker {
+            opt,
+            output_sections,
+            symbols,
+            dynamic_symbols,
             plt_dynamic_symbols,
             writer,
             output_relocations,
@@ -690,304 +6344,1154 @@ impl<'a> Linker<'a> {
             ..
         } = self;
 
-        // assign address to output sections
-        // and generate layout of executable
-        // assume executable is loaded at 0x400000 unless pie
-        self.load_address = if opt.shared || opt.pie { 0 } else { 0x400000 };
-        // the first page is reserved for ELF header & program header
-        writer.reserve_file_header();
-        // for simplicity, use one segment to map them all
-        let mut program_headers_count = 2; // PT_PHDR + PT_LOAD
+        // page sizes used for alignment
+        self.max_page_size = opt.z_options.max_page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+        self.common_page_size = opt.z_options.common_page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+        if self.common_page_size > self.max_page_size {
+            warn!(
+                "Common page size {:#x} is larger than max page size {:#x}, using the latter",
+                self.common_page_size, self.max_page_size
+            );
+            self.common_page_size = self.max_page_size;
+        }
+
+        // ifunc resolvers run after other dynamic relocations are applied
+        if let Some(rela_dyn) = output_relocations.get_mut(".rela.dyn") {
+            rela_dyn
+                .relocations
+                .sort_by_key(|rel| rel.r_type == R_X86_64_IRELATIVE);
+        }
+
+        // assign address to output sections
+        // and generate layout of executable
+        // assume executable is loaded at 0x400000 unless pie
+        self.load_address = if opt.shared || opt.pie { 0 } else { 0x400000 };
+        // the first page is reserved for ELF header & program header
+        writer.reserve_file_header();
+        // separate segments for headers and read-only data, code, and
+        // writable data, their permissions follow from output sections
+        let is_dynamic = opt.shared || self.dynamic_link;
+        let parts = layout_parts(output_sections);
+        let fixed = fixed_addresses(
+            opt,
+            &self.script,
+            output_sections,
+            &parts,
+            self.max_page_size,
+        )?;
+        let [early, middle, late] = parts;
+        // (permissions, whether at a fixed address, whether joined) in file
+        // order, a segment starts unless joined or extended by the same
+        // permissions
+        let mut flags: Vec<(u32, bool, bool)> = vec![(object::elf::PF_R, false, false)];
+        flags.extend(early.iter().chain(&middle).chain(&late).map(|name| {
+            (
+                segment_flags(name, &output_sections[name]),
+                fixed.addresses.contains_key(name),
+                fixed.joined.contains(name),
+            )
+        }));
+        if is_dynamic {
+            // .dynamic
+            flags.push((object::elf::PF_R | object::elf::PF_W, false, false));
+        }
+        let mut load_segments_count = 0;
+        let mut current_flags = None;
+        for (flags, fixed, joined) in flags {
+            current_flags = match current_flags {
+                Some(current_flags) if joined => Some(current_flags | flags),
+                Some(current_flags) if current_flags == flags && !fixed => Some(current_flags),
+                _ => {
+                    load_segments_count += 1;
+                    Some(flags)
+                }
+            };
+        }
+        let mut program_headers_count = 2 + load_segments_count; // PT_PHDR + PT_GNU_STACK + PT_LOADs
+        if is_dynamic {
+            // PT_DYNAMIC
+            program_headers_count += 1;
+        }
+        if output_sections.contains_key(".interp") {
+            // PT_INTERP
+            program_headers_count += 1;
+        }
+        if output_sections.values().any(|section| section.is_tls) {
+            // PT_TLS
+            program_headers_count += 1;
+        }
+        let note_runs = note_runs(&early, output_sections);
+        // PT_NOTE
+        program_headers_count += note_runs.len();
+        if output_sections.contains_key(".note.gnu.property") {
+            // PT_GNU_PROPERTY
+            program_headers_count += 1;
+        }
+        if output_sections.contains_key(".eh_frame_hdr") {
+            // PT_GNU_EH_FRAME
+            program_headers_count += 1;
+        }
+        *phdr_offset = writer.reserved_len();
+        *phdr_len = program_headers_count * format.class.program_header_size();
+        writer.reserve_program_headers(program_headers_count as u32);
+        let load_segments = &mut self.load_segments;
+        load_segments.clear();
+        extend_load_segments(
+            load_segments,
+            0,
+            writer.reserved_len() as u64,
+            object::elf::PF_R,
+            Some((self.load_address, self.load_address)),
+            false,
+        );
+
+        // .interp and notes follow headers
+        reserve_output_sections(
+            writer,
+            output_sections,
+            &early,
+            &fixed,
+            load_segments,
+            self.common_page_size,
+            self.max_page_size,
+        );
+        self.note_segments = note_runs
+            .iter()
+            .map(|run| {
+                let first = &output_sections[&run[0]];
+                let last = &output_sections[run.last().unwrap()];
+                NoteSegment {
+                    offset: first.offset,
+                    size: last.offset + last.content.len() as u64 - first.offset,
+                    alignment: first.alignment,
+                }
+            })
+            .collect();
+
+        // reserve .rela.xx sections, read by the dynamic linker or the
+        // startup code of static executables
+        let tables_offset = writer.reserved_len() as u64;
+        for (_name, output_section) in output_relocations.iter_mut() {
+            output_section.offset = writer.reserve(
+                output_section.relocations.len() * format.rela_size(),
+                format.class.align(),
+            ) as u64;
+        }
+        // reserve dynsym, dynstr, hash and gnu_hash, .dynamic comes later
+        self.dynamic_entries_count = 6;
+        if opt.shared || self.dynamic_link {
+            // dynamic entries:
+            // 1. HASH -> .hash
+            // 2. GNU_HASH -> .gnu_hash
+            // 3. STRTAB -> .dynstr
+            // 4. SYMTAB -> .dynsym
+            // 5. STRSZ
+            // 6. SYMENT
+            // 7. SONAME
+            // 8. RUNPATH/RPATH
+            // 9. PLTGOT -> .got.plt
+            // 10. PLTRELSZ
+            // 11. PLTREL
+            // 12. JMPREL -> .rela.plt
+            // 13. RELA -> .rela.dyn
+            // 14. RELASZ
+            // 15. RELAENT
+            // 16. RELACOUNT
+            // 17. TEXTREL
+            // 18. INIT
+            // 19. FINI
+            // 20. PREINIT_ARRAY -> .preinit_array
+            // 21. PREINIT_ARRAYSZ
+            // 22. INIT_ARRAY -> .init_array
+            // 23. INIT_ARRAYSZ
+            // 24. FINI_ARRAY -> .fini_array
+            // 25. FINI_ARRAYSZ
+            // 26. NEEDED
+            // 27. DEBUG
+            // 28. BIND_NOW
+            // 29. FLAGS
+            // 30. FLAGS_1
+            // 31. VERSYM -> .gnu.version
+            // 32. VERNEED -> .gnu.version_r
+            // 33. VERNEEDNUM
+            // 34. NULL
+            if opt.hash_style.sysv {
+                // HASH
+                self.dynamic_entries_count += 1;
+            }
+            if opt.hash_style.gnu {
+                // GNU_HASH
+                self.dynamic_entries_count += 1;
+            }
+            if opt.soname.is_some() {
+                // SONAME
+                self.dynamic_entries_count += 1;
+            }
+            if !opt.rpath.is_empty() {
+                // RUNPATH or RPATH
+                self.dynamic_entries_count += 1;
+            }
+            if output_relocations.contains_key(".rela.plt") {
+                // PLTGOT, PLTRELSZ, PLTREL, JMPREL
+                self.dynamic_entries_count += 4;
+            }
+            if let Some(rela_dyn) = output_relocations.get(".rela.dyn") {
+                // RELA, RELASZ, RELAENT
+                self.dynamic_entries_count += 3;
+                if relative_count(rela_dyn) > 0 {
+                    // RELACOUNT
+                    self.dynamic_entries_count += 1;
+                }
+            }
+            if self.text_relocation {
+                // TEXTREL
+                self.dynamic_entries_count += 1;
+            }
+            for (_, name) in init_fini_functions(opt) {
+                if symbols.get(name).is_some_and(|symbol| {
+                    !symbol.is_plt && symbol.section_name != UNDEFINED_SECTION
+                }) {
+                    // INIT or FINI
+                    self.dynamic_entries_count += 1;
+                }
+            }
+            for name in [".preinit_array", ".init_array", ".fini_array"] {
+                if output_sections.contains_key(name) {
+                    // address and size of the array
+                    self.dynamic_entries_count += 2;
+                }
+            }
+            // NEEDED
+            self.dynamic_entries_count += self.needed.len();
+            if !opt.shared {
+                // DEBUG
+                self.dynamic_entries_count += 1;
+            }
+            if opt.z_options.now {
+                // BIND_NOW
+                self.dynamic_entries_count += 1;
+            }
+            if dynamic_flags(opt, self.text_relocation, self.static_tls) != 0 {
+                // FLAGS
+                self.dynamic_entries_count += 1;
+            }
+            if !self.version_needs.is_empty() {
+                // VERSYM, VERNEED, VERNEEDNUM
+                self.dynamic_entries_count += 3;
+            }
+
+            // dynamic symbols
+            writer.reserve_null_dynamic_symbol_index();
+            for dyn_sym in plt_dynamic_symbols.iter().chain(dynamic_symbols.iter()) {
+                let symbol = symbols.get_mut(&dyn_sym.name).unwrap();
+                symbol.symbol_name_dynamic_string_id =
+                    Some(writer.add_dynamic_string(arena.alloc_str(&dyn_sym.name).as_bytes()));
+                writer.reserve_dynamic_symbol_index();
+            }
+
+            if let Some(soname) = &opt.soname {
+                self.soname_dynamic_string_index =
+                    Some(writer.add_dynamic_string(arena.alloc_str(soname).as_bytes()))
+            };
+
+            if !opt.rpath.is_empty() {
+                // multiple -rpath are concatenated, $ORIGIN is kept as is
+                let runpath = opt.rpath.join(":");
+                self.runpath_dynamic_string_index =
+                    Some(writer.add_dynamic_string(arena.alloc_str(&runpath).as_bytes()))
+            }
+
+            for needed in &mut self.needed {
+                needed.name_string_id =
+                    Some(writer.add_dynamic_string(arena.alloc_str(&needed.name).as_bytes()));
+            }
+
+            for need in &mut self.version_needs {
+                need.file_string_id =
+                    Some(writer.add_dynamic_string(arena.alloc_str(&need.file).as_bytes()));
+                for version in &need.versions {
+                    need.version_string_ids
+                        .push(writer.add_dynamic_string(arena.alloc_str(version).as_bytes()));
+                }
+            }
+
+            self.dynsym_section_offset = writer.reserve_dynsym() as u64;
+
+            // dynamic string, even if empty for DT_STRTAB
+            writer.require_dynstr();
+            self.dynstr_section_offset = writer.reserve_dynstr() as u64;
+
+            // hash table
+            let plt_dynamic_symbols_count = plt_dynamic_symbols.len() as u32;
+            let dynamic_symbols_count = dynamic_symbols.len() as u32;
+            if opt.hash_style.sysv {
+                // chain count: 1 extra element for NULL symbol
+                self.hash_section_offset = writer.reserve_hash(
+                    plt_dynamic_symbols_count + dynamic_symbols_count,
+                    plt_dynamic_symbols_count + dynamic_symbols_count + 1,
+                ) as u64;
+            }
+
+            // gnu hash table
+            if opt.hash_style.gnu {
+                // plt dynamic symbols are not included in gnu hash table
+                let layout = gnu_hash_layout(opt, dynamic_symbols.len());
+                self.gnu_hash_section_offset = writer.reserve_gnu_hash(
+                    layout.bloom_count,
+                    layout.bucket_count,
+                    dynamic_symbols_count,
+                ) as u64;
+            }
+
+            // symbol versions
+            if !self.version_needs.is_empty() {
+                self.gnu_versym_section_offset = writer.reserve_gnu_versym() as u64;
+                let vernaux_count = self
+                    .version_needs
+                    .iter()
+                    .map(|need| need.versions.len())
+                    .sum();
+                self.gnu_verneed_section_offset =
+                    writer.reserve_gnu_verneed(self.version_needs.len(), vernaux_count) as u64;
+            }
+        };
+        extend_load_segments(
+            load_segments,
+            tables_offset,
+            writer.reserved_len() as u64 - tables_offset,
+            object::elf::PF_R,
+            None,
+            false,
+        );
+
+        // thus code begins at the next page, e.g. 0x401000
+        reserve_output_sections(
+            writer,
+            output_sections,
+            &middle,
+            &fixed,
+            load_segments,
+            self.common_page_size,
+            self.max_page_size,
+        );
+
+        if is_dynamic {
+            // .dynamic is written by the dynamic linker, e.g. DT_DEBUG
+            let writable = object::elf::PF_R | object::elf::PF_W;
+            let align = if load_segments.last().unwrap().flags == writable {
+                self.common_page_size
+            } else {
+                self.max_page_size
+            };
+            writer.reserve_until((writer.reserved_len() as u64).next_multiple_of(align) as usize);
+            self.dynamic_section_offset = writer.reserve_dynamic(self.dynamic_entries_count) as u64;
+            extend_load_segments(
+                load_segments,
+                self.dynamic_section_offset,
+                (self.dynamic_entries_count * format.class.dyn_size()) as u64,
+                writable,
+                None,
+                false,
+            );
+        }
+
+        // writable data
+        reserve_output_sections(
+            writer,
+            output_sections,
+            &late,
+            &fixed,
+            load_segments,
+            self.common_page_size,
+            self.max_page_size,
+        );
+        debug_assert_eq!(load_segments.len(), load_segments_count);
+
+        // non-alloc sections follow the loaded image
+        for output_section in self.nonalloc_sections.values_mut() {
+            output_section.offset = writer.reserve(
+                output_section.content.len(),
+                output_section.alignment.max(1) as usize,
+            ) as u64;
+        }
+
+        // segments placed by --section-start must not share pages with others
+        let mut ranges: Vec<(u64, u64)> = load_segments
+            .iter()
+            .map(|segment| (segment.address, segment.address + segment.size))
+            .collect();
+        ranges.sort();
+        for pair in ranges.windows(2) {
+            if pair[0].1.next_multiple_of(self.max_page_size)
+                > pair[1].0 - pair[1].0 % self.max_page_size
+            {
+                bail!(
+                    "Loadable segments at {:#x} and {:#x} overlap",
+                    pair[0].0,
+                    pair[1].0
+                );
+            }
+        }
+        info!("Got {} output sections", output_sections.len());
+
+        // TLS template spans from the first to the last TLS section
+        for name in layout_order(output_sections) {
+            let output_section = &output_sections[&name];
+            if !output_section.is_tls {
+                continue;
+            }
+            let tls = self.tls_segment.get_or_insert(TlsSegment {
+                offset: output_section.offset,
+                file_size: 0,
+                mem_size: 0,
+                alignment: 1,
+            });
+            let end = output_section.offset + output_section.content.len() as u64 - tls.offset;
+            if !output_section.is_bss {
+                tls.file_size = tls.file_size.max(end);
+            }
+            tls.mem_size = tls.mem_size.max(end);
+            tls.alignment = tls.alignment.max(output_section.alignment);
+        }
+
+        // reserve section headers
+        writer.reserve_null_section_index();
+        // use typed-arena to avoid borrow to `output_sections`
+        for name in layout_order(output_sections) {
+            let output_section = output_sections.get_mut(&name).unwrap();
+            output_section.name_string_id = Some(
+                writer.add_section_name(arena.alloc_str(unique_section_name(&name)).as_bytes()),
+            );
+            output_section.section_index = Some(writer.reserve_section_index());
+        }
+        for (name, output_section) in output_relocations.iter_mut() {
+            output_section.name_string_id =
+                Some(writer.add_section_name(arena.alloc_str(name).as_bytes()));
+            output_section.section_index = Some(writer.reserve_section_index());
+        }
+        for (name, output_section) in self.nonalloc_sections.iter_mut() {
+            output_section.name_string_id =
+                Some(writer.add_section_name(arena.alloc_str(name).as_bytes()));
+            output_section.section_index = Some(writer.reserve_section_index());
+        }
+        if !opt.strip_all {
+            let _symtab_section_index = writer.reserve_symtab_section_index();
+            let _strtab_section_index = writer.reserve_strtab_section_index();
+        }
+        let _shstrtab_section_index = writer.reserve_shstrtab_section_index();
+        if opt.shared || self.dynamic_link {
+            // .dynamic, .dynsym, .dynstr, .hash, .gnu_hash
+            *dynamic_section_index = writer.reserve_dynamic_section_index();
+            *dynsym_section_index = writer.reserve_dynsym_section_index();
+            let _dynstr_section_index = writer.reserve_dynstr_section_index();
+            if opt.hash_style.sysv {
+                let _hash_section_index = writer.reserve_hash_section_index();
+            }
+            if opt.hash_style.gnu {
+                let _gnu_hash_section_index = writer.reserve_gnu_hash_section_index();
+            }
+            if !self.version_needs.is_empty() {
+                // .gnu.version, .gnu.version_r
+                let _gnu_versym_section_index = writer.reserve_gnu_versym_section_index();
+                let _gnu_verneed_section_index = writer.reserve_gnu_verneed_section_index();
+            }
+        }
+
+        // prepare symbol table, unless stripped by -s
+        if !opt.strip_all {
+            writer.reserve_null_symbol_index();
+            for (symbol_name, symbol) in symbols.iter_mut() {
+                if !symbol.in_symtab {
+                    // discarded by -x/-X
+                    continue;
+                }
+                if let Some(retained_symbols) = &self.retained_symbols {
+                    if !retained_symbols.contains(symbol_name) {
+                        // discarded by --retain-symbols-file
+                        continue;
+                    }
+                }
+                symbol.symbol_name_string_id =
+                    Some(writer.add_string(arena.alloc_str(symbol_name).as_bytes()));
+                writer.reserve_symbol_index(None);
+            }
+
+            // STT_FILE symbols of objects with local symbols in .symtab,
+            // or recorded in the objects, like GNU ld
+            if self.retained_symbols.is_none() && opt.discard != Discard::All {
+                let mut used: BTreeSet<usize> = symbols
+                    .values()
+                    .filter(|sym| sym.symbol_name_string_id.is_some() && !sym.is_global_binding())
+                    .filter_map(|sym| sym.file_symbol)
+                    .collect();
+                used.extend(
+                    self.file_symbols
+                        .iter()
+                        .enumerate()
+                        .filter(|(_index, file_symbol)| file_symbol.from_input)
+                        .map(|(index, _file_symbol)| index),
+                );
+                for index in used {
+                    let file_symbol = &mut self.file_symbols[index];
+                    file_symbol.string_id =
+                        Some(writer.add_string(arena.alloc_str(&file_symbol.name).as_bytes()));
+                    writer.reserve_symbol_index(None);
+                }
+            }
+        }
+
+        // reserve section headers, symtab, strtab and shstrtab, not loaded
+        writer.reserve_section_headers();
+        if !opt.strip_all {
+            writer.reserve_symtab();
+            writer.reserve_strtab();
+        }
+        writer.reserve_shstrtab();
+
+        Ok(())
+    }
+
+    fn write(&mut self) -> anyhow::Result<()> {
+        let format = self.format;
+        let Linker {
+            opt,
+            output_sections,
+            output_relocations,
+            symbols,
+            dynamic_symbols,
+            plt_dynamic_symbols,
+            writer,
+            soname_dynamic_string_index,
+            section_address,
+            ..
+        } = self;
+
+        // all set! we can now write actual data to buffer
+        // compute entrypoint address
+        let entry_address = if opt.shared {
+            // building shared library, no entrypoint
+            0
+        } else {
+            let entry_symbol = &symbols["_start"];
+            section_address[&entry_symbol.section_name] + entry_symbol.offset
+        };
+        // TLS symbols are valued relative to the TLS template
+        let tls_address = self
+            .tls_segment
+            .as_ref()
+            .map_or(0, |tls| segment_address(&self.load_segments, tls.offset));
+
+        // ELF header
+        writer.write_file_header(&FileHeader {
+            // STT_GNU_IFUNC is a GNU extension
+            os_abi: if symbols.values().any(|symbol| symbol.is_ifunc()) {
+                object::elf::ELFOSABI_GNU
+            } else {
+                object::elf::ELFOSABI_NONE
+            },
+            abi_version: 0,
+            e_type: if opt.shared || opt.pie {
+                object::elf::ET_DYN
+            } else {
+                object::elf::ET_EXEC
+            },
+            e_machine: object::elf::EM_X86_64,
+            // assume that entrypoint is pointed at _start
+            e_entry: entry_address,
+            e_flags: 0,
+        })?;
+
+        // program headers
+        // https://refspecs.linuxbase.org/elf/gabi4+/ch5.pheader.html
+
+        // PT_PHDR The array element, if present, specifies the location and
+        // size of the program header table itself, both in the file and in the
+        // memory image of the program. This segment type may not occur more
+        // than once in a file. Moreover, it may occur only if the program
+        // header table is part of the memory image of the program. If it is
+        // present, it must precede any loadable segment entry.
+        writer.write_program_header(&ProgramHeader {
+            p_type: object::elf::PT_PHDR,
+            p_flags: object::elf::PF_R,
+            p_offset: self.phdr_offset as u64,
+            p_vaddr: segment_address(&self.load_segments, self.phdr_offset as u64),
+            p_paddr: segment_address(&self.load_segments, self.phdr_offset as u64),
+            p_filesz: self.phdr_len as u64,
+            p_memsz: self.phdr_len as u64,
+            p_align: 8,
+        });
+
+        // ask kernel to load segments into memory
+        if output_sections.contains_key(".interp") {
+            // PT_INTERP The array element specifies the location and size of a
+            // null-terminated path name to invoke as an interpreter. This
+            // segment type is meaningful only for executable files (though it
+            // may occur for shared objects); it may not occur more than once in
+            // a file. If it is present, it must precede any loadable segment
+            // entry. See ``Program Interpreter'' below for more information.
+            writer.write_program_header(&ProgramHeader {
+                p_type: object::elf::PT_INTERP,
+                p_flags: object::elf::PF_R,
+                p_offset: output_sections[".interp"].offset,
+                p_vaddr: section_address[".interp"],
+                p_paddr: section_address[".interp"],
+                p_filesz: output_sections[".interp"].content.len() as u64,
+                p_memsz: output_sections[".interp"].content.len() as u64,
+                p_align: 1,
+            });
+        }
+
+        // PT_LOAD The array element specifies a loadable segment, described by
+        // p_filesz and p_memsz. The bytes from the file are mapped to the
+        // beginning of the memory segment. If the segment's memory size
+        // (p_memsz) is larger than the file size (p_filesz), the ``extra''
+        // bytes are defined to hold the value 0 and to follow the segment's
+        // initialized area. The file size may not be larger than the memory
+        // size. Loadable segment entries in the program header table appear in
+        // ascending order, sorted on the p_vaddr member.
+        let mut load_segments: Vec<&LoadSegment> = self.load_segments.iter().collect();
+        load_segments.sort_by_key(|segment| segment.address);
+        for segment in load_segments {
+            writer.write_program_header(&ProgramHeader {
+                p_type: object::elf::PT_LOAD,
+                p_flags: segment.flags,
+                p_offset: segment.offset,
+                p_vaddr: segment.address,
+                p_paddr: segment.physical_address,
+                p_filesz: segment.size,
+                p_memsz: segment.size,
+                p_align: self.max_page_size,
+            });
+        }
         if opt.shared || self.dynamic_link {
-            // PT_DYNAMIC
-            program_headers_count += 1;
+            // PT_DYNAMIC The array element specifies dynamic linking
+            // information. See ``Dynamic Section'' below for more information.
+            writer.write_program_header(&ProgramHeader {
+                p_type: object::elf::PT_DYNAMIC,
+                p_flags: object::elf::PF_W | object::elf::PF_R,
+                p_offset: self.dynamic_section_offset,
+                p_vaddr: segment_address(&self.load_segments, self.dynamic_section_offset),
+                p_paddr: segment_address(&self.load_segments, self.dynamic_section_offset),
+                p_filesz: (self.dynamic_entries_count * format.class.dyn_size()) as u64,
+                p_memsz: (self.dynamic_entries_count * format.class.dyn_size()) as u64,
+                p_align: 8,
+            });
         }
-        if !opt.shared && self.dynamic_link {
-            // PT_INTERP
-            program_headers_count += 1;
+
+        for note in &self.note_segments {
+            // PT_NOTE The array element specifies the location and size of
+            // auxiliary information.
+            writer.write_program_header(&ProgramHeader {
+                p_type: object::elf::PT_NOTE,
+                p_flags: object::elf::PF_R,
+                p_offset: note.offset,
+                p_vaddr: segment_address(&self.load_segments, note.offset),
+                p_paddr: segment_address(&self.load_segments, note.offset),
+                p_filesz: note.size,
+                p_memsz: note.size,
+                p_align: note.alignment,
+            });
         }
-        *phdr_offset = writer.reserved_len();
-        *phdr_len = program_headers_count * std::mem::size_of::<ProgramHeader64<LittleEndian>>();
-        writer.reserve_program_headers(program_headers_count as u32);
 
-        // thus sections begin at 0x401000
-        for (_name, output_section) in output_sections.iter_mut() {
-            output_section.offset = writer.reserve(output_section.content.len(), 4096) as u64;
+        if let Some(property) = output_sections.get(".note.gnu.property") {
+            // PT_GNU_PROPERTY The array element specifies the location and
+            // size of the .note.gnu.property section.
+            writer.write_program_header(&ProgramHeader {
+                p_type: object::elf::PT_GNU_PROPERTY,
+                p_flags: object::elf::PF_R,
+                p_offset: property.offset,
+                p_vaddr: section_address[".note.gnu.property"],
+                p_paddr: section_address[".note.gnu.property"],
+                p_filesz: property.content.len() as u64,
+                p_memsz: property.content.len() as u64,
+                p_align: 8,
+            });
         }
-        info!("Got {} output sections", output_sections.len());
 
-        // reserve .rela.xx sections
-        for (_name, output_section) in output_relocations.iter_mut() {
-            output_section.offset = writer.reserve(
-                output_section.relocations.len()
-                    * std::mem::size_of::<object::elf::Rela64<LittleEndian>>(),
-                8,
-            ) as u64;
+        if let Some(hdr) = output_sections.get(".eh_frame_hdr") {
+            // PT_GNU_EH_FRAME The array element specifies the location and
+            // size of the .eh_frame_hdr section.
+            writer.write_program_header(&ProgramHeader {
+                p_type: object::elf::PT_GNU_EH_FRAME,
+                p_flags: object::elf::PF_R,
+                p_offset: hdr.offset,
+                p_vaddr: section_address[".eh_frame_hdr"],
+                p_paddr: section_address[".eh_frame_hdr"],
+                p_filesz: hdr.content.len() as u64,
+                p_memsz: hdr.content.len() as u64,
+                p_align: 4,
+            });
         }
 
-        // reserve section headers
-        writer.reserve_null_section_index();
-        // use typed-arena to avoid borrow to `output_sections`
-        for (name, output_section) in output_sections.iter_mut() {
-            output_section.name_string_id =
-                Some(writer.add_section_name(arena.alloc_str(name).as_bytes()));
-            output_section.section_index = Some(writer.reserve_section_index());
+        if let Some(tls) = &self.tls_segment {
+            // PT_TLS The array element specifies the Thread-Local Storage
+            // template. Implementations need not support this program table
+            // entry.
+            writer.write_program_header(&ProgramHeader {
+                p_type: object::elf::PT_TLS,
+                p_flags: object::elf::PF_R,
+                p_offset: tls.offset,
+                p_vaddr: segment_address(&self.load_segments, tls.offset),
+                p_paddr: segment_address(&self.load_segments, tls.offset),
+                p_filesz: tls.file_size,
+                p_memsz: tls.mem_size,
+                p_align: tls.alignment,
+            });
         }
-        for (name, output_section) in output_relocations.iter_mut() {
-            output_section.name_string_id =
-                Some(writer.add_section_name(arena.alloc_str(name).as_bytes()));
-            writer.reserve_section_index();
+
+        // PT_GNU_STACK The p_flags member specifies the permissions on the
+        // segment containing the stack and is used to indicate whether the
+        // stack should be executable. The p_memsz member, if non-zero, is the
+        // requested stack size.
+        let mut stack_flags = object::elf::PF_W | object::elf::PF_R;
+        if self.exec_stack {
+            stack_flags |= object::elf::PF_X;
         }
-        let _symtab_section_index = writer.reserve_symtab_section_index();
-        let _strtab_section_index = writer.reserve_strtab_section_index();
-        let _shstrtab_section_index = writer.reserve_shstrtab_section_index();
+        writer.write_program_header(&ProgramHeader {
+            p_type: object::elf::PT_GNU_STACK,
+            p_flags: stack_flags,
+            p_offset: 0,
+            p_vaddr: 0,
+            p_paddr: 0,
+            p_filesz: 0,
+            p_memsz: opt.z_options.stack_size.unwrap_or(0),
+            p_align: 16,
+        });
+
+        // write section data and tables of the dynamic linker, in the order
+        // of reserve()
+        let [early, middle, late] = layout_parts(output_sections);
+        for name in &early {
+            let output_section = &output_sections[name];
+            writer.pad_until(output_section.offset as usize);
+            writer.write(&output_section.content);
+        }
+        for (_name, output_section) in output_relocations.iter() {
+            writer.pad_until(output_section.offset as usize);
+            for rel in &output_section.relocations {
+                // turn offset into absolute
+                let mut addend = rel.addend;
+                if let Some(target) = &rel.target {
+                    addend += target_address(target, symbols, section_address) as i64;
+                    // offset into the tls template of this module
+                    if rel.r_type == R_X86_64_TPOFF64 || rel.r_type == R_X86_64_TLSDESC {
+                        addend -= tls_address as i64;
+                    }
+                }
+                writer.write_relocation(
+                    true,
+                    &Rel {
+                        r_offset: section_address[&rel.section_name] + rel.offset,
+                        r_sym: rel.symbol_index,
+                        r_type: rel.r_type,
+                        r_addend: addend,
+                    },
+                );
+            }
+        }
+
+        // shared library or dynamic linking
         if opt.shared || self.dynamic_link {
-            // .dynamic, .dynsym, .dynstr, .hash, .gnu_hash
-            *dynamic_section_index = writer.reserve_dynamic_section_index();
-            *dynsym_section_index = writer.reserve_dynsym_section_index();
-            let _dynstr_section_index = writer.reserve_dynstr_section_index();
+            // write dynamic symbols
+            writer.write_null_dynamic_symbol();
+            for dyn_sym in plt_dynamic_symbols.iter().chain(dynamic_symbols.iter()) {
+                let symbol = symbols.get(&dyn_sym.name).unwrap();
+                let mut address = section_address[&symbol.section_name] + symbol.offset;
+                if symbol.st_type == STT_TLS && !symbol.is_plt {
+                    address -= tls_address;
+                }
+                writer.write_dynamic_symbol(&Sym {
+                    name: symbol.symbol_name_dynamic_string_id,
+                    section: if symbol.is_plt || symbol.section_name == ABSOLUTE_SECTION {
+                        None
+                    } else {
+                        output_sections[&symbol.section_name].section_index
+                    },
+                    st_info: if symbol.is_weak {
+                        (object::elf::STB_WEAK) << 4
+                    } else {
+                        (object::elf::STB_GLOBAL) << 4
+                    } | symbol.st_type,
+                    st_other: symbol.visibility,
+                    st_shndx: if symbol.section_name == ABSOLUTE_SECTION {
+                        object::elf::SHN_ABS
+                    } else {
+                        0
+                    },
+                    // undefined symbol with non-zero value: canonical plt entry
+                    st_value: if symbol.is_plt && !symbol.canonical_plt {
+                        0
+                    } else {
+                        address
+                    },
+                    st_size: symbol.size,
+                });
+            }
+
+            // write dynamic string table
+            writer.write_dynstr();
+
+            // write hash table
             if opt.hash_style.sysv {
-                let _hash_section_index = writer.reserve_hash_section_index();
+                writer.write_hash(
+                    (plt_dynamic_symbols.len() + dynamic_symbols.len()) as u32,
+                    (plt_dynamic_symbols.len() + dynamic_symbols.len()) as u32 + 1, // + 1 for NULL symbol at start
+                    |idx| {
+                        // compute sysv hash of symbol name
+                        // 0 is reserved for null, skip
+                        if idx == 0 {
+                            None
+                        } else if idx <= plt_dynamic_symbols.len() as u32 {
+                            // UNDEF
+                            None
+                        } else {
+                            Some(object::elf::hash(
+                                dynamic_symbols[idx as usize - 1 - plt_dynamic_symbols.len()]
+                                    .name
+                                    .as_bytes(),
+                            ))
+                        }
+                    },
+                );
             }
+
+            // write gnu hash table
             if opt.hash_style.gnu {
-                let _gnu_hash_section_index = writer.reserve_gnu_hash_section_index();
+                let layout = gnu_hash_layout(opt, dynamic_symbols.len());
+                writer.write_gnu_hash(
+                    1 + plt_dynamic_symbols.len() as u32, // skip NULL symbol and plt UNDEF symbols
+                    layout.bloom_shift,
+                    layout.bloom_count,
+                    layout.bucket_count,
+                    dynamic_symbols.len() as u32,
+                    |idx| {
+                        // compute gnu hash of symbol name
+                        object::elf::gnu_hash(dynamic_symbols[idx as usize].name.as_bytes())
+                    },
+                );
+            }
+
+            // write symbol versions
+            if !self.version_needs.is_empty() {
+                writer.write_null_gnu_versym();
+                for dyn_sym in plt_dynamic_symbols.iter().chain(dynamic_symbols.iter()) {
+                    writer.write_gnu_versym(version_index(&self.version_needs, &dyn_sym.version));
+                }
+
+                writer.write_align_gnu_verneed();
+                for need in &self.version_needs {
+                    writer.write_gnu_verneed(&Verneed {
+                        version: object::elf::VER_NEED_CURRENT,
+                        aux_count: need.versions.len() as u16,
+                        file: need.file_string_id.unwrap(),
+                    });
+                    for (version, name) in need.versions.iter().zip(&need.version_string_ids) {
+                        writer.write_gnu_vernaux(&Vernaux {
+                            flags: 0,
+                            index: version_index(
+                                &self.version_needs,
+                                &Some((need.file.clone(), version.clone())),
+                            ),
+                            name: *name,
+                        });
+                    }
+                }
             }
         }
-        writer.reserve_section_headers();
 
-        // prepare symbol table
-        writer.reserve_null_symbol_index();
-        for (symbol_name, symbol) in symbols.iter_mut() {
-            symbol.symbol_name_string_id =
-                Some(writer.add_string(arena.alloc_str(symbol_name).as_bytes()));
-            writer.reserve_symbol_index(None);
+        for name in &middle {
+            let output_section = &output_sections[name];
+            writer.pad_until(output_section.offset as usize);
+            writer.write(&output_section.content);
         }
 
-        // reserve symtab, strtab and shstrtab
-        writer.reserve_symtab();
-        writer.reserve_strtab();
-        writer.reserve_shstrtab();
+        if opt.shared || self.dynamic_link {
+            writer.pad_until(self.dynamic_section_offset as usize);
+            // https://refspecs.linuxbase.org/elf/gabi4+/ch5.dynamic.html#dynamic_section
+            // .dynamic section entries:
+            // 1. HASH -> .hash
+            // 2. GNU_HASH -> .gnu_hash
+            // 3. STRTAB -> .dynstr
+            // 4. SYMTAB -> .dynsym
+            // 5. STRSZ
+            // 6. SYMENT
+            // 7. SONAME
+            // 8. RUNPATH/RPATH
+            // 9. PLTGOT -> .got.plt
+            // 10. PLTRELSZ
+            // 11. PLTREL
+            // 12. JMPREL -> .rela.plt
+            // 13. RELA -> .rela.dyn
+            // 14. RELASZ
+            // 15. RELAENT
+            // 16. RELACOUNT
+            // 17. TEXTREL
+            // 18. INIT
+            // 19. FINI
+            // 20. PREINIT_ARRAY -> .preinit_array
+            // 21. PREINIT_ARRAYSZ
+            // 22. INIT_ARRAY -> .init_array
+            // 23. INIT_ARRAYSZ
+            // 24. FINI_ARRAY -> .fini_array
+            // 25. FINI_ARRAYSZ
+            // 26. NEEDED
+            // 27. DEBUG
+            // 28. BIND_NOW
+            // 29. FLAGS
+            // 30. FLAGS_1
+            // 31. VERSYM -> .gnu.version
+            // 32. VERNEED -> .gnu.version_r
+            // 33. VERNEEDNUM
+            // 34. NULL
+            writer.write_align_dynamic();
+            if opt.hash_style.sysv {
+                // DT_HASH This element holds the address of the symbol hash
+                // table, described in ``Hash Table''. This hash table refers to
+                // the symbol table referenced by the DT_SYMTAB element.
+                writer.write_dynamic(
+                    DT_HASH,
+                    segment_address(&self.load_segments, self.hash_section_offset),
+                );
+            }
+            if opt.hash_style.gnu {
+                writer.write_dynamic(
+                    DT_GNU_HASH,
+                    segment_address(&self.load_segments, self.gnu_hash_section_offset),
+                );
+            }
+
+            // DT_STRTAB This element holds the address of the string table,
+            // described in Chapter 4. Symbol names, library names, and other
+            // strings reside in this table.
+            writer.write_dynamic(
+                DT_STRTAB,
+                segment_address(&self.load_segments, self.dynstr_section_offset),
+            );
+
+            // DT_SYMTAB This element holds the address of the symbol table,
+            // described in the first part of this chapter, with Elf32_Sym
+            // entries for the 32-bit class of files and Elf64_Sym entries for
+            // the 64-bit class of files.
+            writer.write_dynamic(
+                DT_SYMTAB,
+                segment_address(&self.load_segments, self.dynsym_section_offset),
+            );
+
+            // DT_STRSZ This element holds the size, in bytes, of the string
+            // table.
+            let strsz = writer.dynstr_len() as u64;
+            writer.write_dynamic(DT_STRSZ, strsz); // size of dynamic string table
+
+            // DT_SYMENT This element holds the size, in bytes, of a symbol
+            // table entry.
+            writer.write_dynamic(DT_SYMENT, format.class.sym_size() as u64); // entry size
 
-        // reserve dynamic, dynsym, dynstr, hash and gnu_hash
-        self.dynamic_entries_count = 6;
-        if opt.shared || self.dynamic_link {
-            // dynamic entries:
-            // 1. HASH -> .hash
-            // 2. GNU_HASH -> .gnu_hash
-            // 3. STRTAB -> .dynstr
-            // 4. SYMTAB -> .dynsym
-            // 5. STRSZ
-            // 6. SYMENT
-            // 7. SONAME
-            // 8. PLTGOT -> .got.plt
-            // 9. PLTRELSZ
-            // 10. PLTREL
-            // 11. JMPREL -> .rela.plt
-            // 12. NEEDED
-            // 13. FLAGS_1
-            // 14. NULL
-            if opt.hash_style.sysv {
-                // HASH
-                self.dynamic_entries_count += 1;
-            }
-            if opt.hash_style.gnu {
-                // GNU_HASH
-                self.dynamic_entries_count += 1;
-            }
-            if opt.soname.is_some() {
-                // SONAME
-                self.dynamic_entries_count += 1;
+            if let Some(soname_dynamic_string_index) = &soname_dynamic_string_index {
+                // DT_SONAME This element holds the string table offset of a
+                // null-terminated string, giving the name of the shared object.
+                // The offset is an index into the table recorded in the
+                // DT_STRTAB entry. See ``Shared Object Dependencies'' below for
+                // more information about these names.
+                writer.write_dynamic_string(DT_SONAME, *soname_dynamic_string_index);
             }
-            if self.dynamic_link {
-                // PLTGOT, PLTRELSZ, PLTREL, JMPREL
-                self.dynamic_entries_count += 4;
+
+            if let Some(runpath_dynamic_string_index) = &self.runpath_dynamic_string_index {
+                if opt.disable_new_dtags {
+                    // DT_RPATH This element holds the string table offset of a
+                    // null-terminated search library search path string
+                    // discussed in ``Shared Object Dependencies''. The offset
+                    // is an index into the table recorded in the DT_STRTAB
+                    // entry.
+                    writer.write_dynamic_string(DT_RPATH, *runpath_dynamic_string_index);
+                } else {
+                    // DT_RUNPATH This element holds the string table offset of
+                    // a null-terminated library search path string discussed
+                    // in ``Shared Object Dependencies''. The offset is an index
+                    // into the table recorded in the DT_STRTAB entry.
+                    writer.write_dynamic_string(DT_RUNPATH, *runpath_dynamic_string_index);
+                }
             }
-            // NEEDED
-            self.dynamic_entries_count += self.needed.len();
 
-            // align to 8 bytes boundary
-            self.dynamic_section_offset = writer.reserve_dynamic(self.dynamic_entries_count) as u64;
+            if output_relocations.contains_key(".rela.plt") {
+                // DT_PLTGOT This element holds an address associated with the
+                // procedure linkage table and/or the global offset table. See
+                // this section in the processor supplement for details.
+                writer.write_dynamic(DT_PLTGOT, section_address[".got.plt"]);
 
-            // dynamic symbols
-            writer.reserve_null_dynamic_symbol_index();
-            for dyn_sym in plt_dynamic_symbols.iter().chain(dynamic_symbols.iter()) {
-                let symbol = symbols.get_mut(&dyn_sym.name).unwrap();
-                symbol.symbol_name_dynamic_string_id =
-                    Some(writer.add_dynamic_string(arena.alloc_str(&dyn_sym.name).as_bytes()));
-                writer.reserve_dynamic_symbol_index();
-            }
+                // DT_PLTRELSZ This element holds the total size, in bytes, of
+                // the relocation entries associated with the procedure linkage
+                // table. If an entry of type DT_JMPREL is present, a
+                // DT_PLTRELSZ must accompany it.
+                writer.write_dynamic(
+                    DT_PLTRELSZ,
+                    (output_relocations[".rela.plt"].relocations.len() * format.rela_size()) as u64,
+                );
 
-            if let Some(soname) = &opt.soname {
-                self.soname_dynamic_string_index =
-                    Some(writer.add_dynamic_string(arena.alloc_str(soname).as_bytes()))
-            };
+                // DT_PLTREL This member specifies the type of relocation entry
+                // to which the procedure linkage table refers. The d_val member
+                // holds DT_REL or DT_RELA, as appropriate. All relocations in a
+                // procedure linkage table must use the same relocation.
+                writer.write_dynamic(DT_PLTREL, DT_RELA as u64);
 
-            for needed in &mut self.needed {
-                needed.name_string_id =
-                    Some(writer.add_dynamic_string(arena.alloc_str(&needed.name).as_bytes()));
+                // DT_JMPREL If present, this entry's d_ptr member holds the
+                // address of relocation entries associated solely with the
+                // procedure linkage table. Separating these relocation entries
+                // lets the dynamic linker ignore them during process
+                // initialization, if lazy binding is enabled. If this entry is
+                // present, the related entries of types DT_PLTRELSZ and
+                // DT_PLTREL must also be present.
+                writer.write_dynamic(DT_JMPREL, section_address[".rela.plt"]);
             }
 
-            self.dynsym_section_offset = writer.reserve_dynsym() as u64;
+            if let Some(rela_dyn) = output_relocations.get(".rela.dyn") {
+                // DT_RELA This element holds the address of a relocation
+                // table, described in Chapter 4. Entries in the table have
+                // explicit addends, such as Elf32_Rela for the 32-bit file
+                // class or Elf64_Rela for the 64-bit file class.
+                writer.write_dynamic(DT_RELA, section_address[".rela.dyn"]);
 
-            // dynamic string
-            self.dynstr_section_offset = writer.reserve_dynstr() as u64;
+                // DT_RELASZ This element holds the total size, in bytes, of
+                // the DT_RELA relocation table.
+                writer.write_dynamic(
+                    DT_RELASZ,
+                    (rela_dyn.relocations.len() * format.rela_size()) as u64,
+                );
 
-            // hash table
-            let plt_dynamic_symbols_count = plt_dynamic_symbols.len() as u32;
-            let dynamic_symbols_count = dynamic_symbols.len() as u32;
-            if opt.hash_style.sysv {
-                // chain count: 1 extra element for NULL symbol
-                self.hash_section_offset = writer.reserve_hash(
-                    plt_dynamic_symbols_count + dynamic_symbols_count,
-                    plt_dynamic_symbols_count + dynamic_symbols_count + 1,
-                ) as u64;
+                // DT_RELAENT This element holds the size, in bytes, of the
+                // DT_RELA relocation entry.
+                writer.write_dynamic(DT_RELAENT, format.rela_size() as u64);
+
+                // DT_RELACOUNT The number of R_X86_64_RELATIVE relocations,
+                // which are placed first in the DT_RELA relocation table.
+                let count = relative_count(rela_dyn);
+                if count > 0 {
+                    writer.write_dynamic(DT_RELACOUNT, count as u64);
+                }
             }
 
-            // gnu hash table
-            if opt.hash_style.gnu {
-                // plt dynamic symbols are not included in gnu hash table
-                self.gnu_hash_section_offset =
-                    writer.reserve_gnu_hash(1, dynamic_symbols_count, dynamic_symbols_count) as u64;
+            if self.text_relocation {
+                // DT_TEXTREL This member's absence signifies that no
+                // relocation entry should cause a modification to a
+                // non-writable segment, as specified by the segment
+                // permissions in the program header table.
+                writer.write_dynamic(DT_TEXTREL, 0);
             }
-        };
 
-        Ok(())
-    }
+            // DT_INIT This element holds the address of the initialization
+            // function. DT_FINI This element holds the address of the
+            // termination function.
+            for (tag, name) in init_fini_functions(opt) {
+                if let Some(symbol) = symbols
+                    .get(name)
+                    .filter(|symbol| !symbol.is_plt && symbol.section_name != UNDEFINED_SECTION)
+                {
+                    writer
+                        .write_dynamic(tag, section_address[&symbol.section_name] + symbol.offset);
+                }
+            }
 
-    fn write(&mut self) -> anyhow::Result<()> {
-        let Linker {
-            opt,
-            output_sections,
-            output_relocations,
-            symbols,
-            dynamic_symbols,
-            plt_dynamic_symbols,
-            writer,
-            soname_dynamic_string_index,
-            section_address,
-            ..
-        } = self;
+            if let Some(preinit_array) = output_sections.get(".preinit_array") {
+                // DT_PREINIT_ARRAY This element holds the address of the array
+                // of pointers to pre-initialization functions.
+                writer.write_dynamic(DT_PREINIT_ARRAY, section_address[".preinit_array"]);
+                // DT_PREINIT_ARRAYSZ This element holds the size in bytes of
+                // the array of pre-initialization functions pointed to by the
+                // DT_PREINIT_ARRAY entry.
+                writer.write_dynamic(DT_PREINIT_ARRAYSZ, preinit_array.content.len() as u64);
+            }
+            if let Some(init_array) = output_sections.get(".init_array") {
+                // DT_INIT_ARRAY This element holds the address of the array of
+                // pointers to initialization functions.
+                writer.write_dynamic(DT_INIT_ARRAY, section_address[".init_array"]);
+                // DT_INIT_ARRAYSZ This element holds the size in bytes of the
+                // array of initialization functions pointed to by the
+                // DT_INIT_ARRAY entry.
+                writer.write_dynamic(DT_INIT_ARRAYSZ, init_array.content.len() as u64);
+            }
+            if let Some(fini_array) = output_sections.get(".fini_array") {
+                // DT_FINI_ARRAY This element holds the address of the array of
+                // pointers to termination functions.
+                writer.write_dynamic(DT_FINI_ARRAY, section_address[".fini_array"]);
+                // DT_FINI_ARRAYSZ This element holds the size in bytes of the
+                // array of termination functions pointed to by the
+                // DT_FINI_ARRAY entry.
+                writer.write_dynamic(DT_FINI_ARRAYSZ, fini_array.content.len() as u64);
+            }
+            for needed in &self.needed {
+                // DT_NEEDED This element holds the string table offset of a
+                // null-terminated string, giving the name of a needed library.
+                // The offset is an index into the table recorded in the
+                // DT_STRTAB code. See ``Shared Object Dependencies'' for more
+                // information about these names. The dynamic array may contain
+                // multiple entries with this type. These entries' relative
+                // order is significant, though their relation to entries of
+                // other types is not.
+                writer.write_dynamic_string(DT_NEEDED, needed.name_string_id.unwrap());
+            }
 
-        // all set! we can now write actual data to buffer
-        // compute entrypoint address
-        let entry_address = if opt.shared {
-            // building shared library, no entrypoint
-            0
-        } else {
-            let entry_symbol = &symbols["_start"];
-            section_address[&entry_symbol.section_name] + entry_symbol.offset
-        };
+            if !opt.shared {
+                // DT_DEBUG This member is used for debugging. Its contents are
+                // not specified for the ABI; programs that access this entry
+                // are not ABI-conforming.
+                // the dynamic linker stores the address of r_debug here
+                writer.write_dynamic(DT_DEBUG, 0);
+            }
 
-        // ELF header
-        writer.write_file_header(&FileHeader {
-            os_abi: 0,
-            abi_version: 0,
-            e_type: if opt.shared || opt.pie {
-                object::elf::ET_DYN
-            } else {
-                object::elf::ET_EXEC
-            },
-            e_machine: object::elf::EM_X86_64,
-            // assume that entrypoint is pointed at _start
-            e_entry: entry_address,
-            e_flags: 0,
-        })?;
+            if opt.z_options.now {
+                // DT_BIND_NOW If present in a shared object or executable,
+                // this entry instructs the dynamic linker to process all
+                // relocations for the object containing this entry before
+                // transferring control to the program.
+                writer.write_dynamic(DT_BIND_NOW, 0);
+            }
 
-        // program headers
-        // https://refspecs.linuxbase.org/elf/gabi4+/ch5.pheader.html
+            let flags = dynamic_flags(opt, self.text_relocation, self.static_tls);
+            if flags != 0 {
+                // DT_FLAGS This element holds flag values specific to the
+                // object being loaded.
+                writer.write_dynamic(DT_FLAGS, flags.into());
+            }
 
-        // PT_PHDR The array element, if present, specifies the location and
-        // size of the program header table itself, both in the file and in the
-        // memory image of the program. This segment type may not occur more
-        // than once in a file. Moreover, it may occur only if the program
-        // header table is part of the memory image of the program. If it is
-        // present, it must precede any loadable segment entry.
-        writer.write_program_header(&ProgramHeader {
-            p_type: object::elf::PT_PHDR,
-            p_flags: object::elf::PF_R,
-            p_offset: 0,
-            p_vaddr: self.load_address + self.phdr_offset as u64,
-            p_paddr: self.load_address + self.phdr_offset as u64,
-            p_filesz: self.phdr_len as u64,
-            p_memsz: self.phdr_len as u64,
-            p_align: 8,
-        });
+            // DT_FLAGS_1 If present, this entry's d_val member holds various
+            // state flags.
+            writer.write_dynamic(DT_FLAGS_1, dynamic_flags_1(opt).into());
 
-        // ask kernel to load segments into memory
-        if !opt.shared && self.dynamic_link {
-            // PT_INTERP The array element specifies the location and size of a
-            // null-terminated path name to invoke as an interpreter. This
-            // segment type is meaningful only for executable files (though it
-            // may occur for shared objects); it may not occur more than once in
-            // a file. If it is present, it must precede any loadable segment
-            // entry. See ``Program Interpreter'' below for more information.
-            writer.write_program_header(&ProgramHeader {
-                p_type: object::elf::PT_INTERP,
-                p_flags: object::elf::PF_R,
-                p_offset: output_sections[".interp"].offset,
-                p_vaddr: section_address[".interp"],
-                p_paddr: section_address[".interp"],
-                p_filesz: output_sections[".interp"].content.len() as u64,
-                p_memsz: output_sections[".interp"].content.len() as u64,
-                p_align: 1,
-            });
-        }
+            if !self.version_needs.is_empty() {
+                // DT_VERSYM The address of the table provided by the
+                // .gnu.version section.
+                writer.write_dynamic(
+                    DT_VERSYM,
+                    segment_address(&self.load_segments, self.gnu_versym_section_offset),
+                );
+                // DT_VERNEED The address of the version dependency table.
+                writer.write_dynamic(
+                    DT_VERNEED,
+                    segment_address(&self.load_segments, self.gnu_verneed_section_offset),
+                );
+                // DT_VERNEEDNUM The number of entries in the table referenced
+                // by DT_VERNEED.
+                writer.write_dynamic(DT_VERNEEDNUM, self.version_needs.len() as u64);
+            }
 
-        // PT_LOAD The array element specifies a loadable segment, described by
-        // p_filesz and p_memsz. The bytes from the file are mapped to the
-        // beginning of the memory segment. If the segment's memory size
-        // (p_memsz) is larger than the file size (p_filesz), the ``extra''
-        // bytes are defined to hold the value 0 and to follow the segment's
-        // initialized area. The file size may not be larger than the memory
-        // size. Loadable segment entries in the program header table appear in
-        // ascending order, sorted on the p_vaddr member.
-        writer.write_program_header(&ProgramHeader {
-            p_type: object::elf::PT_LOAD,
-            p_flags: object::elf::PF_X | object::elf::PF_W | object::elf::PF_R,
-            p_offset: 0,
-            p_vaddr: self.load_address,
-            p_paddr: self.load_address,
-            p_filesz: writer.reserved_len() as u64,
-            p_memsz: writer.reserved_len() as u64,
-            p_align: 4096,
-        });
-        if opt.shared || self.dynamic_link {
-            // PT_DYNAMIC The array element specifies dynamic linking
-            // information. See ``Dynamic Section'' below for more information.
-            writer.write_program_header(&ProgramHeader {
-                p_type: object::elf::PT_DYNAMIC,
-                p_flags: object::elf::PF_W | object::elf::PF_R,
-                p_offset: self.dynamic_section_offset,
-                p_vaddr: self.dynamic_section_offset + self.load_address,
-                p_paddr: self.dynamic_section_offset + self.load_address,
-                p_filesz: (self.dynamic_entries_count
-                    * std::mem::size_of::<object::elf::Dyn64<object::LittleEndian>>())
-                    as u64,
-                p_memsz: (self.dynamic_entries_count
-                    * std::mem::size_of::<object::elf::Dyn64<object::LittleEndian>>())
-                    as u64,
-                p_align: 8,
-            });
+            // DT_NULL An entry with a DT_NULL tag marks the end of the _DYNAMIC
+            // array.
+            writer.write_dynamic(DT_NULL, 0);
         }
 
-        // write section data
-        for (_name, output_section) in output_sections.iter() {
+        for name in &late {
+            let output_section = &output_sections[name];
             writer.pad_until(output_section.offset as usize);
             writer.write(&output_section.content);
         }
-        for (_name, output_section) in output_relocations.iter() {
+        for output_section in self.nonalloc_sections.values() {
             writer.pad_until(output_section.offset as usize);
-            for rel in &output_section.relocations {
-                // turn offset into absolute
-                let mut rel = rel.clone();
-                rel.r_offset += section_address[".got.plt"];
-                writer.write_relocation(true, &rel);
-            }
+            writer.write(&output_section.content);
         }
 
         // write section headers
         writer.write_null_section_header();
-        for (name, output_section) in output_sections.iter() {
+        for name in layout_order(output_sections) {
+            let output_section = &output_sections[&name];
             let mut flags = object::elf::SHF_ALLOC;
             if output_section.is_executable {
                 flags |= object::elf::SHF_EXECINSTR;
@@ -995,28 +7499,47 @@ impl<'a> Linker<'a> {
             if output_section.is_writable {
                 flags |= object::elf::SHF_WRITE;
             }
+            if output_section.is_tls {
+                flags |= object::elf::SHF_TLS;
+            }
 
             writer.write_section_header(&SectionHeader {
                 name: output_section.name_string_id,
                 sh_type: if output_section.is_bss {
                     object::elf::SHT_NOBITS
+                } else if output_section.is_note {
+                    object::elf::SHT_NOTE
+                } else if name == ".preinit_array" {
+                    object::elf::SHT_PREINIT_ARRAY
+                } else if name == ".init_array" {
+                    object::elf::SHT_INIT_ARRAY
+                } else if name == ".fini_array" {
+                    object::elf::SHT_FINI_ARRAY
                 } else {
                     object::elf::SHT_PROGBITS
                 },
                 sh_flags: flags as u64,
-                sh_addr: section_address[name],
+                sh_addr: section_address[&name],
                 sh_offset: output_section.offset,
                 sh_size: output_section.content.len() as u64,
                 sh_link: 0,
                 sh_info: 0,
-                sh_addralign: 1,
+                sh_addralign: output_section.alignment.max(1),
                 sh_entsize: 0,
             });
         }
         for (name, output_section) in output_relocations.iter() {
-            let flags = object::elf::SHF_ALLOC | object::elf::SHF_INFO_LINK;
+            // .rela.plt applies to .got.plt, .rela.dyn applies to many sections
+            let (flags, info) = if name == ".rela.plt" {
+                (
+                    object::elf::SHF_ALLOC | object::elf::SHF_INFO_LINK,
+                    output_sections[".got.plt"].section_index.unwrap().0,
+                )
+            } else {
+                (object::elf::SHF_ALLOC, 0)
+            };
 
-            let entsize = std::mem::size_of::<object::elf::Rela64<LittleEndian>>();
+            let entsize = format.rela_size();
             writer.write_section_header(&SectionHeader {
                 name: output_section.name_string_id,
                 sh_type: object::elf::SHT_RELA,
@@ -1025,251 +7548,326 @@ impl<'a> Linker<'a> {
                 sh_offset: output_section.offset,
                 sh_size: (output_section.relocations.len() * entsize) as u64,
                 sh_link: self.dynsym_section_index.0, // associated to .dynsym
-                sh_info: output_sections
-                    .get(".got.plt")
-                    .unwrap()
-                    .section_index
-                    .unwrap()
-                    .0,
-                sh_addralign: 8,
+                sh_info: info,
+                sh_addralign: format.class.align() as u64,
                 sh_entsize: entsize as u64,
             });
         }
-        writer.write_symtab_section_header(
-            1 + symbols.iter().filter(|(_name, sym)| !sym.is_global).count() as u32,
-        ); // +1: one extra null symbol at the beginning
-        writer.write_strtab_section_header();
-        writer.write_shstrtab_section_header();
-        if opt.shared || self.dynamic_link {
-            writer.write_dynamic_section_header(self.dynamic_section_offset + self.load_address);
-            writer.write_dynsym_section_header(self.dynsym_section_offset + self.load_address, 1); // one local: null symbol
-            writer.write_dynstr_section_header(self.dynstr_section_offset + self.load_address);
-            if opt.hash_style.sysv {
-                writer.write_hash_section_header(self.hash_section_offset + self.load_address);
-            }
-            if opt.hash_style.gnu {
-                writer.write_gnu_hash_section_header(
-                    self.gnu_hash_section_offset + self.load_address,
-                );
-            }
-        }
-
-        // write symbol table
-        writer.write_null_symbol();
-        let mut symbols_vec: Vec<_> = symbols.iter().collect();
-        // local symbols first
-        symbols_vec.sort_by_key(|(_name, sym)| sym.is_global);
-        for (_symbol_name, symbol) in symbols_vec {
-            let address = section_address[&symbol.section_name] + symbol.offset;
-            writer.write_symbol(&Sym {
-                name: symbol.symbol_name_string_id,
-                section: if symbol.is_plt {
-                    None // UNDEF
-                } else if symbol.section_name == ".dynamic" {
-                    Some(self.dynamic_section_index)
-                } else {
-                    output_sections[&symbol.section_name].section_index
-                },
-                st_info: if symbol.is_global {
-                    (object::elf::STB_GLOBAL) << 4
-                } else {
-                    (object::elf::STB_LOCAL) << 4
-                },
-                st_other: 0,
-                st_shndx: 0,
-                st_value: if symbol.is_plt { 0 } else { address },
-                st_size: 0,
+        for output_section in self.nonalloc_sections.values() {
+            let (flags, entsize) = if output_section.is_strings {
+                (object::elf::SHF_MERGE | object::elf::SHF_STRINGS, 1)
+            } else {
+                (0, 0)
+            };
+            writer.write_section_header(&SectionHeader {
+                name: output_section.name_string_id,
+                sh_type: object::elf::SHT_PROGBITS,
+                sh_flags: flags as u64,
+                sh_addr: 0,
+                sh_offset: output_section.offset,
+                sh_size: output_section.content.len() as u64,
+                sh_link: 0,
+                sh_info: 0,
+                sh_addralign: output_section.alignment.max(1),
+                sh_entsize: entsize,
             });
         }
-
-        // write string table
-        writer.write_strtab();
-
-        // write section string table
-        writer.write_shstrtab();
-
-        // shared library or dynamic linking
+        if !opt.strip_all {
+            writer.write_symtab_section_header(
+                1 + symbols
+                    .iter()
+                    .filter(|(_name, sym)| {
+                        !sym.is_global_binding() && sym.symbol_name_string_id.is_some()
+                    })
+                    .count() as u32
+                    + self
+                        .file_symbols
+                        .iter()
+                        .filter(|file_symbol| file_symbol.string_id.is_some())
+                        .count() as u32,
+            ); // +1: one extra null symbol at the beginning
+            writer.write_strtab_section_header();
+        }
+        writer.write_shstrtab_section_header();
         if opt.shared || self.dynamic_link {
-            // https://refspecs.linuxbase.org/elf/gabi4+/ch5.dynamic.html#dynamic_section
-            // .dynamic section entries:
-            // 1. HASH -> .hash
-            // 2. GNU_HASH -> .gnu_hash
-            // 3. STRTAB -> .dynstr
-            // 4. SYMTAB -> .dynsym
-            // 5. STRSZ
-            // 6. SYMENT
-            // 7. SONAME
-            // 8. PLTGOT -> .got.plt
-            // 9. PLTRELSZ
-            // 10. PLTREL
-            // 11. JMPREL -> .rela.plt
-            // 12. NEEDED
-            // 13. FLAGS_1
-            // 14. NULL
-            writer.write_align_dynamic();
+            writer.write_dynamic_section_header(segment_address(
+                &self.load_segments,
+                self.dynamic_section_offset,
+            ));
+            writer.write_dynsym_section_header(
+                segment_address(&self.load_segments, self.dynsym_section_offset),
+                1,
+            ); // one local: null symbol
+            writer.write_dynstr_section_header(segment_address(
+                &self.load_segments,
+                self.dynstr_section_offset,
+            ));
             if opt.hash_style.sysv {
-                // DT_HASH This element holds the address of the symbol hash
-                // table, described in ``Hash Table''. This hash table refers to
-                // the symbol table referenced by the DT_SYMTAB element.
-                writer.write_dynamic(DT_HASH, self.hash_section_offset + self.load_address);
+                writer.write_hash_section_header(segment_address(
+                    &self.load_segments,
+                    self.hash_section_offset,
+                ));
             }
             if opt.hash_style.gnu {
-                writer.write_dynamic(
-                    DT_GNU_HASH,
-                    self.gnu_hash_section_offset + self.load_address,
-                );
+                writer.write_gnu_hash_section_header(segment_address(
+                    &self.load_segments,
+                    self.gnu_hash_section_offset,
+                ));
+            }
+            if !self.version_needs.is_empty() {
+                writer.write_gnu_versym_section_header(segment_address(
+                    &self.load_segments,
+                    self.gnu_versym_section_offset,
+                ));
+                writer.write_gnu_verneed_section_header(segment_address(
+                    &self.load_segments,
+                    self.gnu_verneed_section_offset,
+                ));
             }
+        }
 
-            // DT_STRTAB This element holds the address of the string table,
-            // described in Chapter 4. Symbol names, library names, and other
-            // strings reside in this table.
-            writer.write_dynamic(DT_STRTAB, self.dynstr_section_offset + self.load_address);
+        if !opt.strip_all {
+            // write symbol table
+            writer.write_null_symbol();
+            let mut symbols_vec: Vec<_> = symbols
+                .iter()
+                .filter(|(_name, sym)| sym.symbol_name_string_id.is_some())
+                .collect();
+            // local symbols first, grouped by the objects defining them
+            symbols_vec.sort_by_key(|(_name, sym)| {
+                (
+                    sym.is_global_binding(),
+                    if sym.is_global_binding() {
+                        None
+                    } else {
+                        sym.file_symbol
+                    },
+                )
+            });
+            // STT_FILE symbols of objects up to the given index
+            let mut next_file_symbol = 0;
+            let mut write_file_symbols = |writer: &mut Writer, end: usize| {
+                for file_symbol in &self.file_symbols[next_file_symbol..end] {
+                    if let Some(string_id) = file_symbol.string_id {
+                        writer.write_symbol(&Sym {
+                            name: Some(string_id),
+                            section: None,
+                            st_info: (object::elf::STB_LOCAL << 4) | object::elf::STT_FILE,
+                            st_other: 0,
+                            st_shndx: object::elf::SHN_ABS,
+                            st_value: 0,
+                            st_size: 0,
+                        });
+                    }
+                }
+                next_file_symbol = next_file_symbol.max(end);
+            };
+            for (_symbol_name, symbol) in symbols_vec {
+                if symbol.is_global_binding() {
+                    write_file_symbols(writer, self.file_symbols.len());
+                } else if let Some(index) = symbol.file_symbol {
+                    write_file_symbols(writer, index + 1);
+                }
+                let mut address = section_address[&symbol.section_name] + symbol.offset;
+                if symbol.st_type == STT_TLS && !symbol.is_plt {
+                    address -= tls_address;
+                }
+                writer.write_symbol(&Sym {
+                    name: symbol.symbol_name_string_id,
+                    section: if symbol.is_plt
+                        || symbol.section_name == ABSOLUTE_SECTION
+                        || symbol.section_name == UNDEFINED_SECTION
+                    {
+                        None // UNDEF or ABS
+                    } else if symbol.section_name == ".dynamic" {
+                        Some(self.dynamic_section_index)
+                    } else if let Some(rela) = output_relocations.get(&symbol.section_name) {
+                        rela.section_index
+                    } else {
+                        output_sections[&symbol.section_name].section_index
+                    },
+                    st_info: if !symbol.is_global_binding() {
+                        (object::elf::STB_LOCAL) << 4
+                    } else if symbol.is_weak {
+                        (object::elf::STB_WEAK) << 4
+                    } else {
+                        (object::elf::STB_GLOBAL) << 4
+                    } | symbol.st_type,
+                    st_other: symbol.visibility,
+                    st_shndx: if symbol.section_name == ABSOLUTE_SECTION {
+                        object::elf::SHN_ABS
+                    } else {
+                        0
+                    },
+                    // undefined symbol with non-zero value: canonical plt entry
+                    st_value: if symbol.is_plt && !symbol.canonical_plt {
+                        0
+                    } else {
+                        address
+                    },
+                    st_size: symbol.size,
+                });
+            }
+            // remaining STT_FILE symbols, when there is no global symbol
+            write_file_symbols(writer, self.file_symbols.len());
 
-            // DT_SYMTAB This element holds the address of the symbol table,
-            // described in the first part of this chapter, with Elf32_Sym
-            // entries for the 32-bit class of files and Elf64_Sym entries for
-            // the 64-bit class of files.
-            writer.write_dynamic(DT_SYMTAB, self.dynsym_section_offset + self.load_address);
+            // write string table
+            writer.write_strtab();
+        }
 
-            // DT_STRSZ This element holds the size, in bytes, of the string
-            // table.
-            let strsz = writer.dynstr_len() as u64;
-            writer.write_dynamic(DT_STRSZ, strsz); // size of dynamic string table
+        // write section string table
+        writer.write_shstrtab();
 
-            // DT_SYMENT This element holds the size, in bytes, of a symbol
-            // table entry.
-            writer.write_dynamic(DT_SYMENT, std::mem::size_of::<Sym64<LittleEndian>>() as u64); // entry size
+        assert_eq!(writer.reserved_len(), writer.len());
 
-            if let Some(soname_dynamic_string_index) = &soname_dynamic_string_index {
-                // DT_SONAME This element holds the string table offset of a
-                // null-terminated string, giving the name of the shared object.
-                // The offset is an index into the table recorded in the
-                // DT_STRTAB entry. See ``Shared Object Dependencies'' below for
-                // more information about these names.
-                writer.write_dynamic_string(DT_SONAME, *soname_dynamic_string_index);
-            }
+        Ok(())
+    }
 
-            if self.dynamic_link {
-                // DT_PLTGOT This element holds an address associated with the
-                // procedure linkage table and/or the global offset table. See
-                // this section in the processor supplement for details.
-                writer.write_dynamic(DT_PLTGOT, section_address[".got.plt"]);
+    /// Generate link map in the format of GNU ld
+    fn generate_map(&self) -> String {
+        let mut map = String::new();
 
-                // DT_PLTRELSZ This element holds the total size, in bytes, of
-                // the relocation entries associated with the procedure linkage
-                // table. If an entry of type DT_JMPREL is present, a
-                // DT_PLTRELSZ must accompany it.
-                writer.write_dynamic(
-                    DT_PLTRELSZ,
-                    (output_relocations[".rela.plt"].relocations.len()
-                        * std::mem::size_of::<object::elf::Rela64<LittleEndian>>())
-                        as u64,
-                );
+        map += "Discarded input sections\n\n";
+        for section in &self.discarded_sections {
+            map += &format_map_section(&section.name, 0, section.size, &section.file_name);
+        }
 
-                // DT_PLTREL This member specifies the type of relocation entry
-                // to which the procedure linkage table refers. The d_val member
-                // holds DT_REL or DT_RELA, as appropriate. All relocations in a
-                // procedure linkage table must use the same relocation.
-                writer.write_dynamic(DT_PLTREL, DT_RELA as u64);
+        map += "\nMemory Configuration\n\n";
+        map += &format!(
+            "{:<16} {:<18} {:<18} {}\n",
+            "Name", "Origin", "Length", "Attributes"
+        );
+        map += &format!("{:<16} 0x{:016x} 0x{:016x}\n", "*default*", 0, u64::MAX);
 
-                // DT_JMPREL If present, this entry's d_ptr member holds the
-                // address of relocation entries associated solely with the
-                // procedure linkage table. Separating these relocation entries
-                // lets the dynamic linker ignore them during process
-                // initialization, if lazy binding is enabled. If this entry is
-                // present, the related entries of types DT_PLTRELSZ and
-                // DT_PLTREL must also be present.
-                writer.write_dynamic(DT_JMPREL, section_address[".rela.plt"]);
-            }
-            for needed in &self.needed {
-                // DT_NEEDED This element holds the string table offset of a
-                // null-terminated string, giving the name of a needed library.
-                // The offset is an index into the table recorded in the
-                // DT_STRTAB code. See ``Shared Object Dependencies'' for more
-                // information about these names. The dynamic array may contain
-                // multiple entries with this type. These entries' relative
-                // order is significant, though their relation to entries of
-                // other types is not.
-                writer.write_dynamic_string(DT_NEEDED, needed.name_string_id.unwrap());
+        map += "\nLinker script and memory map\n\n";
+        for file in &self.files {
+            map += &format!("LOAD {}\n", file.name);
+        }
+
+        for name in layout_order(&self.output_sections) {
+            let output_section = &self.output_sections[&name];
+            let address = self.section_address[&name];
+            map += "\n";
+            if output_section.name.len() >= 15 {
+                map += &format!("{}\n{:<15}", output_section.name, "");
+            } else {
+                map += &format!("{:<15}", output_section.name);
             }
+            map += &format!(
+                " 0x{:016x} {:>10}\n",
+                address,
+                format!("{:#x}", output_section.content.len())
+            );
 
-            // DT_FLAGS_1 If present, this entry's d_val member holds various
-            // state flags.
-            writer.write_dynamic(DT_FLAGS_1, if opt.pie { DF_1_PIE.into() } else { 0 });
+            // symbols in this output section, sorted by offset
+            let mut symbols: Vec<(&String, &Symbol)> = self
+                .symbols
+                .iter()
+                .filter(|(_name, symbol)| {
+                    symbol.is_global && !symbol.is_plt && symbol.section_name == *name
+                })
+                .collect();
+            symbols.sort_by_key(|(_name, symbol)| symbol.offset);
 
-            // DT_NULL An entry with a DT_NULL tag marks the end of the _DYNAMIC
-            // array.
-            writer.write_dynamic(DT_NULL, 0);
+            for (index, input_section) in output_section.input_sections.iter().enumerate() {
+                map += &format_map_section(
+                    &input_section.name,
+                    address + input_section.offset,
+                    input_section.size,
+                    &input_section.file_name,
+                );
 
-            // write dynamic symbols
-            writer.write_null_dynamic_symbol();
-            for dyn_sym in plt_dynamic_symbols.iter().chain(dynamic_symbols.iter()) {
-                let symbol = symbols.get(&dyn_sym.name).unwrap();
-                let address = section_address[&symbol.section_name] + symbol.offset;
-                writer.write_dynamic_symbol(&Sym {
-                    name: symbol.symbol_name_dynamic_string_id,
-                    section: if symbol.is_plt {
-                        None
-                    } else {
-                        output_sections[&symbol.section_name].section_index
-                    },
-                    st_info: (object::elf::STB_GLOBAL) << 4,
-                    st_other: 0,
-                    st_shndx: 0,
-                    st_value: if symbol.is_plt { 0 } else { address },
-                    st_size: 0,
-                });
+                // symbols belong to the last input section starting before them
+                let end = output_section
+                    .input_sections
+                    .get(index + 1)
+                    .map(|next| next.offset)
+                    .unwrap_or(u64::MAX);
+                for (symbol_name, symbol) in &symbols {
+                    if symbol.offset >= input_section.offset && symbol.offset < end {
+                        map += &format!(
+                            "{:<16}0x{:016x}                {}\n",
+                            "",
+                            address + symbol.offset,
+                            demangle(&self.opt, symbol_name)
+                        );
+                    }
+                }
             }
+        }
 
-            // write dynamic string table
-            writer.write_dynstr();
+        map += "\nOUTPUT(";
+        map += self.opt.output.as_deref().unwrap_or_default();
+        map += " elf64-x86-64)\n";
+        map
+    }
 
-            // write hash table
-            if opt.hash_style.sysv {
-                writer.write_hash(
-                    (plt_dynamic_symbols.len() + dynamic_symbols.len()) as u32,
-                    (plt_dynamic_symbols.len() + dynamic_symbols.len()) as u32 + 1, // + 1 for NULL symbol at start
-                    |idx| {
-                        // compute sysv hash of symbol name
-                        // 0 is reserved for null, skip
-                        if idx == 0 {
-                            None
-                        } else if idx <= plt_dynamic_symbols.len() as u32 {
-                            // UNDEF
-                            None
-                        } else {
-                            Some(object::elf::hash(
-                                dynamic_symbols[idx as usize - 1 - plt_dynamic_symbols.len()]
-                                    .name
-                                    .as_bytes(),
-                            ))
-                        }
-                    },
-                );
+    /// Generate cross reference table in the format of GNU ld
+    fn generate_cref(&self) -> String {
+        let mut cref = String::new();
+        cref += "\nCross Reference Table\n\n";
+        cref += &format!("{:<50}{}\n", "Symbol", "File");
+
+        let mut names: BTreeSet<&String> = self.defined_in.keys().collect();
+        names.extend(self.referenced_by.keys());
+        for name in names {
+            // defining file comes first, followed by referencing files
+            let (defined_by, referenced_by) = match self.symbols.get(name.as_str()) {
+                Some(symbol) if symbol.is_global && symbol.defined_by.is_some() => {
+                    (symbol.defined_by.as_ref(), symbol.referenced_by.as_slice())
+                }
+                // undefined, or only defined by a shared library
+                _ => (
+                    self.defined_in.get(name),
+                    self.referenced_by
+                        .get(name)
+                        .map_or(&[][..], |files| files.as_slice()),
+                ),
+            };
+            let mut files = vec![];
+            match defined_by {
+                Some(file) => files.push(file.clone()),
+                None => files.push("<undefined>".to_string()),
             }
+            files.extend(referenced_by.iter().cloned());
 
-            // write gnu hash table
-            if opt.hash_style.gnu {
-                writer.write_gnu_hash(
-                    1 + plt_dynamic_symbols.len() as u32, // skip NULL symbol and plt UNDEF symbols
-                    1,
-                    1,
-                    dynamic_symbols.len() as u32,
-                    dynamic_symbols.len() as u32,
-                    |idx| {
-                        // compute gnu hash of symbol name
-                        object::elf::gnu_hash(dynamic_symbols[idx as usize].name.as_bytes())
-                    },
-                );
+            let name = demangle(&self.opt, name);
+            for (index, file) in files.iter().enumerate() {
+                if index == 0 {
+                    if name.len() >= 50 {
+                        cref += &format!("{}\n{:<50}{}\n", name, "", file);
+                    } else {
+                        cref += &format!("{:<50}{}\n", name, file);
+                    }
+                } else {
+                    cref += &format!("{:<50}{}\n", "", file);
+                }
             }
         }
+        cref
+    }
 
-        assert_eq!(writer.reserved_len(), writer.len());
+    /// Write link map and cross reference table to file or stdout
+    fn write_map(&self) -> anyhow::Result<()> {
+        let mut map = String::new();
+        if self.opt.map_file.is_some() || self.opt.print_map {
+            map += &self.generate_map();
+        }
+        if self.opt.cref {
+            map += &self.generate_cref();
+        }
 
+        if let Some(map_file) = &self.opt.map_file {
+            info!("Writing link map to {}", map_file);
+            std::fs::write(map_file, map).context(format!("Writing link map to {}", map_file))?;
+        } else if !map.is_empty() {
+            print!("{}", map);
+        }
         Ok(())
     }
 
     fn relocate(&mut self) -> anyhow::Result<()> {
+        let format = self.format;
         let Linker {
             opt,
             output_sections,
@@ -1281,41 +7879,104 @@ impl<'a> Linker<'a> {
 
         // compute mapping from section name to virtual address
         for (name, output_section) in output_sections.iter() {
-            section_address.insert(name.clone(), output_section.offset + self.load_address);
+            section_address.insert(
+                name.clone(),
+                segment_address(&self.load_segments, output_section.offset),
+            );
         }
         for (name, output_section) in output_relocations.iter() {
-            section_address.insert(name.clone(), output_section.offset + self.load_address);
+            section_address.insert(
+                name.clone(),
+                segment_address(&self.load_segments, output_section.offset),
+            );
         }
         if opt.shared || self.dynamic_link {
             section_address.insert(
                 ".dynamic".to_string(),
-                self.load_address + self.dynamic_section_offset,
+                segment_address(&self.load_segments, self.dynamic_section_offset),
             );
         }
+        section_address.insert(ABSOLUTE_SECTION.to_string(), 0);
+        section_address.insert(UNDEFINED_SECTION.to_string(), 0);
+
+        // linker defined symbols from the final layout
+        let mut text_end = self.load_address;
+        let mut data_end = self.load_address;
+        let mut bss_start = None;
+        for (name, output_section) in output_sections.iter() {
+            let start = section_address[name];
+            let end = start + output_section.content.len() as u64;
+            if output_section.is_executable {
+                text_end = text_end.max(end);
+            }
+            if output_section.is_bss && !output_section.is_tls {
+                bss_start = Some(bss_start.unwrap_or(start).min(start));
+            } else if !output_section.is_bss {
+                data_end = data_end.max(end);
+            }
+        }
+        // end of the last PT_LOAD segment in memory, symbol tables are not
+        // loaded
+        let image_end = self
+            .load_segments
+            .iter()
+            .map(|segment| segment.address + segment.size)
+            .max()
+            .unwrap_or(self.load_address);
+        for name in &self.layout_symbols {
+            let address = match name.as_str() {
+                // ELF header is mapped at the beginning of the segment
+                "__ehdr_start" | "__executable_start" => self.load_address,
+                "etext" | "_etext" | "__etext" => text_end,
+                "edata" | "_edata" => data_end,
+                "__bss_start" => bss_start.unwrap_or(data_end),
+                "end" | "_end" => image_end,
+                _ => unreachable!(),
+            };
+            info!("Linker symbol {} is at {:#x}", name, address);
+            symbols.get_mut(name).unwrap().offset = address;
+        }
+        for (name, value) in script_symbol_values(
+            &self.script,
+            &self.script_symbols,
+            output_sections,
+            section_address,
+            &self.load_segments,
+            symbols,
+        )? {
+            info!("Symbol {} from linker script is {:#x}", name, value);
+            symbols.get_mut(&name).unwrap().offset = value;
+        }
+
+        // the tls block of this module is a copy of the template, and the
+        // thread pointer points to the end of the static tls block, which
+        // is the template of the executable aligned up
+        let tls_address = self
+            .tls_segment
+            .as_ref()
+            .map_or(0, |tls| segment_address(&self.load_segments, tls.offset));
+        let thread_pointer = self.tls_segment.as_ref().map_or(0, |tls| {
+            tls_address + tls.mem_size.next_multiple_of(tls.alignment)
+        });
+
+        // _GLOBAL_OFFSET_TABLE_, the base of got-relative relocations
+        let got_base = symbols.get("_GLOBAL_OFFSET_TABLE_").map_or(0, |symbol| {
+            section_address[&symbol.section_name] + symbol.offset
+        });
 
         // compute relocation
         for (name, output_section) in output_sections.iter_mut() {
             let _span = info_span!("section", name = name).entered();
             for (index, relocation) in output_section.relocations.iter().enumerate() {
                 let _span = info_span!("relocation", index = index).entered();
-                let target_address = match &relocation.target {
-                    RelocationTarget::Section((name, offset)) => {
-                        info!("Relocation is targeting section {}", name);
-                        section_address[name] + offset
-                    }
-                    RelocationTarget::Symbol(name) => {
-                        info!("Relocation is targeting symbol {}", name);
-                        let symbol = &symbols[name];
-                        section_address[&symbol.section_name] + symbol.offset
-                    }
-                };
+                let target_address = target_address(&relocation.target, symbols, section_address);
 
                 // symbol
                 let s = target_address as i64;
                 // addend
                 let a = relocation.addend;
                 // pc
-                let p = self.load_address + output_section.offset + relocation.offset;
+                let p = section_address[name] + relocation.offset;
 
                 match (relocation.kind, relocation.encoding, relocation.size) {
                     // R_X86_64_64
@@ -1323,9 +7984,11 @@ impl<'a> Linker<'a> {
                         info!("Relocation type is R_X86_64_64");
                         // S + A
                         let value = s.wrapping_add(a);
-                        output_section.content
-                            [(relocation.offset) as usize..(relocation.offset + 8) as usize]
-                            .copy_from_slice(&(value as i64).to_le_bytes());
+                        format.store(
+                            &mut output_section.content
+                                [(relocation.offset) as usize..(relocation.offset + 8) as usize],
+                            value as u64,
+                        );
                     }
                     // R_X86_64_32S
                     (
@@ -1336,9 +7999,20 @@ impl<'a> Linker<'a> {
                         info!("Relocation type is R_X86_64_32S");
                         // S + A
                         let value = s.wrapping_add(a);
-                        output_section.content
-                            [(relocation.offset) as usize..(relocation.offset + 4) as usize]
-                            .copy_from_slice(&(value as i32).to_le_bytes());
+                        check_overflow(
+                            opt,
+                            name,
+                            output_section,
+                            relocation,
+                            value,
+                            32,
+                            Overflow::Signed,
+                        )?;
+                        format.store(
+                            &mut output_section.content
+                                [(relocation.offset) as usize..(relocation.offset + 4) as usize],
+                            value as u64,
+                        );
                     }
                     // R_X86_64_PLT32
                     (
@@ -1347,25 +8021,443 @@ impl<'a> Linker<'a> {
                         32,
                     ) => {
                         info!("Relocation type is R_X86_64_PLT32");
-                        // we don't have PLT now, implement as R_X86_64_PC32
+                        // calls needing plt entry are redirected to it, others
+                        // are direct like R_X86_64_PC32
                         // S + A - P
                         let value = s.wrapping_add(a).wrapping_sub_unsigned(p);
+                        check_overflow(
+                            opt,
+                            name,
+                            output_section,
+                            relocation,
+                            value,
+                            32,
+                            Overflow::Signed,
+                        )?;
 
-                        output_section.content
-                            [(relocation.offset) as usize..(relocation.offset + 4) as usize]
-                            .copy_from_slice(&(value as i32).to_le_bytes());
+                        format.store(
+                            &mut output_section.content
+                                [(relocation.offset) as usize..(relocation.offset + 4) as usize],
+                            value as u64,
+                        );
                     }
                     // R_X86_64_PC32
                     (object::RelocationKind::Relative, object::RelocationEncoding::Generic, 32) => {
                         info!("Relocation type is R_X86_64_PC32");
                         // S + A - P
                         let value = s.wrapping_add(a).wrapping_sub_unsigned(p);
+                        check_overflow(
+                            opt,
+                            name,
+                            output_section,
+                            relocation,
+                            value,
+                            32,
+                            Overflow::Signed,
+                        )?;
+
+                        format.store(
+                            &mut output_section.content
+                                [(relocation.offset) as usize..(relocation.offset + 4) as usize],
+                            value as u64,
+                        );
+                    }
+                    // R_X86_64_32, R_X86_64_16 and R_X86_64_8
+                    (
+                        object::RelocationKind::Absolute,
+                        object::RelocationEncoding::Generic,
+                        size,
+                    ) if matches!(size, 32 | 16 | 8) => {
+                        info!("Relocation type is {}", relocation_name(relocation));
+                        // S + A
+                        let value = s.wrapping_add(a);
+                        let overflow = if size == 32 {
+                            Overflow::Unsigned
+                        } else {
+                            Overflow::Bitfield
+                        };
+                        check_overflow(
+                            opt,
+                            name,
+                            output_section,
+                            relocation,
+                            value,
+                            size.into(),
+                            overflow,
+                        )?;
+                        let bytes = size as usize / 8;
+                        format.store(
+                            &mut output_section.content[(relocation.offset) as usize
+                                ..(relocation.offset) as usize + bytes],
+                            value as u64,
+                        );
+                    }
+                    // R_X86_64_PC16 and R_X86_64_PC8
+                    (
+                        object::RelocationKind::Relative,
+                        object::RelocationEncoding::Generic,
+                        size,
+                    ) if matches!(size, 16 | 8) => {
+                        info!("Relocation type is {}", relocation_name(relocation));
+                        // S + A - P
+                        let value = s.wrapping_add(a).wrapping_sub_unsigned(p);
+                        check_overflow(
+                            opt,
+                            name,
+                            output_section,
+                            relocation,
+                            value,
+                            size.into(),
+                            Overflow::Signed,
+                        )?;
+                        let bytes = size as usize / 8;
+                        format.store(
+                            &mut output_section.content[(relocation.offset) as usize
+                                ..(relocation.offset) as usize + bytes],
+                            value as u64,
+                        );
+                    }
+                    // R_X86_64_GOT32, targeting the .got entry
+                    (object::RelocationKind::Got, object::RelocationEncoding::Generic, 32) => {
+                        info!("Relocation type is R_X86_64_GOT32");
+                        // G + A
+                        let value = s.wrapping_add(a).wrapping_sub_unsigned(got_base);
+                        check_overflow(
+                            opt,
+                            name,
+                            output_section,
+                            relocation,
+                            value,
+                            32,
+                            Overflow::Signed,
+                        )?;
+                        format.store(
+                            &mut output_section.content
+                                [(relocation.offset) as usize..(relocation.offset + 4) as usize],
+                            value as u64,
+                        );
+                    }
+                    // R_X86_64_PC64, and R_X86_64_GOTPCREL64 targeting the
+                    // .got entry
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_PC64 =>
+                    {
+                        info!("Relocation type is R_X86_64_PC64");
+                        // S + A - P
+                        let value = s.wrapping_add(a).wrapping_sub_unsigned(p);
+                        format.store(
+                            &mut output_section.content
+                                [(relocation.offset) as usize..(relocation.offset + 8) as usize],
+                            value as u64,
+                        );
+                    }
+                    // R_X86_64_GOTOFF64 and R_X86_64_PLTOFF64, and
+                    // R_X86_64_GOT64 and R_X86_64_GOTPLT64 targeting the
+                    // .got entry
+                    (object::RelocationKind::Unknown, _, _)
+                        if matches!(
+                            relocation.r_type,
+                            R_X86_64_GOTOFF64
+                                | R_X86_64_PLTOFF64
+                                | R_X86_64_GOT64
+                                | R_X86_64_GOTPLT64
+                        ) =>
+                    {
+                        info!("Relocation type is {}", relocation_name(relocation));
+                        // S + A - GOT
+                        let value = s.wrapping_add(a).wrapping_sub_unsigned(got_base);
+                        format.store(
+                            &mut output_section.content
+                                [(relocation.offset) as usize..(relocation.offset + 8) as usize],
+                            value as u64,
+                        );
+                    }
+                    // R_X86_64_GOTPC32
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_GOTPC32 =>
+                    {
+                        info!("Relocation type is R_X86_64_GOTPC32");
+                        // GOT + A - P
+                        let value = (got_base as i64).wrapping_add(a).wrapping_sub_unsigned(p);
+                        check_overflow(
+                            opt,
+                            name,
+                            output_section,
+                            relocation,
+                            value,
+                            32,
+                            Overflow::Signed,
+                        )?;
+                        format.store(
+                            &mut output_section.content
+                                [(relocation.offset) as usize..(relocation.offset + 4) as usize],
+                            value as u64,
+                        );
+                    }
+                    // R_X86_64_GOTPC64
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_GOTPC64 =>
+                    {
+                        info!("Relocation type is R_X86_64_GOTPC64");
+                        // GOT + A - P
+                        let value = (got_base as i64).wrapping_add(a).wrapping_sub_unsigned(p);
+                        format.store(
+                            &mut output_section.content
+                                [(relocation.offset) as usize..(relocation.offset + 8) as usize],
+                            value as u64,
+                        );
+                    }
+                    // R_X86_64_SIZE32 and R_X86_64_SIZE64
+                    (object::RelocationKind::Unknown, _, _)
+                        if matches!(relocation.r_type, R_X86_64_SIZE32 | R_X86_64_SIZE64) =>
+                    {
+                        info!("Relocation type is {}", relocation_name(relocation));
+                        // Z + A
+                        let z = match &relocation.target {
+                            RelocationTarget::Symbol(symbol_name) => symbols[symbol_name].size,
+                            RelocationTarget::Section(_) => 0,
+                        };
+                        let value = (z as i64).wrapping_add(a);
+                        let bytes = if relocation.r_type == R_X86_64_SIZE64 {
+                            8
+                        } else {
+                            check_overflow(
+                                opt,
+                                name,
+                                output_section,
+                                relocation,
+                                value,
+                                32,
+                                Overflow::Unsigned,
+                            )?;
+                            4
+                        };
+                        format.store(
+                            &mut output_section.content[(relocation.offset) as usize
+                                ..(relocation.offset) as usize + bytes],
+                            value as u64,
+                        );
+                    }
+                    // R_X86_64_TPOFF32 of local-exec tls
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_TPOFF32 =>
+                    {
+                        info!("Relocation type is R_X86_64_TPOFF32");
+                        // the static tls block is only known in executable
+                        if let RelocationTarget::Symbol(symbol_name) = &relocation.target {
+                            if opt.shared {
+                                bail!(
+                                    "relocation R_X86_64_TPOFF32 against symbol `{}` can not be used when making a shared object; recompile with -fPIC",
+                                    demangle(opt, symbol_name)
+                                );
+                            } else if symbols[symbol_name].is_plt {
+                                bail!(
+                                    "relocation R_X86_64_TPOFF32 against symbol `{}` defined in shared library; recompile with -fPIC",
+                                    demangle(opt, symbol_name)
+                                );
+                            }
+                        } else if opt.shared {
+                            bail!(
+                                "relocation R_X86_64_TPOFF32 can not be used when making a shared object; recompile with -fPIC"
+                            );
+                        }
+                        // S + A - TP
+                        let value = s.wrapping_add(a).wrapping_sub_unsigned(thread_pointer);
+                        check_overflow(
+                            opt,
+                            name,
+                            output_section,
+                            relocation,
+                            value,
+                            32,
+                            Overflow::Signed,
+                        )?;
+                        format.store(
+                            &mut output_section.content
+                                [(relocation.offset) as usize..(relocation.offset + 4) as usize],
+                            value as u64,
+                        );
+                    }
+                    // R_X86_64_DTPOFF32 of local-dynamic tls
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_DTPOFF32 =>
+                    {
+                        info!("Relocation type is R_X86_64_DTPOFF32");
+                        // S + A - TLS
+                        let value = s.wrapping_add(a).wrapping_sub_unsigned(tls_address);
+                        check_overflow(
+                            opt,
+                            name,
+                            output_section,
+                            relocation,
+                            value,
+                            32,
+                            Overflow::Signed,
+                        )?;
+                        format.store(
+                            &mut output_section.content
+                                [(relocation.offset) as usize..(relocation.offset + 4) as usize],
+                            value as u64,
+                        );
+                    }
+                    // R_X86_64_DTPOFF64 in .got entries of general-dynamic tls
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_DTPOFF64 =>
+                    {
+                        info!("Relocation type is R_X86_64_DTPOFF64");
+                        // S + A - TLS
+                        let value = s.wrapping_add(a).wrapping_sub_unsigned(tls_address);
+                        format.store(
+                            &mut output_section.content
+                                [(relocation.offset) as usize..(relocation.offset + 8) as usize],
+                            value as u64,
+                        );
+                    }
+                    // R_X86_64_TLSDESC_CALL marks the call of tls descriptor
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_TLSDESC_CALL =>
+                    {
+                        info!("Relocation type is R_X86_64_TLSDESC_CALL");
+                    }
+                    // R_X86_64_TPOFF64 in .got entries of initial-exec tls
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_TPOFF64 =>
+                    {
+                        info!("Relocation type is R_X86_64_TPOFF64");
+                        // S + A - TP
+                        let value = s.wrapping_add(a).wrapping_sub_unsigned(thread_pointer);
+                        format.store(
+                            &mut output_section.content
+                                [(relocation.offset) as usize..(relocation.offset + 8) as usize],
+                            value as u64,
+                        );
+                    }
+                    _ => bail!(
+                        "{}: unsupported relocation {}",
+                        relocation_location(name, output_section, relocation.offset),
+                        relocation_name(relocation)
+                    ),
+                }
+            }
+        }
+
+        // binary search table of FDEs sorted by the initial location, all
+        // relative to .eh_frame_hdr
+        if let Some(hdr) = output_sections.get_mut(".eh_frame_hdr") {
+            let hdr_address = section_address[".eh_frame_hdr"];
+            let eh_frame_address = section_address[".eh_frame"];
+            let mut table: Vec<(u64, u64)> = self
+                .eh_frame_fdes
+                .iter()
+                .map(|(offset, target, addend)| {
+                    let location = target_address(target, symbols, section_address)
+                        .wrapping_add_signed(*addend);
+                    (location, eh_frame_address + offset)
+                })
+                .collect();
+            table.sort();
+            format.store(
+                &mut hdr.content[4..8],
+                eh_frame_address.wrapping_sub(hdr_address + 4),
+            );
+            format.store(&mut hdr.content[8..12], table.len() as u64);
+            for (index, (location, fde)) in table.into_iter().enumerate() {
+                let entry = 12 + index * 8;
+                format.store(
+                    &mut hdr.content[entry..entry + 4],
+                    location.wrapping_sub(hdr_address),
+                );
+                format.store(
+                    &mut hdr.content[entry + 4..entry + 8],
+                    fde.wrapping_sub(hdr_address),
+                );
+            }
+        }
 
-                        output_section.content
-                            [(relocation.offset) as usize..(relocation.offset + 4) as usize]
-                            .copy_from_slice(&(value as i32).to_le_bytes());
+        // debug sections refer to offsets in other debug sections and to
+        // addresses of code and data
+        for (name, output_section) in self
+            .nonalloc_sections
+            .iter_mut()
+            .chain(self.separate_debug_sections.iter_mut())
+        {
+            let _span = info_span!("section", name = name).entered();
+            for relocation in &output_section.relocations {
+                let s = match &relocation.target {
+                    RelocationTarget::Section((target, offset))
+                        if !section_address.contains_key(target) =>
+                    {
+                        // another non-alloc section, at address zero
+                        *offset
+                    }
+                    // symbol in removed code
+                    RelocationTarget::Symbol(target) if !symbols.contains_key(target) => {
+                        debug_tombstone(name)
+                    }
+                    target => target_address(target, symbols, section_address),
+                };
+                let value = s.wrapping_add_signed(relocation.addend);
+                // non-alloc sections are at address zero
+                let p = relocation.offset;
+                let range = relocation.offset as usize..;
+                match (relocation.kind, relocation.encoding, relocation.size) {
+                    // R_X86_64_64, R_X86_64_32, R_X86_64_32S, R_X86_64_16 and
+                    // R_X86_64_8
+                    (object::RelocationKind::Absolute, _, size)
+                        if matches!(size, 64 | 32 | 16 | 8) =>
+                    {
+                        let bytes = size as usize / 8;
+                        format.store(&mut output_section.content[range][..bytes], value);
+                    }
+                    // R_X86_64_PC32
+                    (object::RelocationKind::Relative, object::RelocationEncoding::Generic, 32) => {
+                        let value = value.wrapping_sub(p);
+                        format.store(&mut output_section.content[range][..4], value);
+                    }
+                    // R_X86_64_PC64
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_PC64 =>
+                    {
+                        let value = value.wrapping_sub(p);
+                        format.store(&mut output_section.content[range][..8], value);
+                    }
+                    // R_X86_64_SIZE32 and R_X86_64_SIZE64
+                    (object::RelocationKind::Unknown, _, _)
+                        if matches!(relocation.r_type, R_X86_64_SIZE32 | R_X86_64_SIZE64) =>
+                    {
+                        let z = match &relocation.target {
+                            RelocationTarget::Symbol(symbol_name) => {
+                                symbols.get(symbol_name).map_or(0, |symbol| symbol.size)
+                            }
+                            RelocationTarget::Section(_) => 0,
+                        };
+                        let value = z.wrapping_add_signed(relocation.addend);
+                        let bytes = if relocation.r_type == R_X86_64_SIZE64 {
+                            8
+                        } else {
+                            4
+                        };
+                        format.store(&mut output_section.content[range][..bytes], value);
+                    }
+                    // R_X86_64_DTPOFF64 and R_X86_64_DTPOFF32: location of
+                    // thread-local variables
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_DTPOFF64 =>
+                    {
+                        let value = value.wrapping_sub(tls_address);
+                        format.store(&mut output_section.content[range][..8], value);
+                    }
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_DTPOFF32 =>
+                    {
+                        let value = value.wrapping_sub(tls_address);
+                        format.store(&mut output_section.content[range][..4], value);
                     }
-                    _ => unimplemented!("Unimplemented relocation {:?}", relocation),
+                    _ => bail!(
+                        "{}: unsupported relocation {} in debug section",
+                        relocation_location(name, output_section, relocation.offset),
+                        relocation_name(relocation)
+                    ),
                 }
             }
         }
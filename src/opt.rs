@@ -1,4 +1,6 @@
 use anyhow::{anyhow, bail};
+use std::collections::BTreeMap;
+use tracing::warn;
 
 /// handle --push-state/--pop-state
 #[derive(Debug, Copy, Clone)]
@@ -19,6 +21,8 @@ pub struct FileOpt {
 #[derive(Debug, Clone)]
 pub struct LibraryOpt {
     pub name: String,
+    /// -l:filename, name is the file name as is
+    pub verbatim: bool,
     /// --as-needed
     pub as_needed: bool,
     /// -static
@@ -31,6 +35,8 @@ pub enum ObjectFileOpt {
     File(FileOpt),
     /// -l namespec
     Library(LibraryOpt),
+    /// -R filename, --just-symbols=filename
+    JustSymbols(String),
     /// --start-group
     StartGroup,
     /// --end-group
@@ -52,14 +58,99 @@ impl Default for HashStyle {
     }
 }
 
+/// which local symbols to discard from the output symbol table
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Discard {
+    /// --discard-none
+    #[default]
+    None,
+    /// -X, --discard-locals: temporary locals starting with .L
+    Locals,
+    /// -x, --discard-all
+    All,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Symbolic {
+    /// -Bno-symbolic
+    #[default]
+    None,
+    /// -Bsymbolic-functions: bind function symbols locally
+    Functions,
+    /// -Bsymbolic: bind all symbols locally
+    All,
+}
+
+/// how the build ID note is computed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildId {
+    /// --build-id, --build-id=sha1: SHA-1 of the output
+    Sha1,
+    /// --build-id=md5: MD5 of the output
+    Md5,
+    /// --build-id=uuid: random
+    Uuid,
+    /// --build-id=0xHEX: given bytes
+    Hex(Vec<u8>),
+}
+
+/// what to do with sections not placed by the default layout
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum OrphanHandling {
+    /// --orphan-handling=place: next to sections with similar flags
+    #[default]
+    Place,
+    /// --orphan-handling=warn: place and warn
+    Warn,
+    /// --orphan-handling=error: place and fail the link
+    Error,
+    /// --orphan-handling=discard: remove from the output
+    Discard,
+}
+
+/// -z keyword
+#[derive(Debug, Clone, Default)]
+pub struct ZOptions {
+    /// -z muldefs, --allow-multiple-definition
+    pub muldefs: bool,
+    /// -z now
+    pub now: bool,
+    /// -z origin
+    pub origin: bool,
+    /// -z global: symbols of this object are available to later dlopen()
+    pub global: bool,
+    /// -z initfirst
+    pub initfirst: bool,
+    /// -z nodelete: not unloaded by dlclose()
+    pub nodelete: bool,
+    /// -z nodlopen: not loadable by dlopen()
+    pub nodlopen: bool,
+    /// -z execstack/noexecstack
+    pub execstack: Option<bool>,
+    /// -z text/notext
+    pub text: bool,
+    /// -z stack-size=size
+    pub stack_size: Option<u64>,
+    /// -z max-page-size=size
+    pub max_page_size: Option<u64>,
+    /// -z common-page-size=size
+    pub common_page_size: Option<u64>,
+    /// -z gnu-hash-buckets=count, computed from symbol count by default
+    pub gnu_hash_buckets: Option<u32>,
+    /// -z gnu-hash-bloom-shift=shift, the second hash bit of bloom filter
+    pub gnu_hash_bloom_shift: Option<u32>,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Opt {
-    /// --build-id
-    pub build_id: bool,
+    /// --build-id[=style]
+    pub build_id: Option<BuildId>,
     /// --eh-frame-hdr
     pub eh_frame_hdr: bool,
     /// -pie
     pub pie: bool,
+    /// -static -pie: relocated by its own startup code, without ld.so
+    pub static_pie: bool,
     /// -shared
     pub shared: bool,
     /// -m emulation
@@ -68,148 +159,1088 @@ pub struct Opt {
     pub output: Option<String>,
     /// -dynamic-linker
     pub dynamic_linker: Option<String>,
+    /// --no-dynamic-linker: omit .interp and PT_INTERP
+    pub no_dynamic_linker: bool,
     /// -L searchdir
     pub search_dir: Vec<String>,
+    /// --sysroot=directory
+    pub sysroot: Option<String>,
     /// --hash-style=sysv/gnu/both
     pub hash_style: HashStyle,
     /// -soname SONAME
     pub soname: Option<String>,
+    /// -init name, _init by default
+    pub init: Option<String>,
+    /// -fini name, _fini by default
+    pub fini: Option<String>,
+    /// -rpath dir
+    pub rpath: Vec<String>,
+    /// -rpath-link dir
+    pub rpath_link: Vec<String>,
+    /// --disable-new-dtags
+    pub disable_new_dtags: bool,
+    /// -Map mapfile
+    pub map_file: Option<String>,
+    /// -M, --print-map
+    pub print_map: bool,
+    /// --cref
+    pub cref: bool,
+    /// --demangle, --no-demangle
+    pub demangle: bool,
+    /// --relax, --no-relax: relax R_X86_64_GOTPCRELX code sequences
+    pub relax: bool,
+    /// -S, --strip-debug
+    pub strip_debug: bool,
+    /// -s, --strip-all
+    pub strip_all: bool,
+    /// --separate-debug-file[=FILE], empty for OUTPUT.dbg
+    pub separate_debug_file: Option<String>,
+    /// -x, -X, --discard-none
+    pub discard: Discard,
+    /// -Bsymbolic, -Bsymbolic-functions, -Bno-symbolic
+    pub symbolic: Symbolic,
+    /// --orphan-handling=place/warn/error/discard
+    pub orphan_handling: OrphanHandling,
+    /// --section-start=section=org, -Ttext, -Tdata, -Tbss
+    pub section_start: BTreeMap<String, u64>,
+    /// --unique[=SECTION]: section name patterns, empty for orphan sections
+    pub unique: Vec<String>,
+    /// -T scriptfile, --script=scriptfile
+    pub scripts: Vec<String>,
+    /// --require-defined=symbol
+    pub require_defined: Vec<String>,
+    /// -u symbol, --undefined=symbol
+    pub undefined: Vec<String>,
+    /// --gc-sections, --no-gc-sections
+    pub gc_sections: bool,
+    /// --print-gc-sections, --no-print-gc-sections
+    pub print_gc_sections: bool,
+    /// --retain-symbols-file=filename
+    pub retain_symbols_file: Option<String>,
+    /// --symbol-ordering-file=filename
+    pub symbol_ordering_file: Option<String>,
+    /// -t, --trace
+    pub trace: bool,
+    /// -y symbol, --trace-symbol=symbol
+    pub trace_symbol: Vec<String>,
+    /// --version
+    pub version: bool,
+    /// -v
+    pub print_version: bool,
+    /// --verbose
+    pub verbose: bool,
+    /// --help
+    pub help: bool,
+    /// -z keyword
+    pub z_options: ZOptions,
     /// ObjectFile
     pub obj_file: Vec<ObjectFileOpt>,
 }
 
-/// parse arguments
-pub fn parse_opts(args: &[String]) -> anyhow::Result<Opt> {
-    let mut opt = Opt::default();
-    let mut cur_opt_stack = OptStack {
-        as_needed: false,
-        link_static: false,
+/// Version banner, mentioning GNU ld for compiler drivers probing the linker
+pub fn version_banner() -> String {
+    format!(
+        "cold {} (compatible with GNU ld)",
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// parse number in decimal, or hexadecimal with 0x prefix
+fn parse_number(s: &str) -> anyhow::Result<u64> {
+    let res = if let Some(hex) = s.strip_prefix("0x").or(s.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16)
+    } else {
+        s.parse::<u64>()
     };
-    let mut opt_stack = vec![];
-    let mut iter = args.iter();
-    while let Some(arg) = iter.next() {
-        match arg.as_str() {
-            // single dash
-            s if s.starts_with("-L") => {
-                // library search path argument
-                opt.search_dir
-                    .push(s.strip_prefix("-L").unwrap().to_string());
-            }
-            "-dynamic-linker" => {
-                // dynamic linker argument
-                opt.dynamic_linker = Some(
-                    iter.next()
-                        .ok_or(anyhow!("Missing dynamic linker after -dynamic-linker"))?
-                        .to_string(),
-                );
-            }
-            s if s.starts_with("-l") => {
-                // library argument
-                opt.obj_file.push(ObjectFileOpt::Library(LibraryOpt {
-                    name: s.strip_prefix("-l").unwrap().to_string(),
-                    as_needed: cur_opt_stack.as_needed,
-                    link_static: cur_opt_stack.link_static,
-                }));
-            }
-            "-m" => {
-                // emulation argument
-                opt.emulation = Some(
-                    iter.next()
-                        .ok_or(anyhow!("Missing emulation after -m"))?
-                        .to_string(),
-                );
-            }
-            "-o" => {
-                // output argument
-                opt.output = Some(
-                    iter.next()
-                        .ok_or(anyhow!("Missing output after -o"))?
-                        .to_string(),
-                );
-            }
-            "-pie" => {
-                opt.pie = true;
-            }
-            "-plugin" => {
-                // skip plugin argument
-                iter.next();
-            }
-            s if s.starts_with("-plugin-opt=") => {
-                // ignored
-            }
-            "-shared" => {
-                opt.shared = true;
-            }
-            "-soname" => {
-                // soname argument
-                opt.soname = Some(
-                    iter.next()
-                        .ok_or(anyhow!("Missing file name after -soname"))?
-                        .to_string(),
-                );
-            }
-            "-static" => {
-                cur_opt_stack.link_static = true;
-            }
-            "-z" => {
-                // skip -z argument for now
-                iter.next();
-            }
+    res.map_err(|err| anyhow!("Invalid number {s}: {err}"))
+}
 
-            // double dashes
-            "--as-needed" => {
-                cur_opt_stack.as_needed = true;
-            }
-            "--build-id" => {
-                opt.build_id = true;
-            }
-            "--eh-frame-hdr" => {
-                opt.eh_frame_hdr = true;
+/// parse address of -Ttext and --section-start, always in hexadecimal
+fn parse_address(s: &str) -> anyhow::Result<u64> {
+    let hex = s.strip_prefix("0x").or(s.strip_prefix("0X")).unwrap_or(s);
+    u64::from_str_radix(hex, 16).map_err(|err| anyhow!("Invalid address {s}: {err}"))
+}
+
+/// parse page size, which must be a power of two
+fn parse_page_size(s: &str) -> anyhow::Result<u64> {
+    let size = parse_number(s)?;
+    if !size.is_power_of_two() {
+        bail!("Invalid page size {s}: must be a power of two");
+    }
+    Ok(size)
+}
+
+/// parse -z keyword
+fn parse_z_option(z_options: &mut ZOptions, keyword: &str) -> anyhow::Result<()> {
+    match keyword {
+        "execstack" => {
+            z_options.execstack = Some(true);
+        }
+        "global" => {
+            z_options.global = true;
+        }
+        "initfirst" => {
+            z_options.initfirst = true;
+        }
+        "lazy" => {
+            z_options.now = false;
+        }
+        "muldefs" => {
+            z_options.muldefs = true;
+        }
+        "nodelete" => {
+            z_options.nodelete = true;
+        }
+        "nodlopen" => {
+            z_options.nodlopen = true;
+        }
+        "noexecstack" => {
+            z_options.execstack = Some(false);
+        }
+        "origin" => {
+            z_options.origin = true;
+        }
+        "notext" | "textoff" => {
+            z_options.text = false;
+        }
+        "text" => {
+            z_options.text = true;
+        }
+        s if s.starts_with("common-page-size=") => {
+            z_options.common_page_size = Some(parse_page_size(
+                s.strip_prefix("common-page-size=").unwrap(),
+            )?);
+        }
+        s if s.starts_with("gnu-hash-buckets=") => {
+            let count = parse_number(s.strip_prefix("gnu-hash-buckets=").unwrap())?;
+            if count == 0 || count > u32::MAX as u64 {
+                bail!("Invalid .gnu.hash bucket count {count}");
             }
-            "--end-group" => {
-                opt.obj_file.push(ObjectFileOpt::EndGroup);
+            z_options.gnu_hash_buckets = Some(count as u32);
+        }
+        s if s.starts_with("gnu-hash-bloom-shift=") => {
+            let shift = parse_number(s.strip_prefix("gnu-hash-bloom-shift=").unwrap())?;
+            // the shifted hash selects a bit in 64-bit bloom words
+            if shift == 0 || shift >= 32 {
+                bail!("Invalid .gnu.hash bloom shift {shift}, should be in 1..32");
             }
-            s if s.starts_with("--hash-style=") => match s {
-                "--hash-style=sysv" => {
-                    opt.hash_style.sysv = true;
-                    opt.hash_style.gnu = false;
+            z_options.gnu_hash_bloom_shift = Some(shift as u32);
+        }
+        s if s.starts_with("max-page-size=") => {
+            z_options.max_page_size =
+                Some(parse_page_size(s.strip_prefix("max-page-size=").unwrap())?);
+        }
+        s if s.starts_with("stack-size=") => {
+            z_options.stack_size = Some(parse_number(s.strip_prefix("stack-size=").unwrap())?);
+        }
+        "now" => {
+            z_options.now = true;
+        }
+        _ => {
+            // unknown keywords are ignored, like GNU ld does
+            warn!("Ignoring unsupported -z {keyword}");
+        }
+    }
+    Ok(())
+}
+
+/// parser state shared by option handlers
+struct ParseState {
+    opt: Opt,
+    cur_opt_stack: OptStack,
+    opt_stack: Vec<OptStack>,
+}
+
+/// how an option takes its argument
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum OptionArg {
+    /// no argument, e.g. -pie
+    None,
+    /// argument in the next arg, after '=', or joined to a single letter option:
+    /// -o out, -o=out, -oout, --output out, --output=out
+    Required,
+    /// like Required, but -x=value keeps the '=', e.g. -L=dir is relative to sysroot
+    RequiredKeepEquals,
+    /// argument only after '=', empty if absent: --build-id, --build-id=sha1
+    Optional,
+}
+
+/// a recognized option, used for both parsing and --help
+struct OptionSpec {
+    /// names without leading dashes: single letters are used as -x,
+    /// longer names as either -name or --name
+    names: &'static [&'static str],
+    arg: OptionArg,
+    /// argument name shown in --help
+    metavar: &'static str,
+    /// description shown in --help
+    help: &'static str,
+    handler: fn(&mut ParseState, &str) -> anyhow::Result<()>,
+}
+
+/// all recognized options
+static OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        names: &["allow-multiple-definition"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Allow multiple definitions, same as -z muldefs",
+        handler: |state, _| {
+            state.opt.z_options.muldefs = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["as-needed"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Only add DT_NEEDED for libraries that are used",
+        handler: |state, _| {
+            state.cur_opt_stack.as_needed = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["Bno-symbolic"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Allow global symbols of shared library to be preempted",
+        handler: |state, _| {
+            state.opt.symbolic = Symbolic::None;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["Bsymbolic"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Bind global references locally in shared library",
+        handler: |state, _| {
+            state.opt.symbolic = Symbolic::All;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["Bsymbolic-functions"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Bind global function references locally in shared library",
+        handler: |state, _| {
+            state.opt.symbolic = Symbolic::Functions;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["build-id"],
+        arg: OptionArg::Optional,
+        metavar: "STYLE",
+        help: "Generate build ID note with sha1, md5, uuid, 0xHEX or none",
+        handler: |state, value| {
+            state.opt.build_id = match value {
+                "" | "sha1" => Some(BuildId::Sha1),
+                "md5" => Some(BuildId::Md5),
+                "uuid" => Some(BuildId::Uuid),
+                "none" => None,
+                _ => {
+                    let hex = value
+                        .strip_prefix("0x")
+                        .or(value.strip_prefix("0X"))
+                        .filter(|hex| !hex.is_empty() && hex.len().is_multiple_of(2))
+                        .ok_or(anyhow!("Invalid build-id style: {value}"))?;
+                    let bytes = (0..hex.len())
+                        .step_by(2)
+                        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                        .collect::<Result<Vec<u8>, _>>()
+                        .map_err(|_| anyhow!("Invalid build-id style: {value}"))?;
+                    Some(BuildId::Hex(bytes))
                 }
-                "--hash-style=gnu" => {
-                    opt.hash_style.sysv = false;
-                    opt.hash_style.gnu = true;
+            };
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["cref"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Output cross reference table",
+        handler: |state, _| {
+            state.opt.cref = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["demangle"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Demangle symbol names in diagnostics",
+        handler: |state, _| {
+            state.opt.demangle = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["disable-new-dtags"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Use DT_RPATH instead of DT_RUNPATH",
+        handler: |state, _| {
+            state.opt.disable_new_dtags = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["x", "discard-all"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Discard all local symbols",
+        handler: |state, _| {
+            state.opt.discard = Discard::All;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["X", "discard-locals"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Discard temporary local symbols starting with .L",
+        handler: |state, _| {
+            state.opt.discard = Discard::Locals;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["discard-none"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Keep all local symbols",
+        handler: |state, _| {
+            state.opt.discard = Discard::None;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["dynamic-linker"],
+        arg: OptionArg::Required,
+        metavar: "PROGRAM",
+        help: "Set the dynamic linker to use",
+        handler: |state, value| {
+            state.opt.dynamic_linker = Some(value.to_string());
+            state.opt.no_dynamic_linker = false;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["eh-frame-hdr"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Create .eh_frame_hdr section and PT_GNU_EH_FRAME segment",
+        handler: |state, _| {
+            state.opt.eh_frame_hdr = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["enable-new-dtags"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Use DT_RUNPATH instead of DT_RPATH",
+        handler: |state, _| {
+            state.opt.disable_new_dtags = false;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["end-group"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "End a group of archives",
+        handler: |state, _| {
+            state.opt.obj_file.push(ObjectFileOpt::EndGroup);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["fini"],
+        arg: OptionArg::Required,
+        metavar: "SYMBOL",
+        help: "Call SYMBOL at unload time, _fini by default",
+        handler: |state, value| {
+            state.opt.fini = Some(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["gc-sections"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Remove unused sections",
+        handler: |state, _| {
+            state.opt.gc_sections = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["hash-style"],
+        arg: OptionArg::Required,
+        metavar: "STYLE",
+        help: "Set hash style to sysv, gnu or both",
+        handler: |state, value| {
+            let hash_style = &mut state.opt.hash_style;
+            match value {
+                "sysv" => {
+                    hash_style.sysv = true;
+                    hash_style.gnu = false;
                 }
-                "--hash-style=both" => {
-                    opt.hash_style.sysv = true;
-                    opt.hash_style.gnu = true;
+                "gnu" => {
+                    hash_style.sysv = false;
+                    hash_style.gnu = true;
+                }
+                "both" => {
+                    hash_style.sysv = true;
+                    hash_style.gnu = true;
                 }
                 _ => {
-                    bail!("Invalid --hash-style option: {}", s)
+                    bail!("Invalid --hash-style option: {}", value)
                 }
-            },
-            "--start-group" => {
-                opt.obj_file.push(ObjectFileOpt::StartGroup);
             }
-            "--pop-state" => {
-                cur_opt_stack = opt_stack.pop().unwrap();
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["help"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Print option help",
+        handler: |state, _| {
+            state.opt.help = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["init"],
+        arg: OptionArg::Required,
+        metavar: "SYMBOL",
+        help: "Call SYMBOL at load time, _init by default",
+        handler: |state, value| {
+            state.opt.init = Some(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["L", "library-path"],
+        arg: OptionArg::RequiredKeepEquals,
+        metavar: "DIR",
+        help: "Add directory to library search path",
+        handler: |state, value| {
+            state.opt.search_dir.push(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["l", "library"],
+        arg: OptionArg::Required,
+        metavar: "NAMESPEC",
+        help: "Search for library NAMESPEC, or file name if it starts with :",
+        handler: |state, value| {
+            // -l:filename searches for filename verbatim
+            let (name, verbatim) = match value.strip_prefix(':') {
+                Some(name) => (name, true),
+                None => (value, false),
+            };
+            state.opt.obj_file.push(ObjectFileOpt::Library(LibraryOpt {
+                name: name.to_string(),
+                verbatim,
+                as_needed: state.cur_opt_stack.as_needed,
+                link_static: state.cur_opt_stack.link_static,
+            }));
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["M", "print-map"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Print link map to standard output",
+        handler: |state, _| {
+            state.opt.print_map = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["Map"],
+        arg: OptionArg::Required,
+        metavar: "FILE",
+        help: "Write link map to FILE",
+        handler: |state, value| {
+            state.opt.map_file = Some(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["m"],
+        arg: OptionArg::Required,
+        metavar: "EMULATION",
+        help: "Set emulation",
+        handler: |state, value| {
+            state.opt.emulation = Some(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["no-demangle"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Do not demangle symbol names in diagnostics",
+        handler: |state, _| {
+            state.opt.demangle = false;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["no-dynamic-linker"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Do not request a program interpreter",
+        handler: |state, _| {
+            state.opt.dynamic_linker = None;
+            state.opt.no_dynamic_linker = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["no-gc-sections"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Do not remove unused sections",
+        handler: |state, _| {
+            state.opt.gc_sections = false;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["no-print-gc-sections"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Do not list removed unused sections",
+        handler: |state, _| {
+            state.opt.print_gc_sections = false;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["no-relax"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Keep .got accesses of locally bound symbols",
+        handler: |state, _| {
+            state.opt.relax = false;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["o", "output"],
+        arg: OptionArg::Required,
+        metavar: "FILE",
+        help: "Set output file name",
+        handler: |state, value| {
+            state.opt.output = Some(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["orphan-handling"],
+        arg: OptionArg::Required,
+        metavar: "MODE",
+        help: "Place, warn, error or discard orphan sections",
+        handler: |state, value| {
+            state.opt.orphan_handling = match value {
+                "place" => OrphanHandling::Place,
+                "warn" => OrphanHandling::Warn,
+                "error" => OrphanHandling::Error,
+                "discard" => OrphanHandling::Discard,
+                _ => bail!("Invalid --orphan-handling option: {}", value),
+            };
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["pie"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Create a position independent executable",
+        handler: |state, _| {
+            state.opt.pie = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["plugin"],
+        arg: OptionArg::Required,
+        metavar: "PLUGIN",
+        help: "Load plugin (ignored)",
+        handler: |_, _| Ok(()),
+    },
+    OptionSpec {
+        names: &["plugin-opt"],
+        arg: OptionArg::Required,
+        metavar: "ARG",
+        help: "Send argument to plugin (ignored)",
+        handler: |_, _| Ok(()),
+    },
+    OptionSpec {
+        names: &["pop-state"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Restore state saved by --push-state",
+        handler: |state, _| {
+            state.cur_opt_stack = state
+                .opt_stack
+                .pop()
+                .ok_or(anyhow!("--pop-state without matching --push-state"))?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["print-gc-sections"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "List sections removed by --gc-sections",
+        handler: |state, _| {
+            state.opt.print_gc_sections = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["push-state"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Save --as-needed and -static state",
+        handler: |state, _| {
+            state.opt_stack.push(state.cur_opt_stack);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["R", "just-symbols"],
+        arg: OptionArg::Required,
+        metavar: "FILE",
+        help: "Use symbol addresses of FILE without linking it, or -rpath for directories",
+        handler: |state, value| {
+            if std::path::Path::new(value).is_dir() {
+                // compatible with other ELF linkers: -R dir is -rpath dir
+                state.opt.rpath.push(value.to_string());
+            } else {
+                state
+                    .opt
+                    .obj_file
+                    .push(ObjectFileOpt::JustSymbols(value.to_string()));
             }
-            "--push-state" => {
-                opt_stack.push(cur_opt_stack);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["relax"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Rewrite .got accesses of locally bound symbols (default)",
+        handler: |state, _| {
+            state.opt.relax = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["require-defined"],
+        arg: OptionArg::Required,
+        metavar: "SYMBOL",
+        help: "Require SYMBOL be defined in the final output",
+        handler: |state, value| {
+            state.opt.require_defined.push(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["retain-symbols-file"],
+        arg: OptionArg::Required,
+        metavar: "FILE",
+        help: "Keep only symbols listed in FILE in .symtab",
+        handler: |state, value| {
+            state.opt.retain_symbols_file = Some(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["rpath"],
+        arg: OptionArg::Required,
+        metavar: "DIR",
+        help: "Add directory to runtime library search path",
+        handler: |state, value| {
+            state.opt.rpath.push(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["rpath-link"],
+        arg: OptionArg::Required,
+        metavar: "DIR",
+        help: "Add directory to link time shared library search path",
+        handler: |state, value| {
+            state.opt.rpath_link.push(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["section-start"],
+        arg: OptionArg::Required,
+        metavar: "SECTION=ADDRESS",
+        help: "Set address of output section",
+        handler: |state, value| {
+            let (section, address) = value
+                .split_once('=')
+                .ok_or(anyhow!("Invalid --section-start option: {value}"))?;
+            state
+                .opt
+                .section_start
+                .insert(section.to_string(), parse_address(address)?);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["separate-debug-file"],
+        arg: OptionArg::Optional,
+        metavar: "FILE",
+        help: "Write debug sections to FILE, OUTPUT.dbg by default",
+        handler: |state, value| {
+            state.opt.separate_debug_file = Some(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["shared"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Create a shared library",
+        handler: |state, _| {
+            state.opt.shared = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["soname", "h"],
+        arg: OptionArg::Required,
+        metavar: "NAME",
+        help: "Set DT_SONAME of shared library",
+        handler: |state, value| {
+            state.opt.soname = Some(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["start-group"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Start a group of archives",
+        handler: |state, _| {
+            state.opt.obj_file.push(ObjectFileOpt::StartGroup);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["static"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Do not link against shared libraries, static pie with -pie",
+        handler: |state, _| {
+            state.cur_opt_stack.link_static = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["S", "strip-debug"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Omit debug sections",
+        handler: |state, _| {
+            state.opt.strip_debug = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["s", "strip-all"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Omit debug sections and symbol table",
+        handler: |state, _| {
+            state.opt.strip_debug = true;
+            state.opt.strip_all = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["symbol-ordering-file"],
+        arg: OptionArg::Required,
+        metavar: "FILE",
+        help: "Lay out sections in the order of symbols listed in FILE",
+        handler: |state, value| {
+            state.opt.symbol_ordering_file = Some(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["sysroot"],
+        arg: OptionArg::Required,
+        metavar: "DIR",
+        help: "Set the system root",
+        handler: |state, value| {
+            state.opt.sysroot = Some(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["T", "script"],
+        arg: OptionArg::Required,
+        metavar: "FILE",
+        help: "Read linker script",
+        handler: |state, value| {
+            state.opt.scripts.push(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["Tbss"],
+        arg: OptionArg::Required,
+        metavar: "ADDRESS",
+        help: "Set address of .bss section",
+        handler: |state, value| {
+            state
+                .opt
+                .section_start
+                .insert(".bss".to_string(), parse_address(value)?);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["Tdata"],
+        arg: OptionArg::Required,
+        metavar: "ADDRESS",
+        help: "Set address of .data section",
+        handler: |state, value| {
+            state
+                .opt
+                .section_start
+                .insert(".data".to_string(), parse_address(value)?);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["Ttext"],
+        arg: OptionArg::Required,
+        metavar: "ADDRESS",
+        help: "Set address of .text section",
+        handler: |state, value| {
+            state
+                .opt
+                .section_start
+                .insert(".text".to_string(), parse_address(value)?);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["t", "trace"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Trace file opens",
+        handler: |state, _| {
+            state.opt.trace = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["y", "trace-symbol"],
+        arg: OptionArg::Required,
+        metavar: "SYMBOL",
+        help: "Trace mentions of SYMBOL",
+        handler: |state, value| {
+            state.opt.trace_symbol.push(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["u", "undefined"],
+        arg: OptionArg::Required,
+        metavar: "SYMBOL",
+        help: "Start with SYMBOL undefined",
+        handler: |state, value| {
+            state.opt.undefined.push(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["unique"],
+        arg: OptionArg::Optional,
+        metavar: "SECTION",
+        help: "Do not merge input sections matching SECTION, or orphan sections",
+        handler: |state, value| {
+            state.opt.unique.push(value.to_string());
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["v"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Print version information and continue",
+        handler: |state, _| {
+            state.opt.print_version = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["verbose"],
+        arg: OptionArg::Optional,
+        metavar: "LEVEL",
+        help: "Print version information and the default linker script",
+        handler: |state, value| {
+            // any level prints the same
+            if !value.is_empty() {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| anyhow!("Invalid verbose level {}", value))?;
             }
-            // end of known flags
-            s if s.starts_with('-') => {
-                // unknown flag
-                return Err(anyhow!("Unknown argument: {s}"));
+            state.opt.verbose = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["version"],
+        arg: OptionArg::None,
+        metavar: "",
+        help: "Print version information and exit",
+        handler: |state, _| {
+            state.opt.version = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["z"],
+        arg: OptionArg::Required,
+        metavar: "KEYWORD",
+        help: "Set -z keyword, e.g. now, origin, execstack, stack-size=SIZE",
+        handler: |state, value| parse_z_option(&mut state.opt.z_options, value),
+    },
+];
+
+/// find the option matching arg, along with its joined argument if any
+fn find_option(arg: &str) -> Option<(&'static OptionSpec, Option<&str>)> {
+    // long options: -name, --name, -name=value or --name=value,
+    // names starting with 'o' require two dashes to avoid confusion with -o
+    let long = arg
+        .strip_prefix("--")
+        .or(arg.strip_prefix('-').filter(|s| !s.starts_with('o')));
+    if let Some(long) = long {
+        let (name, value) = match long.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (long, None),
+        };
+        for spec in OPTIONS {
+            if spec.names.iter().any(|n| n.len() > 1 && *n == name)
+                && (value.is_none() || spec.arg != OptionArg::None)
+            {
+                return Some((spec, value));
             }
-            s => {
-                // object file argument
-                opt.obj_file.push(ObjectFileOpt::File(FileOpt {
-                    name: s.to_string(),
-                    as_needed: cur_opt_stack.as_needed,
-                }));
+        }
+    }
+
+    // single letter options: -x, -xvalue or -x=value
+    let short = arg.strip_prefix('-').filter(|s| !s.starts_with('-'))?;
+    for spec in OPTIONS {
+        for name in spec.names.iter().filter(|n| n.len() == 1) {
+            let Some(rest) = short.strip_prefix(name) else {
+                continue;
+            };
+            match spec.arg {
+                _ if rest.is_empty() => return Some((spec, None)),
+                OptionArg::None => {}
+                OptionArg::Required => {
+                    return Some((spec, Some(rest.strip_prefix('=').unwrap_or(rest))))
+                }
+                OptionArg::RequiredKeepEquals => return Some((spec, Some(rest))),
+                OptionArg::Optional => {}
             }
         }
     }
-    Ok(opt)
+    None
+}
+
+/// generate --help text from the option table
+pub fn help_text() -> String {
+    let mut res = String::new();
+    res += "Usage: ld [options] file...\n";
+    res += "Options:\n";
+    for spec in OPTIONS {
+        let names = spec
+            .names
+            .iter()
+            .map(|name| {
+                let dashes = if name.len() == 1 { "-" } else { "--" };
+                if spec.arg == OptionArg::None {
+                    format!("{dashes}{name}")
+                } else if spec.arg == OptionArg::Optional {
+                    format!("{dashes}{name}[={}]", spec.metavar)
+                } else {
+                    format!("{dashes}{name} {}", spec.metavar)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        if names.len() > 28 {
+            res += &format!("  {names}\n  {:28}  {}\n", "", spec.help);
+        } else {
+            res += &format!("  {names:28}  {}\n", spec.help);
+        }
+    }
+    res
+}
+
+/// parse arguments
+pub fn parse_opts(args: &[String]) -> anyhow::Result<Opt> {
+    let mut state = ParseState {
+        opt: Opt {
+            // like GNU ld, demangle by default unless the compiler driver does it
+            demangle: std::env::var_os("COLLECT_NO_DEMANGLE").is_none(),
+            relax: true,
+            ..Opt::default()
+        },
+        cur_opt_stack: OptStack {
+            as_needed: false,
+            link_static: false,
+        },
+        opt_stack: vec![],
+    };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if let Some((spec, value)) = find_option(arg) {
+            let value = match (spec.arg, value) {
+                (OptionArg::None, _) => "",
+                (OptionArg::Optional, value) => value.unwrap_or_default(),
+                (_, Some(value)) => value,
+                (_, None) => iter
+                    .next()
+                    .ok_or(anyhow!("Missing {} after {arg}", spec.metavar))?,
+            };
+            (spec.handler)(&mut state, value)?;
+        } else if arg.starts_with('-') {
+            // unknown flag
+            return Err(anyhow!("Unknown argument: {arg}"));
+        } else {
+            // object file argument
+            state.opt.obj_file.push(ObjectFileOpt::File(FileOpt {
+                name: arg.to_string(),
+                as_needed: state.cur_opt_stack.as_needed,
+            }));
+        }
+    }
+    state.opt.static_pie = state.opt.pie && state.cur_opt_stack.link_static;
+    Ok(state.opt)
 }
 
 #[cfg(test)]
@@ -231,22 +1262,484 @@ mod tests {
         if let ObjectFileOpt::Library(lib) = &opts.obj_file[0] {
             assert_eq!(lib.name, "a");
             assert!(!lib.as_needed);
+            assert!(!lib.verbatim);
         } else {
-            assert!(false);
+            unreachable!();
         }
 
         if let ObjectFileOpt::Library(lib) = &opts.obj_file[1] {
             assert_eq!(lib.name, "b");
             assert!(lib.as_needed);
         } else {
-            assert!(false);
+            unreachable!();
         }
 
         if let ObjectFileOpt::Library(lib) = &opts.obj_file[2] {
             assert_eq!(lib.name, "c");
             assert!(!lib.as_needed);
         } else {
-            assert!(false);
+            unreachable!();
+        }
+    }
+
+    #[test]
+    fn test_z_options() {
+        let opts = parse_opts(&["-z".to_string(), "now".to_string()]).unwrap();
+        assert!(opts.z_options.now);
+        assert!(!opts.z_options.origin);
+
+        let opts = parse_opts(&["-zorigin".to_string()]).unwrap();
+        assert!(opts.z_options.origin);
+
+        let opts = parse_opts(&["--allow-multiple-definition".to_string()]).unwrap();
+        assert!(opts.z_options.muldefs);
+
+        let opts = parse_opts(&["-znow".to_string(), "-zlazy".to_string()]).unwrap();
+        assert!(!opts.z_options.now);
+        assert_eq!(opts.z_options.execstack, None);
+
+        let opts = parse_opts(&["-z".to_string(), "noexecstack".to_string()]).unwrap();
+        assert_eq!(opts.z_options.execstack, Some(false));
+
+        let opts = parse_opts(&[
+            "-znodelete".to_string(),
+            "-znodlopen".to_string(),
+            "-z".to_string(),
+            "global".to_string(),
+            "-zinitfirst".to_string(),
+        ])
+        .unwrap();
+        assert!(opts.z_options.nodelete);
+        assert!(opts.z_options.nodlopen);
+        assert!(opts.z_options.global);
+        assert!(opts.z_options.initfirst);
+
+        let opts = parse_opts(&["-zstack-size=0x100000".to_string()]).unwrap();
+        assert_eq!(opts.z_options.stack_size, Some(0x100000));
+        assert!(parse_opts(&["-zstack-size=abc".to_string()]).is_err());
+
+        let opts = parse_opts(&[
+            "-z".to_string(),
+            "max-page-size=65536".to_string(),
+            "-z".to_string(),
+            "common-page-size=0x4000".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(opts.z_options.max_page_size, Some(65536));
+        assert_eq!(opts.z_options.common_page_size, Some(0x4000));
+        assert!(parse_opts(&["-zmax-page-size=12345".to_string()]).is_err());
+
+        let opts = parse_opts(&[
+            "-zgnu-hash-buckets=7".to_string(),
+            "-zgnu-hash-bloom-shift=6".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(opts.z_options.gnu_hash_buckets, Some(7));
+        assert_eq!(opts.z_options.gnu_hash_bloom_shift, Some(6));
+        assert!(parse_opts(&["-zgnu-hash-buckets=0".to_string()]).is_err());
+        assert!(parse_opts(&["-zgnu-hash-bloom-shift=32".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_rpath() {
+        let opts = parse_opts(&[
+            "-rpath".to_string(),
+            "$ORIGIN/../lib".to_string(),
+            "-rpath=/opt/lib".to_string(),
+            "-rpath-link".to_string(),
+            ".".to_string(),
+            "--disable-new-dtags".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(opts.rpath, vec!["$ORIGIN/../lib", "/opt/lib"]);
+        assert_eq!(opts.rpath_link, vec!["."]);
+        assert!(opts.disable_new_dtags);
+        assert_eq!(opts.retain_symbols_file, None);
+
+        let opts = parse_opts(&["--retain-symbols-file=keep.txt".to_string()]).unwrap();
+        assert_eq!(opts.retain_symbols_file.as_deref(), Some("keep.txt"));
+    }
+
+    #[test]
+    fn test_soname() {
+        for args in [
+            vec!["-soname", "libfoo.so.1"],
+            vec!["-h", "libfoo.so.1"],
+            vec!["--soname", "libfoo.so.1"],
+            vec!["--soname=libfoo.so.1"],
+            vec!["-soname=libfoo.so.1"],
+        ] {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            let opts = parse_opts(&args).unwrap();
+            assert_eq!(opts.soname.as_deref(), Some("libfoo.so.1"));
+        }
+        assert!(parse_opts(&["-h".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_static_pie() {
+        let args: Vec<String> = ["-static", "-pie"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        assert!(parse_opts(&args[..2]).unwrap().static_pie);
+        assert!(!parse_opts(&args[1..2]).unwrap().static_pie);
+        assert!(!parse_opts(&args[..1]).unwrap().static_pie);
+    }
+
+    #[test]
+    fn test_init_fini() {
+        let opts = parse_opts(&[]).unwrap();
+        assert_eq!(opts.init, None);
+        assert_eq!(opts.fini, None);
+        let args: Vec<String> = ["-init", "my_init", "--fini=my_fini"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let opts = parse_opts(&args).unwrap();
+        assert_eq!(opts.init.as_deref(), Some("my_init"));
+        assert_eq!(opts.fini.as_deref(), Some("my_fini"));
+    }
+
+    #[test]
+    fn test_map() {
+        let opts = parse_opts(&["-Map".to_string(), "a.map".to_string()]).unwrap();
+        assert_eq!(opts.map_file.as_deref(), Some("a.map"));
+        let opts = parse_opts(&["--Map=b.map".to_string(), "-M".to_string()]).unwrap();
+        assert_eq!(opts.map_file.as_deref(), Some("b.map"));
+        assert!(opts.print_map);
+    }
+
+    #[test]
+    fn test_trace() {
+        let opts = parse_opts(&[
+            "-t".to_string(),
+            "-y".to_string(),
+            "foo".to_string(),
+            "-ybar".to_string(),
+            "--trace-symbol=baz".to_string(),
+        ])
+        .unwrap();
+        assert!(opts.trace);
+        assert_eq!(opts.trace_symbol, vec!["foo", "bar", "baz"]);
+    }
+
+    #[test]
+    fn test_unique() {
+        let opts = parse_opts(&["--unique".to_string(), "--unique=.text.*".to_string()]).unwrap();
+        assert_eq!(opts.unique, vec!["", ".text.*"]);
+    }
+
+    #[test]
+    fn test_separate_debug_file() {
+        let opts = parse_opts(&[]).unwrap();
+        assert_eq!(opts.separate_debug_file, None);
+        let opts = parse_opts(&["--separate-debug-file".to_string()]).unwrap();
+        assert_eq!(opts.separate_debug_file.as_deref(), Some(""));
+        let opts = parse_opts(&["--separate-debug-file=a.debug".to_string()]).unwrap();
+        assert_eq!(opts.separate_debug_file.as_deref(), Some("a.debug"));
+    }
+
+    #[test]
+    fn test_version() {
+        let opts = parse_opts(&["--version".to_string()]).unwrap();
+        assert!(opts.version);
+        let opts = parse_opts(&["-v".to_string(), "a.o".to_string()]).unwrap();
+        assert!(opts.print_version);
+        assert_eq!(opts.obj_file.len(), 1);
+        assert!(version_banner().contains("GNU ld"));
+        assert!(parse_opts(&["--verbose".to_string()]).unwrap().verbose);
+        assert!(parse_opts(&["--verbose=1".to_string()]).unwrap().verbose);
+        assert!(parse_opts(&["--verbose=high".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_help() {
+        let opts = parse_opts(&["--help".to_string()]).unwrap();
+        assert!(opts.help);
+        let help = help_text();
+        for spec in OPTIONS {
+            for name in spec.names {
+                assert!(help.contains(name));
+            }
+            assert!(help.contains(spec.help));
+        }
+        assert!(parse_opts(&["--no-such-option".to_string()]).is_err());
+        assert!(parse_opts(&["--pop-state".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_option_syntax() {
+        for args in [
+            vec!["-o", "out"],
+            vec!["-o=out"],
+            vec!["-oout"],
+            vec!["--output", "out"],
+            vec!["--output=out"],
+        ] {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            let opts = parse_opts(&args).unwrap();
+            assert_eq!(opts.output.as_deref(), Some("out"));
+        }
+
+        let opts = parse_opts(&[
+            "-L".to_string(),
+            "a".to_string(),
+            "-Lb".to_string(),
+            "--library-path".to_string(),
+            "c".to_string(),
+            "-library-path=d".to_string(),
+            "-L=e".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(opts.search_dir, vec!["a", "b", "c", "d", "=e"]);
+
+        let opts = parse_opts(&[
+            "--dynamic-linker=/lib/ld.so".to_string(),
+            "--pie".to_string(),
+            "-melf_x86_64".to_string(),
+            "--library=c".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(opts.dynamic_linker.as_deref(), Some("/lib/ld.so"));
+        assert!(opts.pie);
+        assert_eq!(opts.emulation.as_deref(), Some("elf_x86_64"));
+        assert_eq!(opts.obj_file.len(), 1);
+
+        let opts = parse_opts(&[
+            "-dynamic-linker".to_string(),
+            "/lib/ld.so".to_string(),
+            "--no-dynamic-linker".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(opts.dynamic_linker, None);
+        assert!(opts.no_dynamic_linker);
+
+        let opts = parse_opts(&[
+            "--no-dynamic-linker".to_string(),
+            "-dynamic-linker=/lib/ld.so".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(opts.dynamic_linker.as_deref(), Some("/lib/ld.so"));
+        assert!(!opts.no_dynamic_linker);
+
+        let opts = parse_opts(&["-l:libfoo.so.3".to_string()]).unwrap();
+        if let ObjectFileOpt::Library(lib) = &opts.obj_file[0] {
+            assert_eq!(lib.name, "libfoo.so.3");
+            assert!(lib.verbatim);
+        } else {
+            unreachable!();
         }
+
+        // long options starting with 'o' need two dashes
+        let opts = parse_opts(&["-output".to_string()]).unwrap();
+        assert_eq!(opts.output.as_deref(), Some("utput"));
+        assert!(parse_opts(&["--pie=1".to_string()]).is_err());
+        assert!(parse_opts(&["--output".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_just_symbols() {
+        let opts = parse_opts(&[
+            "-R".to_string(),
+            "firmware.elf".to_string(),
+            "--just-symbols=kernel".to_string(),
+            "-R/".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(opts.obj_file.len(), 2);
+        if let ObjectFileOpt::JustSymbols(name) = &opts.obj_file[1] {
+            assert_eq!(name, "kernel");
+        } else {
+            unreachable!();
+        }
+        assert_eq!(opts.rpath, vec!["/"]);
+    }
+
+    #[test]
+    fn test_discard() {
+        let opts = parse_opts(&[]).unwrap();
+        assert_eq!(opts.discard, Discard::None);
+        let opts = parse_opts(&["-X".to_string()]).unwrap();
+        assert_eq!(opts.discard, Discard::Locals);
+        let opts = parse_opts(&["-X".to_string(), "--discard-all".to_string()]).unwrap();
+        assert_eq!(opts.discard, Discard::All);
+        let opts = parse_opts(&["-x".to_string(), "--discard-none".to_string()]).unwrap();
+        assert_eq!(opts.discard, Discard::None);
+    }
+
+    #[test]
+    fn test_strip() {
+        let opts = parse_opts(&["-S".to_string()]).unwrap();
+        assert!(opts.strip_debug);
+        assert!(!opts.strip_all);
+        let opts = parse_opts(&["--strip-all".to_string()]).unwrap();
+        assert!(opts.strip_debug);
+        assert!(opts.strip_all);
+        // -static is not -s
+        let opts = parse_opts(&["-static".to_string()]).unwrap();
+        assert!(!opts.strip_all);
+    }
+
+    #[test]
+    fn test_symbolic() {
+        let opts = parse_opts(&["-Bsymbolic".to_string()]).unwrap();
+        assert_eq!(opts.symbolic, Symbolic::All);
+        let opts = parse_opts(&["--Bsymbolic-functions".to_string()]).unwrap();
+        assert_eq!(opts.symbolic, Symbolic::Functions);
+        let opts = parse_opts(&["-Bsymbolic".to_string(), "-Bno-symbolic".to_string()]).unwrap();
+        assert_eq!(opts.symbolic, Symbolic::None);
+    }
+
+    #[test]
+    fn test_demangle() {
+        let opts = parse_opts(&["--no-demangle".to_string()]).unwrap();
+        assert!(!opts.demangle);
+        let opts = parse_opts(&["--no-demangle".to_string(), "--demangle".to_string()]).unwrap();
+        assert!(opts.demangle);
+    }
+
+    #[test]
+    fn test_relax() {
+        let opts = parse_opts(&[]).unwrap();
+        assert!(opts.relax);
+        let opts = parse_opts(&["--no-relax".to_string()]).unwrap();
+        assert!(!opts.relax);
+        let opts = parse_opts(&["--no-relax".to_string(), "--relax".to_string()]).unwrap();
+        assert!(opts.relax);
+    }
+
+    #[test]
+    fn test_require_defined() {
+        let opts = parse_opts(&[
+            "--require-defined=foo".to_string(),
+            "--require-defined".to_string(),
+            "bar".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(opts.require_defined, vec!["foo", "bar"]);
+    }
+
+    #[test]
+    fn test_orphan_handling() {
+        let opts = parse_opts(&[]).unwrap();
+        assert_eq!(opts.orphan_handling, OrphanHandling::Place);
+        let opts = parse_opts(&["--orphan-handling=warn".to_string()]).unwrap();
+        assert_eq!(opts.orphan_handling, OrphanHandling::Warn);
+        let opts = parse_opts(&["--orphan-handling".to_string(), "discard".to_string()]).unwrap();
+        assert_eq!(opts.orphan_handling, OrphanHandling::Discard);
+        assert!(parse_opts(&["--orphan-handling=keep".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_section_start() {
+        let opts = parse_opts(&[
+            "-Ttext=0x10000".to_string(),
+            "-Tdata".to_string(),
+            "20000".to_string(),
+            "--Tbss=0X30000".to_string(),
+            "--section-start=.mydata=40000".to_string(),
+            "--section-start".to_string(),
+            ".text=0x1000".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(
+            opts.section_start,
+            BTreeMap::from([
+                (".text".to_string(), 0x1000),
+                (".data".to_string(), 0x20000),
+                (".bss".to_string(), 0x30000),
+                (".mydata".to_string(), 0x40000),
+            ])
+        );
+        assert!(parse_opts(&["-Ttext=main".to_string()]).is_err());
+
+        let opts = parse_opts(&[
+            "-Tfirmware.ld".to_string(),
+            "-T".to_string(),
+            "memory.ld".to_string(),
+            "--script=sections.ld".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(
+            opts.scripts,
+            vec!["firmware.ld", "memory.ld", "sections.ld"]
+        );
+        assert!(opts.section_start.is_empty());
+        assert!(parse_opts(&["--section-start=.text".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_build_id() {
+        let opts = parse_opts(&[]).unwrap();
+        assert_eq!(opts.build_id, None);
+
+        let opts = parse_opts(&["--build-id".to_string()]).unwrap();
+        assert_eq!(opts.build_id, Some(BuildId::Sha1));
+
+        let opts = parse_opts(&["--build-id=md5".to_string()]).unwrap();
+        assert_eq!(opts.build_id, Some(BuildId::Md5));
+
+        let opts = parse_opts(&["-build-id=uuid".to_string()]).unwrap();
+        assert_eq!(opts.build_id, Some(BuildId::Uuid));
+
+        let opts = parse_opts(&["--build-id=0x0123abCD".to_string()]).unwrap();
+        assert_eq!(
+            opts.build_id,
+            Some(BuildId::Hex(vec![0x01, 0x23, 0xab, 0xcd]))
+        );
+
+        let opts = parse_opts(&["--build-id".to_string(), "--build-id=none".to_string()]).unwrap();
+        assert_eq!(opts.build_id, None);
+
+        assert!(parse_opts(&["--build-id=0x123".to_string()]).is_err());
+        assert!(parse_opts(&["--build-id=0xzz".to_string()]).is_err());
+        assert!(parse_opts(&["--build-id=crc".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_gc_sections() {
+        let opts = parse_opts(&[]).unwrap();
+        assert!(!opts.gc_sections);
+        assert!(!opts.print_gc_sections);
+        assert!(opts.undefined.is_empty());
+
+        let opts = parse_opts(&[
+            "--gc-sections".to_string(),
+            "--print-gc-sections".to_string(),
+            "-u".to_string(),
+            "foo".to_string(),
+            "--undefined=bar".to_string(),
+        ])
+        .unwrap();
+        assert!(opts.gc_sections);
+        assert!(opts.print_gc_sections);
+        assert_eq!(opts.undefined, vec!["foo", "bar"]);
+
+        let opts = parse_opts(&[
+            "--gc-sections".to_string(),
+            "--print-gc-sections".to_string(),
+            "--no-gc-sections".to_string(),
+            "--no-print-gc-sections".to_string(),
+        ])
+        .unwrap();
+        assert!(!opts.gc_sections);
+        assert!(!opts.print_gc_sections);
+    }
+
+    #[test]
+    fn test_symbol_ordering_file() {
+        let opts = parse_opts(&[]).unwrap();
+        assert_eq!(opts.symbol_ordering_file, None);
+
+        let opts = parse_opts(&["--symbol-ordering-file=order.txt".to_string()]).unwrap();
+        assert_eq!(opts.symbol_ordering_file.as_deref(), Some("order.txt"));
+
+        let opts = parse_opts(&[
+            "--symbol-ordering-file".to_string(),
+            "order.txt".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(opts.symbol_ordering_file.as_deref(), Some("order.txt"));
     }
 }
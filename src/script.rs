@@ -0,0 +1,929 @@
+use anyhow::{anyhow, bail};
+
+/// MEMORY region
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub name: String,
+    /// attributes like rwx, those after '!' are negated
+    pub attributes: String,
+    pub origin: u64,
+    pub length: u64,
+}
+
+/// input section description: sections of matching files and names
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputSectionDescription {
+    pub file_pattern: String,
+    pub section_patterns: Vec<String>,
+    /// KEEP(...), not removed by --gc-sections
+    pub keep: bool,
+}
+
+/// INSERT BEFORE or AFTER an output section of the default linker script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Insert {
+    Before(String),
+    After(String),
+}
+
+/// output section description in SECTIONS
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutputSectionDescription {
+    pub name: String,
+    /// virtual address before the colon
+    pub address: Option<Expr>,
+    /// AT(lma)
+    pub load_address: Option<Expr>,
+    pub inputs: Vec<InputSectionDescription>,
+    /// >region
+    pub region: Option<String>,
+    /// AT>region
+    pub load_region: Option<String>,
+    /// INSERT following SECTIONS: augments the default linker script
+    pub insert: Option<Insert>,
+}
+
+/// where a symbol assignment appears in a linker script
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignmentLocation {
+    /// outside SECTIONS
+    Outside,
+    /// in SECTIONS before the output section of the index
+    Before(usize),
+    /// in the output section of the index, before its inputs
+    Start(usize),
+    /// in the output section of the index, after some of its inputs
+    End(usize),
+}
+
+/// symbol = expr, PROVIDE(symbol = expr) or PROVIDE_HIDDEN(symbol = expr),
+/// symbol . assigns the location counter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assignment {
+    pub symbol: String,
+    pub expr: Expr,
+    /// PROVIDE: only defined if referenced but not defined by objects
+    pub provide: bool,
+    /// PROVIDE_HIDDEN and HIDDEN: not exported from the output
+    pub hidden: bool,
+    pub location: AssignmentLocation,
+}
+
+/// file in INPUT or GROUP, -lname for libraries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputFile {
+    pub name: String,
+    /// in AS_NEEDED(...)
+    pub as_needed: bool,
+}
+
+/// INPUT(files) or GROUP(files)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputCommand {
+    pub files: Vec<InputFile>,
+    /// GROUP: archives are searched repeatedly like --start-group
+    pub group: bool,
+}
+
+/// parsed linker script
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    pub memory: Vec<MemoryRegion>,
+    pub sections: Vec<OutputSectionDescription>,
+    /// symbol assignments in order
+    pub assignments: Vec<Assignment>,
+    /// INPUT and GROUP commands
+    pub inputs: Vec<InputCommand>,
+}
+
+/// expression of linker script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Number(u64),
+    /// symbol, or . for the location counter
+    Symbol(String),
+    /// -, ~ or !
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    /// builtin function, e.g. ALIGN(8) or ORIGIN(FLASH)
+    Call(String, Vec<Expr>),
+}
+
+/// values that expressions refer to
+pub trait Context {
+    /// value of a symbol, or . for the location counter
+    fn symbol(&self, name: &str) -> anyhow::Result<u64>;
+    /// functions taking a name, e.g. ORIGIN(region) and LENGTH(region)
+    fn function(&self, function: &str, name: &str) -> anyhow::Result<u64>;
+}
+
+/// binary operators and their precedence, higher binds tighter
+const BINARY_OPERATORS: &[(&str, u32)] = &[
+    ("|", 1),
+    ("&", 2),
+    ("<<", 3),
+    (">>", 3),
+    ("+", 4),
+    ("-", 4),
+    ("*", 5),
+    ("/", 5),
+    ("%", 5),
+];
+
+/// operators of assignments, compound ones apply the binary operator
+const ASSIGNMENT_OPERATORS: &[&str] = &["<<=", ">>=", "+=", "-=", "*=", "/=", "&=", "|=", "="];
+
+impl Expr {
+    pub fn eval(&self, context: &dyn Context) -> anyhow::Result<u64> {
+        Ok(match self {
+            Expr::Number(value) => *value,
+            Expr::Symbol(name) => context.symbol(name)?,
+            Expr::Unary(op, expr) => {
+                let value = expr.eval(context)?;
+                match *op {
+                    "-" => value.wrapping_neg(),
+                    "~" => !value,
+                    _ => (value == 0) as u64,
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(context)?, rhs.eval(context)?);
+                match *op {
+                    "|" => lhs | rhs,
+                    "&" => lhs & rhs,
+                    "<<" => lhs.wrapping_shl(rhs as u32),
+                    ">>" => lhs.wrapping_shr(rhs as u32),
+                    "+" => lhs.wrapping_add(rhs),
+                    "-" => lhs.wrapping_sub(rhs),
+                    "*" => lhs.wrapping_mul(rhs),
+                    "/" => lhs.checked_div(rhs).ok_or(anyhow!("Division by zero"))?,
+                    _ => lhs.checked_rem(rhs).ok_or(anyhow!("Division by zero"))?,
+                }
+            }
+            Expr::Call(function, args) => match (function.as_str(), args.as_slice()) {
+                ("ALIGN", [align]) => align_up(context.symbol(".")?, align.eval(context)?),
+                ("ALIGN", [value, align]) => align_up(value.eval(context)?, align.eval(context)?),
+                ("MAX", [lhs, rhs]) => lhs.eval(context)?.max(rhs.eval(context)?),
+                ("MIN", [lhs, rhs]) => lhs.eval(context)?.min(rhs.eval(context)?),
+                // all values are absolute addresses
+                ("ABSOLUTE", [value]) => value.eval(context)?,
+                (_, [Expr::Symbol(name)]) => context.function(function, name)?,
+                _ => bail!("Unsupported function {} in linker script", function),
+            },
+        })
+    }
+}
+
+/// round up to a multiple of align, unless it is zero
+fn align_up(value: u64, align: u64) -> u64 {
+    if align == 0 {
+        value
+    } else {
+        value.next_multiple_of(align)
+    }
+}
+
+/// parse number in decimal, hexadecimal with 0x prefix or octal with 0
+/// prefix, optionally multiplied by K or M suffix
+fn parse_number(s: &str) -> anyhow::Result<u64> {
+    let (digits, multiplier) = match s.strip_suffix(['K', 'k']) {
+        Some(digits) => (digits, 1024),
+        None => match s.strip_suffix(['M', 'm']) {
+            Some(digits) => (digits, 1024 * 1024),
+            None => (s, 1),
+        },
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        u64::from_str_radix(&digits[1..], 8)
+    } else {
+        digits.parse::<u64>()
+    };
+    value
+        .map(|value| value * multiplier)
+        .map_err(|err| anyhow!("Invalid number {s}: {err}"))
+}
+
+/// ORIGIN(region) and LENGTH(region)
+pub fn memory_function(memory: &[MemoryRegion], function: &str, name: &str) -> anyhow::Result<u64> {
+    let region = memory
+        .iter()
+        .find(|region| region.name == name)
+        .ok_or(anyhow!("Unknown memory region {}", name))?;
+    match function {
+        "ORIGIN" => Ok(region.origin),
+        "LENGTH" => Ok(region.length),
+        _ => bail!("Unsupported function {} in linker script", function),
+    }
+}
+
+/// values while parsing MEMORY: origin and length of earlier regions
+struct MemoryContext<'a>(&'a [MemoryRegion]);
+
+impl Context for MemoryContext<'_> {
+    fn symbol(&self, name: &str) -> anyhow::Result<u64> {
+        bail!("Symbol {} is not allowed in MEMORY", name)
+    }
+
+    fn function(&self, function: &str, name: &str) -> anyhow::Result<u64> {
+        memory_function(self.0, function, name)
+    }
+}
+
+/// match name against a wildcard pattern with * and ?
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    match pattern.chars().next() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len())
+            .filter(|i| name.is_char_boundary(*i))
+            .any(|i| glob_match(&pattern[1..], &name[i..])),
+        Some(c) => name.chars().next().is_some_and(|first| {
+            (c == '?' || c == first)
+                && glob_match(&pattern[c.len_utf8()..], &name[first.len_utf8()..])
+        }),
+    }
+}
+
+impl Script {
+    /// Output section description matching an input section, and whether
+    /// the input section is kept by KEEP
+    pub fn find_output_section(
+        &self,
+        file_name: &str,
+        section_name: &str,
+    ) -> Option<(usize, bool)> {
+        let base_name = std::path::Path::new(file_name)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(file_name);
+        self.sections
+            .iter()
+            .enumerate()
+            .find_map(|(index, section)| {
+                section
+                    .inputs
+                    .iter()
+                    .find(|input| {
+                        (glob_match(&input.file_pattern, file_name)
+                            || glob_match(&input.file_pattern, base_name))
+                            && input
+                                .section_patterns
+                                .iter()
+                                .any(|pattern| glob_match(pattern, section_name))
+                    })
+                    .map(|input| (index, input.keep))
+            })
+    }
+
+    /// Append commands of another script
+    pub fn extend(&mut self, other: Script) {
+        let base = self.sections.len();
+        self.memory.extend(other.memory);
+        self.sections.extend(other.sections);
+        self.inputs.extend(other.inputs);
+        self.assignments
+            .extend(other.assignments.into_iter().map(|mut assignment| {
+                assignment.location = match assignment.location {
+                    AssignmentLocation::Outside => AssignmentLocation::Outside,
+                    AssignmentLocation::Before(index) => AssignmentLocation::Before(base + index),
+                    AssignmentLocation::Start(index) => AssignmentLocation::Start(base + index),
+                    AssignmentLocation::End(index) => AssignmentLocation::End(base + index),
+                };
+                assignment
+            }));
+    }
+}
+
+/// tokenizer and parser of linker scripts
+struct Parser<'a> {
+    file_name: &'a str,
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: String) -> anyhow::Error {
+        let line = self.input[..self.pos].matches('\n').count() + 1;
+        anyhow!("{}:{}: {}", self.file_name, line, message)
+    }
+
+    /// skip whitespace and comments
+    fn skip(&mut self) -> anyhow::Result<()> {
+        loop {
+            let rest = &self.input[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if !trimmed.starts_with("/*") {
+                return Ok(());
+            }
+            let Some(end) = trimmed.find("*/") else {
+                return Err(self.error("unterminated comment".to_string()));
+            };
+            self.pos += end + 2;
+        }
+    }
+
+    /// next token without consuming it, empty at the end of input; names
+    /// in expressions stop at operators, other names may contain wildcards
+    /// and dashes like file and section names
+    fn peek(&mut self, expression: bool) -> anyhow::Result<&'a str> {
+        self.skip()?;
+        let rest = &self.input[self.pos..];
+        let is_name = |c: char| {
+            c.is_ascii_alphanumeric()
+                || "_.$".contains(c)
+                || (!expression && "/\\*?[]-~!^".contains(c))
+        };
+        let len = rest.find(|c: char| !is_name(c)).unwrap_or(rest.len());
+        if len > 0 {
+            return Ok(&rest[..len]);
+        }
+        if let Some(op) = ASSIGNMENT_OPERATORS
+            .iter()
+            .chain(&["<<", ">>"])
+            .find(|op| rest.starts_with(*op))
+        {
+            return Ok(&rest[..op.len()]);
+        }
+        Ok(&rest[..rest.chars().next().map_or(0, |c| c.len_utf8())])
+    }
+
+    fn next(&mut self, expression: bool) -> anyhow::Result<&'a str> {
+        let token = self.peek(expression)?;
+        self.pos += token.len();
+        Ok(token)
+    }
+
+    /// file name in INPUT and GROUP, which may contain any character but
+    /// parentheses, commas and whitespace
+    fn next_file_name(&mut self) -> anyhow::Result<&'a str> {
+        self.skip()?;
+        let rest = &self.input[self.pos..];
+        let len = rest
+            .find(|c: char| c.is_whitespace() || "(),".contains(c))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn expect(&mut self, expected: &str, expression: bool) -> anyhow::Result<()> {
+        let token = self.next(expression)?;
+        if token != expected {
+            return Err(self.error(format!("expected {:?}, found {:?}", expected, token)));
+        }
+        Ok(())
+    }
+
+    fn parse_expr(&mut self) -> anyhow::Result<Expr> {
+        self.parse_binary(0)
+    }
+
+    /// binary operators of at least the given precedence, left associative
+    fn parse_binary(&mut self, min_precedence: u32) -> anyhow::Result<Expr> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            let token = self.peek(true)?;
+            let Some(&(op, precedence)) = BINARY_OPERATORS
+                .iter()
+                .find(|(op, precedence)| *op == token && *precedence >= min_precedence)
+            else {
+                return Ok(lhs);
+            };
+            self.next(true)?;
+            let rhs = self.parse_binary(precedence + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn parse_unary(&mut self) -> anyhow::Result<Expr> {
+        let token = self.next(true)?;
+        Ok(match token {
+            "-" => Expr::Unary("-", Box::new(self.parse_unary()?)),
+            "~" => Expr::Unary("~", Box::new(self.parse_unary()?)),
+            "!" => Expr::Unary("!", Box::new(self.parse_unary()?)),
+            "(" => {
+                let expr = self.parse_expr()?;
+                self.expect(")", true)?;
+                expr
+            }
+            _ if token.starts_with(|c: char| c.is_ascii_digit()) => {
+                Expr::Number(parse_number(token).map_err(|err| self.error(err.to_string()))?)
+            }
+            _ if token.starts_with(|c: char| c.is_ascii_alphabetic() || "_.$".contains(c)) => {
+                if self.peek(true)? != "(" {
+                    return Ok(Expr::Symbol(token.to_string()));
+                }
+                self.next(true)?;
+                let mut args = vec![];
+                while self.peek(true)? != ")" {
+                    args.push(self.parse_expr()?);
+                    if self.peek(true)? == "," {
+                        self.next(true)?;
+                    }
+                }
+                self.next(true)?;
+                Expr::Call(token.to_string(), args)
+            }
+            _ => return Err(self.error(format!("unexpected {:?} in expression", token))),
+        })
+    }
+
+    /// MEMORY { name (attributes) : ORIGIN = expr, LENGTH = expr ... }
+    fn parse_memory(&mut self, memory: &mut Vec<MemoryRegion>) -> anyhow::Result<()> {
+        self.expect("{", false)?;
+        loop {
+            let name = self.next(false)?;
+            if name == "}" {
+                return Ok(());
+            }
+            let mut attributes = String::new();
+            if self.peek(false)? == "(" {
+                self.next(false)?;
+                loop {
+                    match self.next(false)? {
+                        ")" => break,
+                        "" => return Err(self.error("unexpected end of file".to_string())),
+                        token => attributes += token,
+                    }
+                }
+            }
+            self.expect(":", false)?;
+            let mut values = [0; 2];
+            for (index, keywords) in [["ORIGIN", "org", "o"], ["LENGTH", "len", "l"]]
+                .iter()
+                .enumerate()
+            {
+                if index > 0 {
+                    self.expect(",", true)?;
+                }
+                let keyword = self.next(false)?;
+                if !keywords.contains(&keyword) {
+                    return Err(
+                        self.error(format!("expected {:?}, found {:?}", keywords[0], keyword))
+                    );
+                }
+                self.expect("=", true)?;
+                values[index] = self
+                    .parse_expr()?
+                    .eval(&MemoryContext(memory))
+                    .map_err(|err| self.error(err.to_string()))?;
+            }
+            memory.push(MemoryRegion {
+                name: name.to_string(),
+                attributes,
+                origin: values[0],
+                length: values[1],
+            });
+        }
+    }
+
+    /// assignment after its symbol or PROVIDE-like keyword:
+    /// symbol op expr ; or ( symbol = expr )
+    fn parse_assignment(
+        &mut self,
+        token: &str,
+        location: AssignmentLocation,
+    ) -> anyhow::Result<Assignment> {
+        let (provide, hidden) = match token {
+            "PROVIDE" => (true, false),
+            "PROVIDE_HIDDEN" => (true, true),
+            "HIDDEN" => (false, true),
+            _ => (false, false),
+        };
+        let wrapped = provide || hidden;
+        let symbol = if wrapped {
+            self.expect("(", false)?;
+            self.next(false)?
+        } else {
+            token
+        };
+        let op = self.next(true)?;
+        if !ASSIGNMENT_OPERATORS.contains(&op) {
+            return Err(self.error(format!("expected \"=\", found {:?}", op)));
+        }
+        let mut expr = self.parse_expr()?;
+        if op != "=" {
+            let op = BINARY_OPERATORS
+                .iter()
+                .find(|(binary, _)| *binary == &op[..op.len() - 1])
+                .unwrap()
+                .0;
+            expr = Expr::Binary(
+                op,
+                Box::new(Expr::Symbol(symbol.to_string())),
+                Box::new(expr),
+            );
+        }
+        if wrapped {
+            self.expect(")", true)?;
+        }
+        if !wrapped || self.peek(true)? == ";" {
+            self.expect(";", true)?;
+        }
+        Ok(Assignment {
+            symbol: symbol.to_string(),
+            expr,
+            provide,
+            hidden,
+            location,
+        })
+    }
+
+    /// whether the token starts an assignment
+    fn is_assignment(&mut self, token: &str) -> anyhow::Result<bool> {
+        Ok(matches!(token, "PROVIDE" | "PROVIDE_HIDDEN" | "HIDDEN")
+            || ASSIGNMENT_OPERATORS.contains(&self.peek(true)?))
+    }
+
+    /// SECTIONS { name [address] : [AT(lma)] { inputs } [>region] [AT>region] ... }
+    /// with assignments between and inside output sections
+    fn parse_sections(&mut self, script: &mut Script) -> anyhow::Result<()> {
+        self.expect("{", false)?;
+        loop {
+            let name = self.next(false)?;
+            match name {
+                "}" => return Ok(()),
+                "" => return Err(self.error("unexpected end of file".to_string())),
+                _ => {}
+            }
+            let index = script.sections.len();
+            if self.is_assignment(name)? {
+                let assignment = self.parse_assignment(name, AssignmentLocation::Before(index))?;
+                script.assignments.push(assignment);
+                continue;
+            }
+            let mut section = OutputSectionDescription {
+                name: name.to_string(),
+                ..OutputSectionDescription::default()
+            };
+            if self.peek(false)? != ":" {
+                section.address = Some(self.parse_expr()?);
+            }
+            self.expect(":", false)?;
+            if self.peek(false)? == "AT" {
+                self.next(false)?;
+                self.expect("(", true)?;
+                section.load_address = Some(self.parse_expr()?);
+                self.expect(")", true)?;
+            }
+            self.expect("{", false)?;
+            loop {
+                let token = self.next(false)?;
+                match token {
+                    "}" => break,
+                    ";" => {}
+                    "" => return Err(self.error("unexpected end of file".to_string())),
+                    "KEEP" => {
+                        self.expect("(", false)?;
+                        let file_pattern = self.next(false)?;
+                        section.inputs.push(self.parse_input(file_pattern, true)?);
+                        self.expect(")", false)?;
+                    }
+                    _ if self.is_assignment(token)? => {
+                        let location = if section.inputs.is_empty() {
+                            AssignmentLocation::Start(index)
+                        } else {
+                            AssignmentLocation::End(index)
+                        };
+                        let assignment = self.parse_assignment(token, location)?;
+                        script.assignments.push(assignment);
+                    }
+                    _ => section.inputs.push(self.parse_input(token, false)?),
+                }
+            }
+            loop {
+                match self.peek(false)? {
+                    ">" => {
+                        self.next(false)?;
+                        section.region = Some(self.next(false)?.to_string());
+                    }
+                    "AT" => {
+                        self.next(false)?;
+                        self.expect(">", false)?;
+                        section.load_region = Some(self.next(false)?.to_string());
+                    }
+                    "," => {
+                        self.next(false)?;
+                    }
+                    _ => break,
+                }
+            }
+            script.sections.push(section);
+        }
+    }
+
+    /// ( files ), files may be in AS_NEEDED ( files )
+    fn parse_input_files(
+        &mut self,
+        files: &mut Vec<InputFile>,
+        as_needed: bool,
+    ) -> anyhow::Result<()> {
+        self.expect("(", false)?;
+        loop {
+            match self.next_file_name()? {
+                "" => match self.next(false)? {
+                    ")" => return Ok(()),
+                    "," => {}
+                    "" => return Err(self.error("unexpected end of file".to_string())),
+                    token => return Err(self.error(format!("unexpected {:?}", token))),
+                },
+                "AS_NEEDED" => self.parse_input_files(files, true)?,
+                name => files.push(InputFile {
+                    name: name.to_string(),
+                    as_needed,
+                }),
+            }
+        }
+    }
+
+    /// file pattern ( section patterns )
+    fn parse_input(
+        &mut self,
+        file_pattern: &str,
+        keep: bool,
+    ) -> anyhow::Result<InputSectionDescription> {
+        self.expect("(", false)?;
+        let mut section_patterns = vec![];
+        loop {
+            match self.next(false)? {
+                ")" => break,
+                "," => {}
+                "" => return Err(self.error("unexpected end of file".to_string())),
+                pattern => section_patterns.push(pattern.to_string()),
+            }
+        }
+        Ok(InputSectionDescription {
+            file_pattern: file_pattern.to_string(),
+            section_patterns,
+            keep,
+        })
+    }
+}
+
+/// Parse a linker script, file name is used in errors
+pub fn parse_script(file_name: &str, content: &str) -> anyhow::Result<Script> {
+    let mut parser = Parser {
+        file_name,
+        input: content,
+        pos: 0,
+    };
+    let mut script = Script::default();
+    // sections before this are placed by an earlier INSERT
+    let mut insert_start = 0;
+    loop {
+        match parser.next(false)? {
+            "" => return Ok(script),
+            ";" => {}
+            "INSERT" => {
+                let position = parser.next(false)?;
+                let name = parser.next(false)?.to_string();
+                let insert = match position {
+                    "AFTER" => Insert::After(name),
+                    "BEFORE" => Insert::Before(name),
+                    _ => {
+                        return Err(parser.error(format!(
+                            "expected \"AFTER\" or \"BEFORE\", found {:?}",
+                            position
+                        )))
+                    }
+                };
+                for section in &mut script.sections[insert_start..] {
+                    section.insert = Some(insert.clone());
+                }
+                insert_start = script.sections.len();
+            }
+            "MEMORY" => parser.parse_memory(&mut script.memory)?,
+            "SECTIONS" => parser.parse_sections(&mut script)?,
+            command @ ("INPUT" | "GROUP") => {
+                let mut files = vec![];
+                parser.parse_input_files(&mut files, false)?;
+                script.inputs.push(InputCommand {
+                    files,
+                    group: command == "GROUP",
+                });
+            }
+            // the output is always elf64-x86-64
+            "OUTPUT_ARCH" | "OUTPUT_FORMAT" => {
+                parser.expect("(", false)?;
+                while !matches!(parser.next(false)?, ")" | "") {}
+            }
+            token if parser.is_assignment(token)? => {
+                let assignment = parser.parse_assignment(token, AssignmentLocation::Outside)?;
+                script.assignments.push(assignment);
+            }
+            command => return Err(parser.error(format!("unsupported command {}", command))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_memory() {
+        let script = parse_script(
+            "test.ld",
+            "/* regions */
+            MEMORY {
+                FLASH (rx) : ORIGIN = 0x08000000, LENGTH = 64K
+                RAM (rw!x) : org = ORIGIN(FLASH) + LENGTH(FLASH), len = 2M - 010
+            }",
+        )
+        .unwrap();
+        assert_eq!(
+            script.memory,
+            vec![
+                MemoryRegion {
+                    name: "FLASH".to_string(),
+                    attributes: "rx".to_string(),
+                    origin: 0x08000000,
+                    length: 0x10000,
+                },
+                MemoryRegion {
+                    name: "RAM".to_string(),
+                    attributes: "rw!x".to_string(),
+                    origin: 0x08010000,
+                    length: 0x200000 - 8,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_sections() {
+        let script = parse_script(
+            "test.ld",
+            "OUTPUT_ARCH(i386:x86-64)
+            SECTIONS {
+                .text : { KEEP(*(.init)) *(.text .text.*) }>FLASH
+                .data 0x1000 + 2 * 8 : AT(0x2000) { crt*.o(.data) *(.data*) } > RAM AT > FLASH
+                .note.gnu.build-id : { *(.note.gnu.build-id) }
+            }",
+        )
+        .unwrap();
+        assert_eq!(script.sections.len(), 3);
+        let text = &script.sections[0];
+        assert_eq!(text.name, ".text");
+        assert_eq!(text.region.as_deref(), Some("FLASH"));
+        assert_eq!(text.inputs.len(), 2);
+        assert!(text.inputs[0].keep);
+        assert_eq!(text.inputs[1].section_patterns, vec![".text", ".text.*"]);
+        let data = &script.sections[1];
+        assert_eq!(
+            data.address
+                .as_ref()
+                .unwrap()
+                .eval(&MemoryContext(&[]))
+                .unwrap(),
+            0x1010
+        );
+        assert_eq!(data.load_address, Some(Expr::Number(0x2000)));
+        assert_eq!(data.region.as_deref(), Some("RAM"));
+        assert_eq!(data.load_region.as_deref(), Some("FLASH"));
+        assert_eq!(script.sections[2].name, ".note.gnu.build-id");
+
+        assert_eq!(
+            script.find_output_section("crt1.o", ".data"),
+            Some((1, false))
+        );
+        assert_eq!(
+            script.find_output_section("dir/a.o", ".text.main"),
+            Some((0, false))
+        );
+        assert_eq!(script.find_output_section("a.o", ".init"), Some((0, true)));
+        assert_eq!(script.find_output_section("a.o", ".rodata"), None);
+    }
+
+    #[test]
+    fn test_parse_assignments() {
+        let script = parse_script(
+            "test.ld",
+            "_stack_size = 0x400;
+            SECTIONS {
+                . = 0x10000;
+                .data : {
+                    PROVIDE_HIDDEN(_sdata = .);
+                    *(.data)
+                    . = ALIGN(8);
+                    _edata=.;
+                }
+                _stack_size += 16;
+                PROVIDE(__stack_top = ORIGIN(RAM) + LENGTH(RAM));
+            }
+            HIDDEN(_end = .);",
+        )
+        .unwrap();
+        let symbols: Vec<_> = script
+            .assignments
+            .iter()
+            .map(|assignment| {
+                (
+                    assignment.symbol.as_str(),
+                    assignment.provide,
+                    assignment.hidden,
+                    assignment.location,
+                )
+            })
+            .collect();
+        assert_eq!(
+            symbols,
+            vec![
+                ("_stack_size", false, false, AssignmentLocation::Outside),
+                (".", false, false, AssignmentLocation::Before(0)),
+                ("_sdata", true, true, AssignmentLocation::Start(0)),
+                (".", false, false, AssignmentLocation::End(0)),
+                ("_edata", false, false, AssignmentLocation::End(0)),
+                ("_stack_size", false, false, AssignmentLocation::Before(1)),
+                ("__stack_top", true, false, AssignmentLocation::Before(1)),
+                ("_end", false, true, AssignmentLocation::Outside),
+            ]
+        );
+        assert_eq!(
+            script.assignments[5].expr,
+            Expr::Binary(
+                "+",
+                Box::new(Expr::Symbol("_stack_size".to_string())),
+                Box::new(Expr::Number(16))
+            )
+        );
+        assert_eq!(script.sections[0].inputs.len(), 1);
+
+        let mut other = parse_script("other.ld", "SECTIONS { .bss : { _sbss = .; } }").unwrap();
+        other.extend(script);
+        assert_eq!(other.assignments[2].location, AssignmentLocation::Before(1));
+        assert!(parse_script("test.ld", "PROVIDE(_end = .").is_err());
+    }
+
+    #[test]
+    fn test_parse_inputs() {
+        let script = parse_script(
+            "libc.so",
+            "/* GNU ld script */
+            OUTPUT_FORMAT(elf64-x86-64)
+            GROUP ( /lib/libc.so.6 /usr/lib/libc_nonshared.a  AS_NEEDED ( /lib64/ld-linux-x86-64.so.2 ) )
+            INPUT(-lm,libstdc++.so)",
+        )
+        .unwrap();
+        let file = |name: &str, as_needed| InputFile {
+            name: name.to_string(),
+            as_needed,
+        };
+        assert_eq!(
+            script.inputs,
+            vec![
+                InputCommand {
+                    files: vec![
+                        file("/lib/libc.so.6", false),
+                        file("/usr/lib/libc_nonshared.a", false),
+                        file("/lib64/ld-linux-x86-64.so.2", true),
+                    ],
+                    group: true,
+                },
+                InputCommand {
+                    files: vec![file("-lm", false), file("libstdc++.so", false)],
+                    group: false,
+                },
+            ]
+        );
+        assert!(parse_script("libc.so", "GROUP ( /lib/libc.so.6").is_err());
+    }
+
+    #[test]
+    fn test_parse_insert() {
+        let script = parse_script(
+            "test.ld",
+            "SECTIONS { .mytext : { *(.mytext) } } INSERT AFTER .text;
+            SECTIONS { .mydata : { *(.mydata) } .mybss : { *(.mybss) } }
+            INSERT BEFORE .bss",
+        )
+        .unwrap();
+        let inserts: Vec<_> = script
+            .sections
+            .iter()
+            .map(|section| section.insert.clone())
+            .collect();
+        assert_eq!(
+            inserts,
+            vec![
+                Some(Insert::After(".text".to_string())),
+                Some(Insert::Before(".bss".to_string())),
+                Some(Insert::Before(".bss".to_string())),
+            ]
+        );
+        assert!(parse_script("test.ld", "INSERT INTO .text").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err =
+            parse_script("test.ld", "MEMORY {\n  FLASH : ORIGIN = 1 LENGTH = 2\n}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "test.ld:2: expected \",\", found \"LENGTH\""
+        );
+        assert!(parse_script("test.ld", "SEARCH_DIR(/lib)").is_err());
+        assert!(parse_script("test.ld", "/* unterminated").is_err());
+    }
+}
@@ -0,0 +1,43 @@
+# https://gist.github.com/adrianratnapala/1321776
+    .section .rodata
+message:
+    .string "Hello world!\n"
+
+    .section .text
+    .globl _start
+_start:
+    # apply R_X86_64_IRELATIVE in static executable like libc does
+    lea     __rela_iplt_start(%rip), %rbx
+    lea     __rela_iplt_end(%rip), %r12
+1:
+    cmp     %r12, %rbx
+    jae     2f
+    # call resolver at r_addend, store result at r_offset
+    call    *16(%rbx)
+    mov     (%rbx), %rcx
+    mov     %rax, (%rcx)
+    add     $24, %rbx
+    jmp     1b
+2:
+    call    print
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+    # print is an indirect function
+    .globl print
+    .type print, @gnu_indirect_function
+print:
+    lea     print_impl(%rip), %rax
+    ret
+
+print_impl:
+    # write(1, message, 13)
+    mov     $1, %rdi
+    lea     message(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
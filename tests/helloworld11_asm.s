@@ -0,0 +1,44 @@
+# https://gist.github.com/adrianratnapala/1321776
+    .section .data
+message:
+    .ascii "Hello world!\n"
+
+    .section .bss
+buffer:
+    .zero 13
+
+    .section .text
+    .globl _start
+_start:
+    # copy message to the beginning of bss
+    lea     __bss_start(%rip), %rdi
+    lea     message(%rip), %rsi
+    mov     $13, %rcx
+    rep movsb
+
+    # ELF header is mapped at __ehdr_start
+    mov     __ehdr_start(%rip), %eax
+    cmp     $0x464c457f, %eax
+    jne     1f
+    lea     __executable_start(%rip), %rax
+    lea     __ehdr_start(%rip), %rbx
+    cmp     %rax, %rbx
+    jne     1f
+
+    # bss lies below the end of the image
+    lea     _end(%rip), %rax
+    cmp     %rdi, %rax
+    jb      1f
+
+    # write(1, __bss_start, 13)
+    mov     $1, %rdi
+    lea     __bss_start(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+1:
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,19 @@
+    # section named as a C identifier
+    .section hello_msg, "a"
+    .ascii "Hello world!\n"
+
+    .section .text
+    .globl _start
+_start:
+    # write(1, __start_hello_msg, __stop_hello_msg - __start_hello_msg)
+    mov     $1, %rdi
+    lea     __start_hello_msg(%rip), %rsi
+    lea     __stop_hello_msg(%rip), %rdx
+    sub     %rsi, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,20 @@
+    .section .rodata
+message:
+    .string "Hello interposer!\n"
+
+    .section .text
+    .globl _start
+_start:
+    call print
+    call exit
+
+    # interposes write_hello of libhelloworld7_asm_library
+    .globl write_hello
+write_hello:
+    # write(1, message, 18)
+    mov     $1, %rdi
+    lea     message(%rip), %rsi
+    mov     $18, %rdx
+    mov     $1, %rax
+    syscall
+    ret
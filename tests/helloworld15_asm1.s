@@ -0,0 +1,19 @@
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+
+    .section .text
+    .globl _start
+_start:
+    # write(1, hello, hello_len)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    movabs  $hello_len, %rdx
+    movabs  $SYS_write, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    movabs  $SYS_exit, %rax
+    syscall
@@ -0,0 +1,9 @@
+# absolute symbols defined with SHN_ABS
+    .globl hello_len
+    .set hello_len, 13
+
+    .globl SYS_write
+    .set SYS_write, 1
+
+    .globl SYS_exit
+    .set SYS_exit, 60
@@ -0,0 +1,36 @@
+# thread-local variables in .tdata and .tbss
+    .section .tdata,"awT",@progbits
+    .globl counter
+    .type counter, @object
+    .size counter, 8
+    .p2align 3
+counter:
+    .quad 42
+
+    .section .tbss,"awT",@nobits
+    .globl buffer
+    .type buffer, @object
+    .size buffer, 16
+    .p2align 4
+buffer:
+    .zero 16
+
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+
+    .section .text
+    .globl _start
+_start:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     $hello, %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
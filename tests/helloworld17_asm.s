@@ -0,0 +1,6 @@
+# references to mangled C++ and Rust symbols, left undefined
+    .section .text
+    .globl _start
+_start:
+    call _ZN5hello5printEv
+    call _RNvCs15kBYyAo9fc_5hello4exit
@@ -0,0 +1,39 @@
+# undefined weak symbols are zero, unless defined at runtime
+    .weak missing_function
+    .weak missing_data
+
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+    .section .data
+pointer:
+    .quad missing_data
+
+
+    .section .text
+    .globl _start
+_start:
+    # call missing_function only if defined
+    mov     missing_function@GOTPCREL(%rip), %rax
+    test    %rax, %rax
+    jz      1f
+    call    missing_function@PLT
+1:
+    # pointer should be null
+    mov     pointer(%rip), %rax
+    test    %rax, %rax
+    jnz     2f
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+2:
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
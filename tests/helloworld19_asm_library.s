@@ -0,0 +1,43 @@
+# protected symbols are bound locally in shared library
+    .section .rodata
+    .globl message
+    .protected message
+    .type message, @object
+    .size message, 13
+message:
+    .string "Hello world!\n"
+
+    .section .data
+    .globl message_pointer
+    .type message_pointer, @object
+    .size message_pointer, 8
+message_pointer:
+    .quad message
+
+    .globl pointer_pointer
+    .type pointer_pointer, @object
+    .size pointer_pointer, 8
+pointer_pointer:
+    .quad message_pointer
+
+
+    .section .text
+    .globl print
+    .protected print
+    .type print, @function
+print:
+    # write(1, message, 13)
+    mov     $1, %rdi
+    lea     message(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+
+    .globl exit
+    .type exit, @function
+exit:
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
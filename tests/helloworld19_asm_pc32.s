@@ -0,0 +1,6 @@
+# pc-relative reference to preemptible symbol in shared library
+    .section .text
+    .globl get_pointer
+get_pointer:
+    lea     message_pointer(%rip), %rax
+    ret
@@ -0,0 +1,29 @@
+# common symbols, merged with those of helloworld20_asm2.s
+    .comm buffer, 16, 8
+    .comm counter, 8, 8
+
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+
+    .section .text
+    .globl _start
+_start:
+    # copy hello to buffer
+    mov     $hello, %rsi
+    mov     $buffer, %rdi
+    mov     $13, %rcx
+    rep movsb
+
+    # write(1, buffer, counter)
+    mov     $1, %rdi
+    mov     $buffer, %rsi
+    mov     counter, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,9 @@
+# a larger common symbol, and a definition overriding a common symbol
+    .comm buffer, 64, 32
+
+    .data
+    .globl counter
+    .type counter, @object
+    .size counter, 8
+counter:
+    .quad 13
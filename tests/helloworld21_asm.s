@@ -0,0 +1,34 @@
+# sections like those of -ffunction-sections -fdata-sections
+    .section .rodata.hello,"a"
+hello:
+    .string "Hello world!\n"
+
+    .section .data.length,"aw"
+    .p2align 3
+length:
+    .quad 13
+
+    .section .bss.buffer,"aw",@nobits
+    .p2align 4
+buffer:
+    .zero 16
+
+
+    .section .text.print,"ax"
+print:
+    # write(1, hello, length)
+    mov     $1, %rdi
+    mov     $hello, %rsi
+    mov     length, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+
+    .section .text._start,"ax"
+    .globl _start
+_start:
+    call    print
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
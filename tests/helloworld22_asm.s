@@ -0,0 +1,43 @@
+# unused sections are removed by --gc-sections
+    .section .rodata.hello,"a"
+hello:
+    .string "Hello world!\n"
+
+    .section .rodata.unused,"a"
+unused_message:
+    .string "This is not hello world\n"
+
+    .section .text.unused,"ax"
+    .globl unused
+unused:
+    mov     $unused_message, %rsi
+    ret
+
+    # SHF_GNU_RETAIN
+    .section .text.retained,"axR"
+retained:
+    ret
+
+    # kept for __start_hello_length
+    .section hello_length,"a"
+    .quad 13
+
+
+    .section .text.print,"ax"
+print:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     $hello, %rsi
+    mov     __start_hello_length, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+
+    .section .text._start,"ax"
+    .globl _start
+_start:
+    call    print
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
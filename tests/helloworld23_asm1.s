@@ -0,0 +1,18 @@
+# constants in SHF_MERGE sections, deduplicated with helloworld23_asm2.s
+    .section .rodata.cst8,"aM",@progbits,8
+    .p2align 3
+.LC0:
+    .quad 13
+
+
+    .section .text
+    .globl _start
+_start:
+    # print(13)
+    mov     .LC0(%rip), %rdx
+    call    print
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,12 @@
+# call frame information merged with helloworld24_asm2.s
+    .section .text._start,"ax"
+    .globl _start
+_start:
+    .cfi_startproc
+    call    print
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+    .cfi_endproc
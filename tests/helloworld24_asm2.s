@@ -0,0 +1,26 @@
+# the CIE is the same as that of helloworld24_asm1.s
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+
+    .section .text.print,"ax"
+    .globl print
+print:
+    .cfi_startproc
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     $hello, %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+    .cfi_endproc
+
+    # removed by --gc-sections along with its FDE
+    .section .text.unused,"ax"
+    .globl unused
+unused:
+    .cfi_startproc
+    ret
+    .cfi_endproc
@@ -0,0 +1,76 @@
+# constructors in .init_array and .ctors, run in priority order by _start
+    .section .init_array.50,"aw"
+    .p2align 3
+    .quad   print1
+
+    .section .ctors.65435,"aw"
+    .p2align 3
+    .quad   print2
+
+    .section .init_array,"aw"
+    .p2align 3
+    .quad   print3
+
+    # .ctors runs backwards
+    .section .ctors,"aw"
+    .p2align 3
+    .quad   print5
+    .quad   print4
+
+    .section .fini_array,"aw"
+    .p2align 3
+    .quad   print5
+
+
+    .section .rodata
+digits:
+    .ascii  "12345\n"
+
+
+    .section .text
+    .globl _start
+_start:
+    # for (f = __init_array_start; f != __init_array_end; f++) (*f)()
+    lea     __init_array_start(%rip), %rbx
+    lea     __init_array_end(%rip), %r12
+1:
+    cmp     %r12, %rbx
+    je      2f
+    call    *(%rbx)
+    add     $8, %rbx
+    jmp     1b
+2:
+    # write(1, digits + 5, 1)
+    mov     $5, %rdi
+    call    print
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+print1:
+    mov     $0, %rdi
+    jmp     print
+print2:
+    mov     $1, %rdi
+    jmp     print
+print3:
+    mov     $2, %rdi
+    jmp     print
+print4:
+    mov     $3, %rdi
+    jmp     print
+print5:
+    mov     $4, %rdi
+    jmp     print
+
+    # print(index)
+print:
+    lea     digits(%rip), %rsi
+    add     %rdi, %rsi
+    mov     $1, %rdi
+    mov     $1, %rdx
+    mov     $1, %rax
+    syscall
+    ret
@@ -0,0 +1,61 @@
+# .preinit_array runs before .init_array
+    .section .preinit_array,"aw"
+    .p2align 3
+    .quad   print1
+
+    .section .init_array,"aw"
+    .p2align 3
+    .quad   print2
+
+
+    .section .rodata
+digits:
+    .ascii  "12\n"
+
+
+    .section .text
+    .globl _start
+_start:
+    # for (f = __preinit_array_start; f != __init_array_end; f++) (*f)()
+    # the dynamic linker has already run .preinit_array of dynamic executables
+    lea     __preinit_array_start(%rip), %rbx
+    lea     __preinit_array_end(%rip), %r12
+    call    run
+    lea     __init_array_start(%rip), %rbx
+    lea     __init_array_end(%rip), %r12
+    call    run
+
+    # write(1, digits + 2, 1)
+    mov     $2, %rdi
+    call    print
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+run:
+    cmp     %r12, %rbx
+    je      1f
+    call    *(%rbx)
+    add     $8, %rbx
+    jmp     run
+1:
+    ret
+
+print1:
+    mov     $0, %rdi
+    jmp     print
+print2:
+    mov     $1, %rdi
+    jmp     print
+
+    # print(index)
+print:
+    lea     digits(%rip), %rsi
+    add     %rdi, %rsi
+    mov     $1, %rdi
+    mov     $1, %rdx
+    mov     $1, %rax
+    syscall
+    ret
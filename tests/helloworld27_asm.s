@@ -0,0 +1,39 @@
+# notes copied to the output and described by PT_NOTE
+    .section .note.ABI-tag,"a",@note
+    .p2align 2
+    .long   4               # namesz
+    .long   16              # descsz
+    .long   1               # NT_GNU_ABI_TAG
+    .asciz  "GNU"
+    .long   0, 3, 2, 0      # Linux 3.2.0
+
+    .section .note.package,"a",@note
+    .p2align 2
+    .long   4               # namesz
+    .long   8               # descsz
+    .long   0xcafe1a7e      # NT_FDO_PACKAGING_METADATA
+    .asciz  "FDO"
+    .asciz  "{\"a\":1}"
+
+    .section .note.GNU-stack,"",@progbits
+
+
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+
+    .section .text
+    .globl _start
+_start:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,28 @@
+# GNU properties merged with helloworld28_asm2.s
+    .section .note.gnu.property,"a",@note
+    .p2align 3
+    .long   4               # namesz
+    .long   32              # descsz
+    .long   5               # NT_GNU_PROPERTY_TYPE_0
+    .asciz  "GNU"
+    .long   0xc0000002      # GNU_PROPERTY_X86_FEATURE_1_AND
+    .long   4
+    .long   3               # IBT, SHSTK
+    .p2align 3
+    .long   0xc0008002      # GNU_PROPERTY_X86_ISA_1_NEEDED
+    .long   4
+    .long   1               # x86-64-baseline
+    .p2align 3
+
+    .section .note.GNU-stack,"",@progbits
+
+
+    .section .text
+    .globl _start
+_start:
+    call    print
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,34 @@
+# GNU properties merged with helloworld28_asm1.s
+    .section .note.gnu.property,"a",@note
+    .p2align 3
+    .long   4               # namesz
+    .long   32              # descsz
+    .long   5               # NT_GNU_PROPERTY_TYPE_0
+    .asciz  "GNU"
+    .long   0xc0000002      # GNU_PROPERTY_X86_FEATURE_1_AND
+    .long   4
+    .long   1               # IBT
+    .p2align 3
+    .long   0xc0008002      # GNU_PROPERTY_X86_ISA_1_NEEDED
+    .long   4
+    .long   2               # x86-64-v2
+    .p2align 3
+
+    .section .note.GNU-stack,"",@progbits
+
+
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+
+    .section .text
+    .globl print
+print:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
@@ -0,0 +1,7 @@
+# no GNU properties
+    .section .note.GNU-stack,"",@progbits
+
+    .section .text
+    .globl unused
+unused:
+    ret
@@ -0,0 +1,47 @@
+# orphan sections placed after sections with similar flags
+    .section .mytext,"ax",@progbits
+print:
+    # write(1, hello, length)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     length(%rip), %rdx
+    mov     $1, %rax
+    syscall
+    mov     %rax, written(%rip)
+    ret
+
+    .section .myrodata,"a",@progbits
+hello:
+    .string "Hello world!\n"
+
+    .section .mydata,"aw",@progbits
+length:
+    .quad   13
+
+    .section .mybss,"aw",@nobits
+written:
+    .zero   8
+
+    .section .note.GNU-stack,"",@progbits
+
+
+    .section .rodata
+    .quad   0
+
+    .section .data
+    .quad   0
+
+    .section .bss
+    .zero   8
+
+
+    .section .text
+    .globl _start
+_start:
+    call    print
+
+    # _exit(13 - written)
+    mov     $13, %rdi
+    sub     written(%rip), %rdi
+    mov     $60, %rax
+    syscall
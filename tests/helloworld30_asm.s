@@ -0,0 +1,33 @@
+# symbols assigned by linker scripts
+    .section .text
+    .globl _start
+_start:
+    mov     $__stack_top, %rsp
+    call    print
+
+    # exit(0)
+    mov     $60, %rax
+    xor     %rdi, %rdi
+    syscall
+
+print:
+    # write(1, _srodata, _erodata - _srodata)
+    mov     $1, %rdi
+    mov     $_srodata, %rsi
+    mov     $_erodata, %rdx
+    sub     %rsi, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+
+    .section .rodata
+    .ascii  "Hello world!\n"
+
+    .section .data
+    .quad   1
+
+    .section .bss
+    # stack
+    .zero   4096
+
+    .section .note.GNU-stack,"",@progbits
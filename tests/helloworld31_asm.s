@@ -0,0 +1,15 @@
+    .section .rodata
+length:
+    .quad 13
+
+    .section .text
+    .globl _start
+_start:
+    # write(1, hello, 13), hello and length are accessed through .got
+    mov     $1, %rdi
+    mov     hello@GOTPCREL(%rip), %rsi
+    mov     length@GOTPCREL(%rip), %rdx
+    mov     (%rdx), %rdx
+    mov     $1, %rax
+    syscall
+    call    *exit@GOTPCREL(%rip)
@@ -0,0 +1,22 @@
+    .section .rodata
+hello:
+    .ascii "Hello world!\n"
+
+    .section .data
+message:
+    # relocated by R_X86_64_RELATIVE in position independent output
+    .quad hello
+exit_address:
+    # bound to the shared library by R_X86_64_64
+    .quad exit
+
+    .section .text
+    .globl _start
+_start:
+    # write(1, message, 13)
+    mov     $1, %rdi
+    mov     message(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    call    *exit_address(%rip)
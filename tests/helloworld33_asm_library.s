@@ -0,0 +1,34 @@
+    .section .data
+    .globl hello
+    .type hello, @object
+    .size hello, 13
+hello:
+    .ascii "Hello world!\n"
+init_message:
+    .ascii "Init\n"
+
+    .section .init
+    .globl _init
+    .type _init, @function
+_init:
+    # write(1, init_message, 5), called by the dynamic loader
+    mov     $1, %rdi
+    lea     init_message(%rip), %rsi
+    mov     $5, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+
+    .section .text
+    .globl my_fini
+    .type my_fini, @function
+my_fini:
+    ret
+
+    .globl exit
+    .type exit, @function
+exit:
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,14 @@
+# libc found in ld.so cache without -L
+    .section .rodata
+hello:
+    .string "Hello world!"
+
+    .section .text
+    .globl _start
+_start:
+    # puts(hello)
+    lea     hello(%rip), %rdi
+    call    puts@PLT
+    # exit(0)
+    xor     %edi, %edi
+    call    exit@PLT
@@ -0,0 +1,15 @@
+    .section .data
+    .globl message
+    .type message, @object
+    .size message, 13
+message:
+    .ascii "Hello world!\n"
+
+    .section .text
+    .globl _start
+_start:
+    call    print@PLT
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,18 @@
+# exit has a .got entry, so calls go through .plt.got
+    .section .text
+    .globl _start
+_start:
+    # call exit only if defined
+    mov     exit@GOTPCREL(%rip), %rax
+    test    %rax, %rax
+    jz      1f
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     hello@GOTPCREL(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    call    exit@PLT
+1:
+    ud2
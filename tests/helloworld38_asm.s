@@ -0,0 +1,25 @@
+# IBT and SHSTK compatible, calls go through .plt.sec
+    .section .note.gnu.property,"a"
+    .p2align 3
+    .long   4
+    .long   16
+    # NT_GNU_PROPERTY_TYPE_0
+    .long   5
+    .asciz  "GNU"
+    # GNU_PROPERTY_X86_FEATURE_1_AND: IBT | SHSTK
+    .long   0xc0000002
+    .long   4
+    .long   3
+    .p2align 3
+
+    .section .text
+    .globl _start
+_start:
+    endbr64
+    # write(1, hello, 13), hello is copied from shared library
+    mov     $1, %rdi
+    mov     $hello, %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    call    exit@PLT
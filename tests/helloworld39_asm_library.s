@@ -0,0 +1,21 @@
+# thread-local variables accessed with the initial-exec model
+    .section .text
+    .globl print
+    .type print, @function
+print:
+    # write(1, greeting, 6) from the copy of current thread
+    movq    greeting@GOTTPOFF(%rip), %rsi
+    add     %fs:0, %rsi
+    mov     $1, %rdi
+    mov     $6, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+    .section .tdata,"awT",@progbits
+greeting:
+    .ascii "Hello "
+    .globl message
+    .type message, @object
+    .size message, 7
+message:
+    .ascii "world!\n"
@@ -0,0 +1,22 @@
+# message is a thread-local variable of the shared library
+    .section .text
+    .globl _start
+_start:
+    call    print@PLT
+    # write(1, message, 7)
+    movq    message@GOTTPOFF(%rip), %rsi
+    add     %fs:0, %rsi
+    mov     $1, %rdi
+    mov     $7, %rdx
+    mov     $1, %rax
+    syscall
+    # _exit(counter - 1)
+    movq    counter@GOTTPOFF(%rip), %rax
+    movl    %fs:(%rax), %edi
+    dec     %edi
+    mov     $60, %rax
+    syscall
+    .section .tdata,"awT",@progbits
+    .p2align 3
+counter:
+    .long 1
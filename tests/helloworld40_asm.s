@@ -0,0 +1,44 @@
+# local-exec tls, the thread pointer is set up without libc
+    .section .text
+    .globl _start
+_start:
+    # arch_prctl(ARCH_SET_FS, tcb), which points to itself
+    lea     tcb(%rip), %rsi
+    mov     %rsi, (%rsi)
+    mov     $0x1002, %rdi
+    mov     $158, %rax
+    syscall
+    # copy message to the thread-local buffer
+    lea     message(%rip), %rsi
+    xor     %rcx, %rcx
+1:
+    movb    (%rsi, %rcx), %al
+    movb    %al, %fs:buffer@tpoff(%rcx)
+    inc     %rcx
+    cmp     $13, %rcx
+    jne     1b
+    # write(1, buffer, 13)
+    mov     %fs:0, %rsi
+    lea     buffer@tpoff(%rsi), %rsi
+    mov     $1, %rdi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+    .section .rodata
+message:
+    .ascii "Hello world!\n"
+    .section .tbss,"awT",@nobits
+    .p2align 4
+buffer:
+    .zero 16
+    .section .bss
+    # static tls block below the thread control block
+    .p2align 6
+    .zero 64
+tcb:
+    .zero 64
+    .section .note.GNU-stack,"",@progbits
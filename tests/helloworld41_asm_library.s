@@ -0,0 +1,35 @@
+# thread-local variables accessed via __tls_get_addr
+    .section .text
+    .globl print
+    .type print, @function
+print:
+    push    %rbx
+    # general dynamic: write(1, greeting, 6)
+    .byte   0x66
+    leaq    greeting@tlsgd(%rip), %rdi
+    .value  0x6666
+    rex64
+    call    __tls_get_addr@PLT
+    mov     %rax, %rsi
+    mov     $1, %rdi
+    mov     $6, %rdx
+    mov     $1, %rax
+    syscall
+    # local dynamic: return count
+    leaq    count@tlsld(%rip), %rdi
+    call    __tls_get_addr@PLT
+    movl    count@dtpoff(%rax), %eax
+    pop     %rbx
+    ret
+    .section .tdata,"awT",@progbits
+greeting:
+    .ascii "Hello "
+    .globl message
+    .type message, @object
+    .size message, 7
+message:
+    .ascii "world!\n"
+    .p2align 2
+count:
+    .long 1
+    .section .note.GNU-stack,"",@progbits
@@ -0,0 +1,33 @@
+# message is a thread-local variable of the shared library
+    .section .text
+    .globl _start
+_start:
+    call    print@PLT
+    mov     %eax, %ebx
+    # write(1, message, 7)
+    .byte   0x66
+    leaq    message@tlsgd(%rip), %rdi
+    .value  0x6666
+    rex64
+    call    __tls_get_addr@PLT
+    mov     %rax, %rsi
+    mov     $1, %rdi
+    mov     $7, %rdx
+    mov     $1, %rax
+    syscall
+    # _exit(status - count)
+    .byte   0x66
+    leaq    status@tlsgd(%rip), %rdi
+    .value  0x6666
+    rex64
+    call    __tls_get_addr@PLT
+    movl    (%rax), %edi
+    sub     %ebx, %edi
+    mov     $60, %rax
+    syscall
+    .section .tbss,"awT",@nobits
+    .zero 8
+    .section .tdata,"awT",@progbits
+status:
+    .long 1
+    .section .note.GNU-stack,"",@progbits
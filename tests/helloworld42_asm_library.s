@@ -0,0 +1,24 @@
+# thread-local variables accessed via tls descriptors
+    .section .text
+    .globl print
+    .type print, @function
+print:
+    # write(1, greeting, 6)
+    leaq    greeting@tlsdesc(%rip), %rax
+    call    *greeting@tlscall(%rax)
+    mov     %fs:0, %rsi
+    add     %rax, %rsi
+    mov     $1, %rdi
+    mov     $6, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+    .section .tdata,"awT",@progbits
+greeting:
+    .ascii "Hello "
+    .globl message
+    .type message, @object
+    .size message, 7
+message:
+    .ascii "world!\n"
+    .section .note.GNU-stack,"",@progbits
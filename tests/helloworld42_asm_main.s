@@ -0,0 +1,25 @@
+# message is a thread-local variable of the shared library
+    .section .text
+    .globl _start
+_start:
+    call    print@PLT
+    # write(1, message, 7)
+    leaq    message@tlsdesc(%rip), %rax
+    call    *message@tlscall(%rax)
+    mov     %fs:0, %rsi
+    add     %rax, %rsi
+    mov     $1, %rdi
+    mov     $7, %rdx
+    mov     $1, %rax
+    syscall
+    # _exit(status - 1)
+    leaq    status@tlsdesc(%rip), %rax
+    call    *status@tlscall(%rax)
+    movl    %fs:(%rax), %edi
+    dec     %edi
+    mov     $60, %rax
+    syscall
+    .section .tdata,"awT",@progbits
+status:
+    .long 1
+    .section .note.GNU-stack,"",@progbits
@@ -0,0 +1,67 @@
+# all tls models relaxed to local-exec, without __tls_get_addr
+    .section .text
+    .globl _start
+_start:
+    # arch_prctl(ARCH_SET_FS, tcb), which points to itself
+    lea     tcb(%rip), %rsi
+    mov     %rsi, (%rsi)
+    mov     $0x1002, %rdi
+    mov     $158, %rax
+    syscall
+    # general dynamic
+    .byte   0x66
+    leaq    buffer@tlsgd(%rip), %rdi
+    .value  0x6666
+    rex64
+    call    __tls_get_addr@PLT
+    mov     %rax, %r12
+    # local dynamic
+    leaq    buffer@tlsld(%rip), %rdi
+    call    __tls_get_addr@PLT
+    lea     buffer@dtpoff(%rax), %r13
+    # tls descriptor
+    leaq    buffer@tlsdesc(%rip), %rax
+    call    *buffer@tlscall(%rax)
+    add     %fs:0, %rax
+    mov     %rax, %r14
+    # initial exec
+    movq    buffer@gottpoff(%rip), %r15
+    add     %fs:0, %r15
+    # copy message to the thread-local buffer
+    lea     message(%rip), %rsi
+    xor     %rcx, %rcx
+1:
+    movb    (%rsi, %rcx), %al
+    movb    %al, (%r12, %rcx)
+    inc     %rcx
+    cmp     $13, %rcx
+    jne     1b
+    # write(1, buffer, 13)
+    mov     %r13, %rsi
+    mov     $1, %rdi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    # _exit(0) if all addresses agree
+    mov     %r14, %rdi
+    sub     %r12, %rdi
+    mov     %r15, %rax
+    sub     %r12, %rax
+    or      %rax, %rdi
+    mov     $60, %rax
+    syscall
+    .section .rodata
+message:
+    .ascii "Hello world!\n"
+    .section .tbss,"awT",@nobits
+    .p2align 4
+    .zero 16
+buffer:
+    .zero 16
+    .section .bss
+    # static tls block below the thread control block
+    .p2align 6
+    .zero 64
+tcb:
+    .zero 64
+    .section .note.GNU-stack,"",@progbits
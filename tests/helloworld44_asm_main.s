@@ -0,0 +1,23 @@
+# each function is looked up in .gnu.hash of the library
+    .section .text
+    .globl _start
+_start:
+    xor     %ebx, %ebx
+    .irp n, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39
+    call    func\n@PLT
+    add     %eax, %ebx
+    .endr
+    # write(1, message, 13)
+    mov     $1, %rdi
+    lea     message(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    # _exit(sum - 780)
+    lea     -780(%rbx), %edi
+    mov     $60, %rax
+    syscall
+    .section .rodata
+message:
+    .ascii "Hello world!\n"
+    .section .note.GNU-stack,"",@progbits
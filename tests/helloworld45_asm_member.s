@@ -0,0 +1,36 @@
+# archive member extracted to resolve write_message
+    .section .text
+    .globl write_message
+    .type write_message, @function
+write_message:
+    call    member_hidden
+    mov     member_counter@GOTPCREL(%rip), %rax
+    incq    (%rax)
+    # write(1, message, 13)
+    mov     $1, %rdi
+    lea     message(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+
+    .globl member_hidden
+    .hidden member_hidden
+    .type member_hidden, @function
+member_hidden:
+    ret
+
+    .section .data
+    .globl member_counter
+    .type member_counter, @object
+    .size member_counter, 8
+member_counter:
+    .quad 0
+
+    .comm member_common, 8, 8
+    .comm member_hidden_common, 8, 8
+    .hidden member_hidden_common
+
+    .section .rodata
+message:
+    .ascii "Hello world!\n"
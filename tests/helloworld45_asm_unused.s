@@ -0,0 +1,6 @@
+# archive member that is never extracted
+    .section .text
+    .globl unused_func
+    .type unused_func, @function
+unused_func:
+    ret
@@ -0,0 +1,128 @@
+# the address of message computed through each x86-64 relocation type
+    .section .text
+    .globl _start
+_start:
+    lea     message(%rip), %r12
+    # R_X86_64_GOTPC64: address of _GLOBAL_OFFSET_TABLE_
+1:  lea     1b(%rip), %rbx
+    movabs  $_GLOBAL_OFFSET_TABLE_-1b, %r11
+    add     %r11, %rbx
+    # R_X86_64_GOTPC32
+    lea     _GLOBAL_OFFSET_TABLE_(%rip), %rax
+    cmp     %rax, %rbx
+    jne     fail
+    # R_X86_64_GOTOFF64
+    movabs  $message@GOTOFF, %rax
+    add     %rbx, %rax
+    cmp     %rax, %r12
+    jne     fail
+    # R_X86_64_GOT32
+    movq    $message@GOT, %rax
+    mov     (%rbx,%rax), %rax
+    cmp     %rax, %r12
+    jne     fail
+    # R_X86_64_GOT64
+    movabs  $message@GOT, %rax
+    mov     (%rbx,%rax), %rax
+    cmp     %rax, %r12
+    jne     fail
+    # R_X86_64_GOTPCREL64
+    lea     gotpcrel64(%rip), %rax
+    add     (%rax), %rax
+    mov     (%rax), %rax
+    cmp     %rax, %r12
+    jne     fail
+    # R_X86_64_PC64
+    lea     pc64(%rip), %rax
+    add     (%rax), %rax
+    cmp     %rax, %r12
+    jne     fail
+    # R_X86_64_PC16
+    lea     pc16(%rip), %rax
+    movswq  (%rax), %rcx
+    add     %rcx, %rax
+    lea     target(%rip), %rcx
+    cmp     %rax, %rcx
+    jne     fail
+    # R_X86_64_PC8
+    lea     pc8(%rip), %rax
+    movsbq  (%rax), %rcx
+    add     %rcx, %rax
+    lea     target(%rip), %rcx
+    cmp     %rax, %rcx
+    jne     fail
+    # R_X86_64_PLTOFF64
+    movabs  $get_message@PLTOFF, %rax
+    add     %rbx, %rax
+    call    *%rax
+    cmp     %rax, %r12
+    jne     fail
+    # R_X86_64_SIZE32 and R_X86_64_SIZE64
+    mov     $message@SIZE, %edx
+    movabs  $message@SIZE, %rcx
+    cmp     %rcx, %rdx
+    jne     fail
+    # R_X86_64_32, R_X86_64_16 and R_X86_64_8 against absolute width
+    movzbl  width8(%rip), %ecx
+    cmp     %rcx, %rdx
+    jne     fail
+    movzwl  width16(%rip), %ecx
+    cmp     %rcx, %rdx
+    jne     fail
+    mov     width32(%rip), %ecx
+    cmp     %rcx, %rdx
+    jne     fail
+    # write(1, message, 13)
+    mov     $1, %rdi
+    mov     %r12, %rsi
+    mov     $1, %rax
+    syscall
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+fail:
+    # _exit(1)
+    mov     $1, %rdi
+    mov     $60, %rax
+    syscall
+
+    .type get_message, @function
+get_message:
+    lea     message(%rip), %rax
+    ret
+
+    .section .rodata
+    .globl message
+    .type message, @object
+    .size message, 13
+message:
+    .ascii "Hello world!\n"
+
+    .section .data.rel, "aw"
+    .balign 8
+gotpcrel64:
+    .quad   message@GOTPCREL
+pc64:
+    .quad   message - .
+width32:
+    .long   width
+width16:
+    .word   width
+width8:
+    .byte   width
+
+    .section .data.pc, "aw"
+pc16:
+    .word   target - .
+pc8:
+    .byte   target - .
+
+    .section .data.target, "aw"
+target:
+    .byte   0
+.ifdef unsupported
+    # dynamic relocation in object file
+    .reloc  ., R_X86_64_RELATIVE, target
+    .quad   0
+.endif
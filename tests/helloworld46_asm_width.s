@@ -0,0 +1,3 @@
+# absolute symbol
+    .globl width
+    .set width, 13
@@ -0,0 +1,27 @@
+# .got accesses of local symbols are relaxed to pc-relative ones
+    .section .text
+    .globl _start
+_start:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     hello@GOTPCREL(%rip), %rsi
+    mov     $13, %rdx
+    call    *write@GOTPCREL(%rip)
+    # _exit(0)
+    xor     %rdi, %rdi
+    jmp     *exit@GOTPCREL(%rip)
+
+    .type write, @function
+write:
+    mov     $1, %rax
+    syscall
+    ret
+
+    .type exit, @function
+exit:
+    mov     $60, %rax
+    syscall
+
+    .section .rodata
+hello:
+    .string "Hello world!\n"
@@ -0,0 +1,55 @@
+# large code model: data in large sections, accessed and called
+# relative to _GLOBAL_OFFSET_TABLE_ like -mcmodel=large -fPIC
+    .section .lrodata,"al",@progbits
+answer:
+    .quad   42
+
+    .section .ldata,"awl",@progbits
+    .globl  value
+value:
+    .quad   42
+
+    .section .lbss,"awl",@nobits
+zero:
+    .zero   8
+
+    # common symbol in SHN_X86_64_LCOMMON
+    .largecomm counter, 8, 8
+
+    .section .text
+    .globl _start
+_start:
+    # R_X86_64_GOTPC64: address of _GLOBAL_OFFSET_TABLE_
+1:  lea     1b(%rip), %rbx
+    movabs  $_GLOBAL_OFFSET_TABLE_-1b, %r11
+    add     %r11, %rbx
+    # R_X86_64_GOTOFF64 into .lrodata
+    movabs  $answer@GOTOFF, %rax
+    mov     (%rbx,%rax), %r12
+    # R_X86_64_GOT64 into .ldata
+    movabs  $value@GOT, %rax
+    mov     (%rbx,%rax), %rax
+    cmp     (%rax), %r12
+    jne     fail
+    # R_X86_64_GOTOFF64 into .lbss
+    movabs  $zero@GOTOFF, %rax
+    cmpq    $0, (%rbx,%rax)
+    jne     fail
+    # R_X86_64_GOT64 to the large common symbol
+    movabs  $counter@GOT, %rax
+    mov     (%rbx,%rax), %rax
+    mov     %r12, (%rax)
+    cmp     (%rax), %r12
+    jne     fail
+    # R_X86_64_PLTOFF64 to imported functions
+    movabs  $print@PLTOFF, %rax
+    add     %rbx, %rax
+    call    *%rax
+    movabs  $exit@PLTOFF, %rax
+    add     %rbx, %rax
+    call    *%rax
+fail:
+    # _exit(1)
+    mov     $1, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,72 @@
+# symbols of every binding, type and visibility, absolute and common
+    .section .rodata
+    .type hello, @object
+hello:
+    .string "Hello world!\n"
+    .size hello, .-hello
+
+    .data
+    .type local_object, @object
+local_object:
+    .quad 13
+    .size local_object, 8
+    .weak weak_object
+    .type weak_object, @object
+weak_object:
+    .long 1
+    .size weak_object, 4
+    .weak undefined_weak
+    .quad undefined_weak
+
+    .globl abs_global
+    .set abs_global, 0x1234
+    .set abs_local, 0x5678
+    .comm common_object, 32, 16
+
+    .text
+    .type local_func, @function
+local_func:
+    ret
+    .size local_func, .-local_func
+
+    .globl hidden_func
+    .hidden hidden_func
+    .type hidden_func, @function
+hidden_func:
+    ret
+    .size hidden_func, .-hidden_func
+
+    .globl internal_func
+    .internal internal_func
+    .type internal_func, @function
+internal_func:
+    ret
+    .size internal_func, .-internal_func
+
+    .globl protected_func
+    .protected protected_func
+    .type protected_func, @function
+protected_func:
+    ret
+    .size protected_func, .-protected_func
+
+    .globl _start
+    .type _start, @function
+_start:
+    call    local_func
+    call    hidden_func
+    call    internal_func
+    call    protected_func
+
+    # write(1, hello, local_object)
+    mov     $1, %rdi
+    mov     $hello, %rsi
+    mov     local_object, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+    .size _start, .-_start
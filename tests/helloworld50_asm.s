@@ -0,0 +1,36 @@
+# debug section with relocations of every width, pc-relative and size
+    .section .rodata
+    .globl message
+    .type message, @object
+message:
+    .string "Hello world!\n"
+    .size message, .-message
+
+    .section .debug_foo, "", @progbits
+    .quad   message
+    .long   message
+    .reloc  ., R_X86_64_32S, message
+    .long   0
+    .long   message - .
+    .quad   message - .
+    .long   message@SIZE
+    .quad   message@SIZE
+.ifdef unsupported
+    .reloc  ., R_X86_64_GOTPCREL, message
+    .long   0
+.endif
+
+    .text
+    .globl _start
+_start:
+    # write(1, message, 13)
+    mov     $1, %rdi
+    mov     $message, %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,25 @@
+# text relocation: a pointer stored in read-only section
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+hello_ptr:
+    .quad hello
+
+    .section .text
+    .globl print
+print:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    # load absolute address of hello, requiring R_X86_64_RELATIVE
+    mov     hello_ptr(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+
+    .globl exit
+exit:
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,32 @@
+// static pie without libc: apply R_X86_64_RELATIVE to itself
+#include <elf.h>
+
+extern const Elf64_Ehdr __ehdr_start __attribute__((visibility("hidden")));
+extern Elf64_Dyn _DYNAMIC[] __attribute__((visibility("hidden")));
+long write(int fd, const void *buf, unsigned long count);
+void _exit(int status);
+
+// absolute address, needs a dynamic relocation
+const char *message = "Hello world!\n";
+
+void _start(void) {
+  // linked at address zero
+  Elf64_Addr base = (Elf64_Addr)&__ehdr_start;
+  const Elf64_Rela *rela = 0;
+  Elf64_Xword relasz = 0;
+  for (Elf64_Dyn *dyn = _DYNAMIC; dyn->d_tag != DT_NULL; dyn++) {
+    if (dyn->d_tag == DT_RELA) {
+      rela = (const Elf64_Rela *)(base + dyn->d_un.d_ptr);
+    } else if (dyn->d_tag == DT_RELASZ) {
+      relasz = dyn->d_un.d_val;
+    }
+  }
+  for (Elf64_Xword i = 0; i < relasz / sizeof(Elf64_Rela); i++) {
+    if (ELF64_R_TYPE(rela[i].r_info) == R_X86_64_RELATIVE) {
+      *(Elf64_Addr *)(base + rela[i].r_offset) = base + rela[i].r_addend;
+    }
+  }
+
+  write(1, message, 13);
+  _exit(0);
+}
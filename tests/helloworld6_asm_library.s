@@ -0,0 +1,30 @@
+# https://gist.github.com/adrianratnapala/1321776
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+
+    .section .text
+    # hidden symbols are not exported from the shared library
+    .globl write_hello
+    .hidden write_hello
+write_hello:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+
+    .globl print
+print:
+    call    write_hello
+    ret
+
+    .globl exit
+exit:
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,32 @@
+# https://gist.github.com/adrianratnapala/1321776
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+
+    .section .text
+    # exported symbols can be preempted unless bound by -Bsymbolic
+    .globl write_hello
+    .type write_hello, @function
+write_hello:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+
+    .globl print
+    .type print, @function
+print:
+    call    write_hello@PLT
+    ret
+
+    .globl exit
+    .type exit, @function
+exit:
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
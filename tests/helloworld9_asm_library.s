@@ -0,0 +1,15 @@
+    .section .data
+    .globl hello
+    .type hello, @object
+    .size hello, 13
+hello:
+    .ascii "Hello world!\n"
+
+    .section .text
+    .globl exit
+    .type exit, @function
+exit:
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
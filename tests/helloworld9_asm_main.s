@@ -0,0 +1,10 @@
+    .section .text
+    .globl _start
+_start:
+    # write(1, hello, 13), hello is copied from shared library
+    mov     $1, %rdi
+    mov     $hello, %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    call exit
@@ -0,0 +1,10 @@
+#include <cstdio>
+#include <stdexcept>
+
+int main() {
+  try {
+    throw std::runtime_error("Hello world!");
+  } catch (const std::exception &e) {
+    printf("%s\n", e.what());
+  }
+}